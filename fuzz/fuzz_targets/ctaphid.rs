@@ -0,0 +1,15 @@
+#![no_main]
+
+use ctap_types::{
+    ctaphid::{MessageAssembler, Packet},
+    sizes::PACKET_SIZE,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut assembler = MessageAssembler::<1024>::new();
+    for report in data.chunks_exact(PACKET_SIZE) {
+        let report = report.try_into().unwrap();
+        assembler.push(&Packet::parse(report)).ok();
+    }
+});
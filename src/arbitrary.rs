@@ -1,19 +1,17 @@
 use core::{fmt::Debug, ops::ControlFlow};
 
 use arbitrary::{Arbitrary, Error, Result, Unstructured};
-use cosey::EcdhEsHkdf256PublicKey;
 use heapless::{String, Vec};
 use heapless_bytes::Bytes;
-use serde_bytes::ByteArray;
 
-use crate::{ctap1, ctap2, webauthn};
+use crate::{cose, ctap1, ctap2, webauthn};
 
-// cannot be derived because of missing impl for &[T; N]
+// cannot be derived because of missing impl for &ClientDataHash and &RpIdHash
 impl<'a> Arbitrary<'a> for ctap1::authenticate::Request<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let control_byte = Arbitrary::arbitrary(u)?;
-        let challenge = u.bytes(32)?.try_into().unwrap();
-        let app_id = u.bytes(32)?.try_into().unwrap();
+        let challenge = arbitrary_hash(u)?;
+        let app_id = arbitrary_hash(u)?;
         let key_handle = Arbitrary::arbitrary(u)?;
         Ok(Self {
             control_byte,
@@ -24,11 +22,11 @@ impl<'a> Arbitrary<'a> for ctap1::authenticate::Request<'a> {
     }
 }
 
-// cannot be derived because of missing impl for &[T; N]
+// cannot be derived because of missing impl for &ClientDataHash and &RpIdHash
 impl<'a> Arbitrary<'a> for ctap1::register::Request<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        let challenge = u.bytes(32)?.try_into().unwrap();
-        let app_id = u.bytes(32)?.try_into().unwrap();
+        let challenge = arbitrary_hash(u)?;
+        let app_id = arbitrary_hash(u)?;
         Ok(Self { challenge, app_id })
     }
 }
@@ -36,16 +34,12 @@ impl<'a> Arbitrary<'a> for ctap1::register::Request<'a> {
 // cannot be derived because of missing impl for Vec<_>
 impl<'a> Arbitrary<'a> for ctap2::AttestationFormatsPreference {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        let known_formats = arbitrary_vec(u)?;
-        let unknown = u.arbitrary()?;
-        Ok(Self {
-            known_formats,
-            unknown,
-        })
+        let formats = arbitrary_vec(u)?;
+        Ok(Self { formats })
     }
 }
 
-// cannot be derived because of missing impl for serde_bytes::Bytes, EcdhEsHkdf256PublicKey
+// cannot be derived because of missing impl for serde_bytes::Bytes, EcdhEsHkdf256PublicKeyRef
 impl<'a> Arbitrary<'a> for ctap2::client_pin::Request<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let pin_protocol = u.arbitrary()?;
@@ -68,7 +62,11 @@ impl<'a> Arbitrary<'a> for ctap2::client_pin::Request<'a> {
         };
         let _placeholder07 = u.arbitrary()?;
         let _placeholder08 = u.arbitrary()?;
-        let permissions = u.arbitrary()?;
+        let permissions = arbitrary_option(u, |u| {
+            Ok(ctap2::client_pin::Permissions::from_bits_retain(
+                u.arbitrary()?,
+            ))
+        })?;
         let rp_id = u.arbitrary()?;
         Ok(Self {
             pin_protocol,
@@ -96,19 +94,19 @@ impl<'a> Arbitrary<'a> for ctap2::credential_management::Request<'a> {
         } else {
             None
         };
-        Ok(Self {
+        Ok(Self::new(
             sub_command,
             sub_command_params,
             pin_protocol,
             pin_auth,
-        })
+        ))
     }
 }
 
-// cannot be derived because of missing impl for serde_bytes::ByteArray
+// cannot be derived because of missing impl for &RpIdHash
 impl<'a> Arbitrary<'a> for ctap2::credential_management::SubcommandParameters<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        let rp_id_hash = arbitrary_option(u, arbitrary_byte_array)?;
+        let rp_id_hash = arbitrary_option(u, arbitrary_hash)?;
         let credential_id = u.arbitrary()?;
         let user = u.arbitrary()?;
         Ok(Self {
@@ -119,11 +117,13 @@ impl<'a> Arbitrary<'a> for ctap2::credential_management::SubcommandParameters<'a
     }
 }
 
-// cannot be derived because of missing impl for EcdhEsHkdf256PublicKey, Bytes<_>
-impl<'a> Arbitrary<'a> for ctap2::get_assertion::HmacSecretInput {
+// cannot be derived because of missing impl for EcdhEsHkdf256PublicKeyRef, Bytes<_>
+impl<'a> Arbitrary<'a> for ctap2::get_assertion::HmacSecretInput<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let key_agreement = arbitrary_key(u)?;
-        let salt_enc = arbitrary_bytes(u)?;
+        let salt_len = *u.choose(&[32, 48, 64, 80])?;
+        let salt_enc = ctap2::get_assertion::EncryptedSalts::from_slice(u.bytes(salt_len)?)
+            .map_err(|_| Error::IncorrectFormat)?;
         let salt_auth = arbitrary_bytes(u)?;
         let pin_protocol = u.arbitrary()?;
         Ok(Self {
@@ -135,12 +135,12 @@ impl<'a> Arbitrary<'a> for ctap2::get_assertion::HmacSecretInput {
     }
 }
 
-// cannot be derived because of missing impl for serde_bytes::Bytes, Vec<_>
+// cannot be derived because of missing impl for serde_bytes::Bytes
 impl<'a> Arbitrary<'a> for ctap2::get_assertion::Request<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let rp_id = u.arbitrary()?;
         let client_data_hash = serde_bytes::Bytes::new(u.arbitrary()?);
-        let allow_list = arbitrary_option(u, arbitrary_vec)?;
+        let allow_list = u.arbitrary()?;
         let extensions = u.arbitrary()?;
         let options = u.arbitrary()?;
         let pin_auth = if bool::arbitrary(u)? {
@@ -193,6 +193,32 @@ impl<'a> Arbitrary<'a> for ctap2::large_blobs::Request<'a> {
     }
 }
 
+// cannot be derived because of missing impl for serde_bytes::Bytes
+impl<'a> Arbitrary<'a> for ctap2::make_credential::Extensions<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let cred_blob = if bool::arbitrary(u)? {
+            Some(serde_bytes::Bytes::new(u.arbitrary()?))
+        } else {
+            None
+        };
+        let cred_protect = u.arbitrary()?;
+        let hmac_secret = u.arbitrary()?;
+        let hmac_secret_mc = u.arbitrary()?;
+        let large_blob_key = u.arbitrary()?;
+        #[cfg(feature = "third-party-payment")]
+        let third_party_payment = u.arbitrary()?;
+        Ok(Self {
+            cred_blob,
+            cred_protect,
+            hmac_secret,
+            hmac_secret_mc,
+            large_blob_key,
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment,
+        })
+    }
+}
+
 // cannot be derived because of missing impl for serde_bytes::Bytes
 impl<'a> Arbitrary<'a> for ctap2::make_credential::Request<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
@@ -200,7 +226,7 @@ impl<'a> Arbitrary<'a> for ctap2::make_credential::Request<'a> {
         let rp = u.arbitrary()?;
         let user = u.arbitrary()?;
         let pub_key_cred_params = u.arbitrary()?;
-        let exclude_list = arbitrary_option(u, arbitrary_vec)?;
+        let exclude_list = u.arbitrary()?;
         let extensions = u.arbitrary()?;
         let options = u.arbitrary()?;
         let pin_auth = if bool::arbitrary(u)? {
@@ -231,7 +257,13 @@ impl<'a> Arbitrary<'a> for ctap2::make_credential::Request<'a> {
 impl<'a> Arbitrary<'a> for webauthn::FilteredPublicKeyCredentialParameters {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let parameters = arbitrary_vec(u)?;
-        Ok(Self(parameters))
+        let had_unknown = u.arbitrary()?;
+        let truncated = u.arbitrary()?;
+        Ok(Self {
+            parameters,
+            had_unknown,
+            truncated,
+        })
     }
 }
 
@@ -243,17 +275,22 @@ impl<'a> Arbitrary<'a> for webauthn::KnownPublicKeyCredentialParameters {
     }
 }
 
-// cannot be derived because of missing impl for serde_bytes::Bytes
+// cannot be derived because of missing impl for CredentialIdRef and Vec<_>
 impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialDescriptorRef<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        let id = serde_bytes::Bytes::new(u.arbitrary()?);
+        let id = webauthn::CredentialIdRef::new(u.arbitrary()?);
         let key_type = u.arbitrary()?;
-        Ok(Self { id, key_type })
+        let transports = arbitrary_option(u, arbitrary_vec)?;
+        Ok(Self {
+            id,
+            key_type,
+            transports,
+        })
     }
 }
 
 // cannot be derived because of missing impl for String<_>
-impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialRpEntity {
+impl<'a, const N: usize> Arbitrary<'a> for webauthn::PublicKeyCredentialRpEntity<N> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
         let id = arbitrary_str(u)?;
         let name = if bool::arbitrary(u)? {
@@ -266,10 +303,31 @@ impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialRpEntity {
     }
 }
 
-// cannot be derived because of missing impl for Bytes<_> and String<_>
-impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialUserEntity {
+// cannot be derived because we want to make sure that the user handle is valid
+impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialUserEntityRef<'a> {
     fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
-        let id = arbitrary_bytes(u)?;
+        let id_len = u.int_in_range(1..=64)?;
+        let id = serde_bytes::Bytes::new(u.bytes(id_len)?);
+        let icon = u.arbitrary()?;
+        let name = u.arbitrary()?;
+        let display_name = u.arbitrary()?;
+        Ok(Self {
+            id,
+            icon,
+            name,
+            display_name,
+        })
+    }
+}
+
+// cannot be derived because of missing impl for UserHandle and String<_>
+impl<'a, const N: usize, const I: usize> Arbitrary<'a>
+    for webauthn::PublicKeyCredentialUserEntity<N, I>
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let id_len = u.int_in_range(1..=64)?;
+        let id = webauthn::UserHandle::from_slice(u.bytes(id_len)?)
+            .map_err(|_| Error::IncorrectFormat)?;
         let icon = if bool::arbitrary(u)? {
             Some(arbitrary_str(u)?)
         } else {
@@ -294,10 +352,12 @@ impl<'a> Arbitrary<'a> for webauthn::PublicKeyCredentialUserEntity {
     }
 }
 
-fn arbitrary_byte_array<'a, const N: usize>(u: &mut Unstructured<'_>) -> Result<&'a ByteArray<N>> {
-    let bytes: &[u8; N] = u.bytes(N)?.try_into().unwrap();
-    // TODO: conversion should be provided by serde_bytes
-    Ok(unsafe { &*(bytes as *const [u8; N] as *const ByteArray<N>) })
+fn arbitrary_hash<'a, T>(u: &mut Unstructured<'a>) -> Result<&'a T>
+where
+    &'a T: From<&'a [u8; 32]>,
+{
+    let bytes: &[u8; 32] = u.bytes(32)?.try_into().unwrap();
+    Ok(bytes.into())
 }
 
 fn arbitrary_bytes<const N: usize>(u: &mut Unstructured<'_>) -> Result<Bytes<N>> {
@@ -343,8 +403,8 @@ where
     }
 }
 
-fn arbitrary_key(u: &mut Unstructured<'_>) -> Result<EcdhEsHkdf256PublicKey> {
-    let x = arbitrary_bytes(u)?;
-    let y = arbitrary_bytes(u)?;
-    Ok(EcdhEsHkdf256PublicKey { x, y })
+fn arbitrary_key<'a>(u: &mut Unstructured<'a>) -> Result<cose::EcdhEsHkdf256PublicKeyRef<'a>> {
+    let x = serde_bytes::Bytes::new(u.bytes(32)?);
+    let y = serde_bytes::Bytes::new(u.bytes(32)?);
+    Ok(cose::EcdhEsHkdf256PublicKeyRef { x, y })
 }
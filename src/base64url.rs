@@ -0,0 +1,110 @@
+//! Helpers for the unpadded base64url encoding used in the WebAuthn JSON representation, see
+//! https://www.rfc-editor.org/rfc/rfc4648#section-5
+//!
+//! Credential IDs and user handles provide convenience methods based on these helpers, e.g.
+//! [`CredentialId::to_base64url`][crate::webauthn::CredentialId::to_base64url].
+
+use crate::{Bytes, Error, Result, String};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Returns the length of the unpadded base64url encoding of `n` bytes.
+pub const fn encoded_len(n: usize) -> usize {
+    (n * 4).div_ceil(3)
+}
+
+/// Encodes the given data without padding.
+///
+/// Returns [`Error::InvalidLength`][] if the encoded data does not fit into `N` bytes.
+pub fn encode<const N: usize>(data: &[u8]) -> Result<String<N>> {
+    if encoded_len(data.len()) > N {
+        return Err(Error::InvalidLength);
+    }
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let mut block = [0; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for i in 0..=chunk.len() {
+            let index = (n >> (18 - 6 * i)) & 0x3f;
+            // cannot fail as we checked the encoded length
+            encoded.push(char::from(ALPHABET[index as usize])).ok();
+        }
+    }
+    Ok(encoded)
+}
+
+/// Decodes the given unpadded base64url data.
+///
+/// Returns [`Error::InvalidParameter`][] if the data is not valid unpadded base64url, and
+/// [`Error::InvalidLength`][] if the decoded data does not fit into `N` bytes.
+pub fn decode<const N: usize>(s: &str) -> Result<Bytes<N>> {
+    if s.len() % 4 == 1 {
+        return Err(Error::InvalidParameter);
+    }
+    if s.len() * 3 / 4 > N {
+        return Err(Error::InvalidLength);
+    }
+    let mut decoded = Bytes::new();
+    for chunk in s.as_bytes().chunks(4) {
+        let mut n = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            n |= u32::from(decode_char(*c)?) << (18 - 6 * i);
+        }
+        let block = n.to_be_bytes();
+        let len = chunk.len() - 1;
+        // the unused bits of the last character must be zero
+        if block[1 + len..].iter().any(|b| *b != 0) {
+            return Err(Error::InvalidParameter);
+        }
+        // cannot fail as we checked the decoded length
+        decoded.extend_from_slice(&block[1..1 + len]).ok();
+    }
+    Ok(decoded)
+}
+
+fn decode_char(c: u8) -> Result<u8> {
+    match c {
+        b'A'..=b'Z' => Ok(c - b'A'),
+        b'a'..=b'z' => Ok(c - b'a' + 26),
+        b'0'..=b'9' => Ok(c - b'0' + 52),
+        b'-' => Ok(62),
+        b'_' => Ok(63),
+        _ => Err(Error::InvalidParameter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let vectors: &[(&[u8], &str)] = &[
+            (b"", ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (b"fooba", "Zm9vYmE"),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff, 0xfe], "-__-"),
+        ];
+        for (data, encoded) in vectors {
+            assert_eq!(encoded_len(data.len()), encoded.len());
+            assert_eq!(encode::<8>(data).unwrap(), *encoded);
+            assert_eq!(&decode::<6>(encoded).unwrap()[..], *data);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(encode::<3>(b"foo"), Err(Error::InvalidLength));
+        assert_eq!(decode::<2>("Zm9v"), Err(Error::InvalidLength));
+        assert_eq!(decode::<8>("Zm9vY"), Err(Error::InvalidParameter));
+        assert_eq!(decode::<8>("Zm9vYg=="), Err(Error::InvalidParameter));
+        assert_eq!(decode::<8>("Zm+v"), Err(Error::InvalidParameter));
+        // non-zero trailing bits
+        assert_eq!(decode::<8>("Zh"), Err(Error::InvalidParameter));
+    }
+}
@@ -0,0 +1,387 @@
+//! Helpers for working with raw CBOR data.
+//!
+//! Some structures, for example the authenticator data, embed CBOR data items without a length
+//! prefix.  To split them, we need to determine the length of a CBOR data item without
+//! deserializing it.
+
+/// Limits the nesting of arrays, maps and tags to bound the recursion depth.
+const MAX_DEPTH: usize = 16;
+
+/// Parses the head of the CBOR data item at the start of `data`.
+///
+/// Returns the major type, the argument and the length of the head.  Indefinite-length items
+/// and reserved values are not supported.
+pub(crate) fn head(data: &[u8]) -> Option<(u8, u64, usize)> {
+    let (&initial, rest) = data.split_first()?;
+    let major = initial >> 5;
+    let (argument, len) = match initial & 0x1f {
+        info @ 0..=23 => (u64::from(info), 1),
+        24 => (u64::from(*rest.first()?), 2),
+        25 => (
+            u16::from_be_bytes(rest.get(..2)?.try_into().ok()?).into(),
+            3,
+        ),
+        26 => (
+            u32::from_be_bytes(rest.get(..4)?.try_into().ok()?).into(),
+            5,
+        ),
+        27 => (u64::from_be_bytes(rest.get(..8)?.try_into().ok()?), 9),
+        _ => return None,
+    };
+    Some((major, argument, len))
+}
+
+/// Returns the length of the CBOR data item at the start of `data`, or `None` if the data item is
+/// malformed or truncated.
+pub(crate) fn item_len(data: &[u8]) -> Option<usize> {
+    item_len_nested(data, 0, false)
+}
+
+/// Checks whether `data` consists of exactly one CBOR data item in the CTAP2 canonical encoding.
+///
+/// This requires that integers and lengths are encoded as short as possible and that map keys
+/// are unique and sorted by their length first and then by their encoding.
+pub(crate) fn is_canonical(data: &[u8]) -> bool {
+    item_len_nested(data, 0, true) == Some(data.len())
+}
+
+/// A destination for CBOR data, e.g. a buffer or a hasher.
+pub(crate) trait Sink {
+    /// Appends `data`.
+    ///
+    /// Returns [`Error::Other`][crate::Error::Other] if there is not enough space.
+    fn write(&mut self, data: &[u8]) -> crate::Result<()>;
+}
+
+impl<const N: usize> Sink for crate::Bytes<N> {
+    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
+        self.extend_from_slice(data)
+            .map_err(|_| crate::Error::Other)
+    }
+}
+
+impl<const N: usize> Sink for heapless::Vec<u8, N> {
+    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
+        self.extend_from_slice(data)
+            .map_err(|_| crate::Error::Other)
+    }
+}
+
+#[cfg(feature = "sha2")]
+impl Sink for sha2::Sha256 {
+    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
+        sha2::Digest::update(self, data);
+        Ok(())
+    }
+}
+
+/// Appends a CBOR head with the given major type and argument in the shortest encoding.
+///
+/// Returns [`Error::Other`][crate::Error::Other] if the sink is full.
+pub(crate) fn write_head(sink: &mut impl Sink, major: u8, argument: u64) -> crate::Result<()> {
+    let bytes = argument.to_be_bytes();
+    let (info, argument) = match minimal_head_len(argument) {
+        1 => (bytes[7], &[][..]),
+        2 => (24, &bytes[7..]),
+        3 => (25, &bytes[6..]),
+        5 => (26, &bytes[4..]),
+        _ => (27, &bytes[..]),
+    };
+    sink.write(&[(major << 5) | info])?;
+    sink.write(argument)
+}
+
+/// Appends a CBOR integer in the shortest encoding.
+#[cfg(feature = "sha2")]
+pub(crate) fn write_integer(sink: &mut impl Sink, value: i64) -> crate::Result<()> {
+    if value < 0 {
+        write_head(sink, 1, value.unsigned_abs() - 1)
+    } else {
+        write_head(sink, 0, value.unsigned_abs())
+    }
+}
+
+fn minimal_head_len(argument: u64) -> usize {
+    match argument {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+fn item_len_nested(data: &[u8], depth: usize, canonical: bool) -> Option<usize> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let (major, argument, head_len) = head(data)?;
+    if canonical && major != 7 && head_len != minimal_head_len(argument) {
+        return None;
+    }
+    let len = match major {
+        // unsigned and negative integers, simple values and floats
+        0 | 1 | 7 => head_len,
+        // byte and text strings
+        2 | 3 => head_len.checked_add(usize::try_from(argument).ok()?)?,
+        // arrays
+        4 => {
+            let mut len = head_len;
+            for _ in 0..argument {
+                len += item_len_nested(data.get(len..)?, depth + 1, canonical)?;
+            }
+            len
+        }
+        // maps
+        5 => {
+            let mut len = head_len;
+            let mut previous_key: Option<&[u8]> = None;
+            for _ in 0..argument {
+                let key_len = item_len_nested(data.get(len..)?, depth + 1, canonical)?;
+                let key = &data[len..len + key_len];
+                if canonical {
+                    if let Some(previous_key) = previous_key {
+                        if (previous_key.len(), previous_key) >= (key.len(), key) {
+                            return None;
+                        }
+                    }
+                    previous_key = Some(key);
+                }
+                len += key_len;
+                len += item_len_nested(data.get(len..)?, depth + 1, canonical)?;
+            }
+            len
+        }
+        // tags
+        _ => head_len + item_len_nested(data.get(head_len..)?, depth + 1, canonical)?,
+    };
+    if len <= data.len() {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// Checks whether the CBOR map at the start of `data` contains the given unsigned integer key.
+///
+/// Returns `None` if `data` does not start with a well-formed map.
+pub(crate) fn map_contains_key(data: &[u8], key: u64) -> Option<bool> {
+    map_value(data, key).map(|value| value.is_some())
+}
+
+/// Returns the value for the given unsigned integer key in the CBOR map at the start of `data`.
+///
+/// Returns `None` if `data` does not start with a well-formed map and `Some(None)` if the map
+/// does not contain the key.
+pub(crate) fn map_value(data: &[u8], key: u64) -> Option<Option<&[u8]>> {
+    map_find(
+        data,
+        |entry| matches!(head(entry), Some((0, argument, _)) if argument == key),
+    )
+}
+
+/// Returns the value for the given text string key in the CBOR map at the start of `data`.
+///
+/// Returns `None` if `data` does not start with a well-formed map and `Some(None)` if the map
+/// does not contain the key.
+pub(crate) fn map_text_value<'a>(data: &'a [u8], key: &str) -> Option<Option<&'a [u8]>> {
+    map_find(data, |entry| match head(entry) {
+        Some((3, _, head_len)) => &entry[head_len..] == key.as_bytes(),
+        _ => false,
+    })
+}
+
+fn map_find(data: &[u8], is_key: impl Fn(&[u8]) -> bool) -> Option<Option<&[u8]>> {
+    let (major, count, mut offset) = head(data)?;
+    if major != 5 {
+        return None;
+    }
+    let mut found = None;
+    for _ in 0..count {
+        let key_len = item_len(data.get(offset..)?)?;
+        let key = &data[offset..offset + key_len];
+        offset += key_len;
+        let value_len = item_len(data.get(offset..)?)?;
+        if is_key(key) {
+            found = Some(&data[offset..offset + value_len]);
+        }
+        offset += value_len;
+    }
+    Some(found)
+}
+
+/// An iterator over the raw data items of a well-formed CBOR array.
+#[derive(Clone, Debug)]
+pub(crate) struct ArrayIter<'a> {
+    data: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> ArrayIter<'a> {
+    /// Returns `None` if `data` does not start with a well-formed array.
+    pub(crate) fn new(data: &'a [u8]) -> Option<Self> {
+        let (major, count, head_len) = head(data)?;
+        if major != 4 {
+            return None;
+        }
+        let len = item_len(data)?;
+        Some(Self {
+            data: &data[head_len..len],
+            remaining: usize::try_from(count).ok()?,
+        })
+    }
+
+    /// Creates an iterator without any items.
+    pub(crate) fn empty() -> Self {
+        Self {
+            data: &[],
+            remaining: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // the array has been validated in new
+        let len = item_len(self.data)?;
+        let (item, rest) = self.data.split_at(len);
+        self.data = rest;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for ArrayIter<'_> {}
+
+/// Returns the length of the CBOR serialization of `value` without serializing it into a buffer.
+pub(crate) fn serialized_len<T: serde::Serialize + ?Sized>(value: &T) -> crate::Result<usize> {
+    let mut serializer = cbor_smol::ser::Serializer::new(LengthCounter(0));
+    value
+        .serialize(&mut serializer)
+        .map_err(|_| crate::Error::Other)?;
+    Ok(serializer.into_inner().0)
+}
+
+/// A CBOR writer that only counts the bytes written to it.
+struct LengthCounter(usize);
+
+impl cbor_smol::ser::Writer for LengthCounter {
+    type Error = cbor_smol::Error;
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.0 += buf.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_item_len() {
+        let items: &[&[u8]] = &[
+            &hex!("00"),
+            &hex!("1818"),
+            &hex!("3903e7"),
+            &hex!("f5"),
+            &hex!("fb3ff199999999999a"),
+            &hex!("4401020304"),
+            &hex!("6449455446"),
+            &hex!("83010203"),
+            &hex!("a26161016162820203"),
+            &hex!("c11a514b67b0"),
+        ];
+        for item in items {
+            assert_eq!(item_len(item), Some(item.len()));
+            let mut data = item.to_vec();
+            data.extend_from_slice(&hex!("a0"));
+            assert_eq!(item_len(&data), Some(item.len()));
+            assert_eq!(item_len(&item[..item.len() - 1]), None);
+        }
+        // indefinite length
+        assert_eq!(item_len(&hex!("9f01ff")), None);
+        assert_eq!(item_len(&[]), None);
+    }
+
+    #[test]
+    fn test_map_contains_key() {
+        let map = hex!("a2010203a0");
+        assert_eq!(map_contains_key(&map, 1), Some(true));
+        assert_eq!(map_contains_key(&map, 3), Some(true));
+        assert_eq!(map_contains_key(&map, 2), Some(false));
+        assert_eq!(map_contains_key(&hex!("a2010203"), 1), None);
+        assert_eq!(map_contains_key(&hex!("8101"), 1), None);
+    }
+
+    #[test]
+    fn test_map_value() {
+        let map = hex!("a2018201020343616263");
+        assert_eq!(map_value(&map, 1), Some(Some(hex!("820102").as_slice())));
+        assert_eq!(map_value(&map, 3), Some(Some(hex!("43616263").as_slice())));
+        assert_eq!(map_value(&map, 2), Some(None));
+        assert_eq!(map_value(&map[..map.len() - 1], 1), None);
+    }
+
+    #[test]
+    fn test_array_iter() {
+        let array = hex!("8301820203a0");
+        let mut iter = ArrayIter::new(&array).unwrap();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(hex!("01").as_slice()));
+        assert_eq!(iter.next(), Some(hex!("820203").as_slice()));
+        assert_eq!(iter.next(), Some(hex!("a0").as_slice()));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(ArrayIter::empty().next(), None);
+        assert!(ArrayIter::new(&array[..array.len() - 1]).is_none());
+        assert!(ArrayIter::new(&hex!("a0")).is_none());
+    }
+
+    #[test]
+    fn test_write_head() {
+        let items: &[(u8, u64, &[u8])] = &[
+            (0, 0, &hex!("00")),
+            (0, 23, &hex!("17")),
+            (0, 24, &hex!("1818")),
+            (1, 999, &hex!("3903e7")),
+            (2, 0x1_0000, &hex!("5a00010000")),
+            (4, 0x1_0000_0000, &hex!("9b0000000100000000")),
+        ];
+        for (major, argument, expected) in items {
+            let mut buffer = crate::Bytes::<9>::new();
+            write_head(&mut buffer, *major, *argument).unwrap();
+            assert_eq!(buffer.as_slice(), *expected);
+            assert_eq!(head(&buffer), Some((*major, *argument, expected.len())));
+        }
+        let mut buffer = crate::Bytes::<1>::new();
+        assert_eq!(write_head(&mut buffer, 0, 24), Err(crate::Error::Other));
+    }
+
+    #[test]
+    fn test_is_canonical() {
+        assert!(is_canonical(&hex!("a30118180361616162f5")));
+        assert!(is_canonical(&hex!("a2616101626161f4")));
+        // trailing data
+        assert!(!is_canonical(&hex!("a0a0")));
+        // wrong key order
+        assert!(!is_canonical(&hex!("a203010102")));
+        assert!(!is_canonical(&hex!("a262626101616102")));
+        // duplicate keys
+        assert!(!is_canonical(&hex!("a201010102")));
+        // non-minimal integer and length encoding
+        assert!(!is_canonical(&hex!("1801")));
+        assert!(!is_canonical(&hex!("a1180101")));
+        assert!(!is_canonical(&hex!("780161")));
+    }
+}
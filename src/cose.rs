@@ -0,0 +1,2088 @@
+//! COSE key types.
+//!
+//! The P-256, ECDH-ES+HKDF-256 and Ed25519 public keys are re-exported from `cosey` so that this
+//! module provides a single set of key types for the whole crate.  [`PublicKey`][] is a superset of
+//! [`cosey::PublicKey`][] and can be converted from and into it.
+//!
+//! See [RFC 8152](https://www.rfc-editor.org/rfc/rfc8152) for the key parameters.
+//! The identifiers for the key parameters, key types, algorithms and curves are available in the
+//! [`iana`][] module.
+//!
+//! Signed COSE structures can be parsed and serialized with [`Sign1`][].
+
+use core::fmt::{self, Formatter};
+
+use serde::{
+    de::{Error as _, MapAccess, SeqAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{Bytes, Error, String, Vec};
+
+pub mod iana;
+mod sign1;
+
+pub use cosey::{EcdhEsHkdf256PublicKey, Ed25519PublicKey, P256PublicKey};
+pub use sign1::Sign1;
+
+use iana::{Alg, Crv, Kty, Label};
+
+const KTY: i8 = Label::Kty as i8;
+const KID: i8 = Label::Kid as i8;
+const ALG: i8 = Label::Alg as i8;
+const KEY_OPS: i8 = Label::KeyOps as i8;
+const CRV: i8 = Label::Crv as i8;
+const X: i8 = Label::X as i8;
+const Y: i8 = Label::Y as i8;
+const D: i8 = Label::D as i8;
+
+const KTY_OKP: i8 = Kty::Okp as i8;
+const KTY_EC2: i8 = Kty::Ec2 as i8;
+const KTY_RSA: i8 = Kty::Rsa as i8;
+const KTY_AKP: i8 = Kty::Akp as i8;
+
+const ALG_ES256: i16 = Alg::Es256 as i16;
+const ALG_EDDSA: i16 = Alg::EdDsa as i16;
+const ALG_ECDH_ES_HKDF_256: i16 = Alg::EcdhEsHkdf256 as i16;
+const ALG_ES384: i16 = Alg::Es384 as i16;
+const ALG_ES512: i16 = Alg::Es512 as i16;
+const ALG_ES256K: i16 = Alg::Es256K as i16;
+const ALG_RS256: i16 = Alg::Rs256 as i16;
+#[cfg(feature = "ml-dsa-signatures")]
+const ALG_ML_DSA_44: i16 = Alg::MlDsa44 as i16;
+#[cfg(feature = "ml-dsa-signatures")]
+const ALG_ML_DSA_65: i16 = Alg::MlDsa65 as i16;
+
+const CRV_P256: i8 = Crv::P256 as i8;
+const CRV_P384: i8 = Crv::P384 as i8;
+const CRV_P521: i8 = Crv::P521 as i8;
+const CRV_X25519: i8 = Crv::X25519 as i8;
+const CRV_ED25519: i8 = Crv::Ed25519 as i8;
+const CRV_ED448: i8 = Crv::Ed448 as i8;
+const CRV_SECP256K1: i8 = Crv::Secp256k1 as i8;
+
+/// Max length of the `kid` parameter of a [`PublicKeyWithParameters`][].
+pub const MAX_KID_LENGTH: usize = 64;
+/// Max number of entries of the `key_ops` parameter of a [`PublicKeyWithParameters`][].
+pub const MAX_KEY_OPS_LENGTH: usize = 10;
+
+/// A borrowed version of [`EcdhEsHkdf256PublicKey`][].
+///
+/// This is used in requests to avoid copying the coordinates out of the request buffer.  The
+/// encoding is the same as for the owned type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EcdhEsHkdf256PublicKeyRef<'a> {
+    pub x: &'a serde_bytes::Bytes,
+    pub y: &'a serde_bytes::Bytes,
+}
+
+impl EcdhEsHkdf256PublicKeyRef<'_> {
+    /// Copies the coordinates into an owned key.
+    ///
+    /// Returns `None` if a coordinate is longer than 32 bytes.
+    pub fn try_to_owned(&self) -> Option<EcdhEsHkdf256PublicKey> {
+        Some(EcdhEsHkdf256PublicKey {
+            x: Bytes::from_slice(self.x).ok()?,
+            y: Bytes::from_slice(self.y).ok()?,
+        })
+    }
+}
+
+impl<'a> From<&'a EcdhEsHkdf256PublicKey> for EcdhEsHkdf256PublicKeyRef<'a> {
+    fn from(key: &'a EcdhEsHkdf256PublicKey) -> Self {
+        Self {
+            x: serde_bytes::Bytes::new(&key.x),
+            y: serde_bytes::Bytes::new(&key.y),
+        }
+    }
+}
+
+impl Serialize for EcdhEsHkdf256PublicKeyRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_ec2(serializer, ALG_ECDH_ES_HKDF_256, CRV_P256, self.x, self.y)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for EcdhEsHkdf256PublicKeyRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let key = RawPublicKey::deserialize(deserializer)?;
+        if key.kty != Some(KTY_EC2) {
+            return Err(D::Error::custom("unexpected kty"));
+        }
+        if key.alg.is_some_and(|alg| alg != ALG_ECDH_ES_HKDF_256) {
+            return Err(D::Error::custom("unexpected alg"));
+        }
+        if key.crv != Some(CRV_P256) {
+            return Err(D::Error::custom("unexpected crv"));
+        }
+        Ok(Self {
+            x: key.x.ok_or_else(|| D::Error::missing_field("x"))?,
+            y: key.y.ok_or_else(|| D::Error::missing_field("y"))?,
+        })
+    }
+}
+
+macro_rules! ec2_public_key {
+    (
+        $(#[$outer:meta])*
+        $name:ident, $variant:ident, $alg:expr, $crv:expr, $len:literal
+    ) => {
+        $(#[$outer])*
+        ///
+        /// The parameters are serialized in the canonical CTAP2 order like
+        /// [`cosey::P256PublicKey`][].  When deserializing, the `alg` and `crv` parameters must
+        #[doc = concat!("match and the coordinates must have exactly ", $len, " bytes.")]
+        #[derive(Clone, Debug, Default, Eq, PartialEq)]
+        pub struct $name {
+            pub x: Bytes<$len>,
+            pub y: Bytes<$len>,
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serialize_ec2(serializer, $alg, $crv, &self.x, &self.y)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let key = RawPublicKey::deserialize(deserializer)?;
+                key.check(KTY_EC2, $alg, $crv)?;
+                Ok(Self {
+                    x: exact_coordinate(key.x, "x")?,
+                    y: exact_coordinate(key.y, "y")?,
+                })
+            }
+        }
+
+        impl From<$name> for PublicKey {
+            fn from(key: $name) -> Self {
+                Self::$variant(key)
+            }
+        }
+    };
+}
+
+ec2_public_key! {
+    /// An ECDSA public key on the P-384 curve with the ES384 algorithm.
+    P384PublicKey, P384Key, ALG_ES384, CRV_P384, 48
+}
+
+ec2_public_key! {
+    /// An ECDSA public key on the P-521 curve with the ES512 algorithm.
+    P521PublicKey, P521Key, ALG_ES512, CRV_P521, 66
+}
+
+ec2_public_key! {
+    /// An ECDSA public key on the secp256k1 curve with the ES256K algorithm.
+    Secp256k1PublicKey, Secp256k1Key, ALG_ES256K, CRV_SECP256K1, 32
+}
+
+macro_rules! okp_public_key {
+    (
+        $(#[$outer:meta])*
+        $name:ident, $variant:ident, $alg:expr, $crv:expr, $len:literal
+    ) => {
+        $(#[$outer])*
+        ///
+        /// The parameters are serialized in the canonical CTAP2 order like
+        /// [`cosey::Ed25519PublicKey`][].  When deserializing, the `alg` and `crv` parameters must
+        #[doc = concat!("match and the public key must have exactly ", $len, " bytes.")]
+        #[derive(Clone, Debug, Default, Eq, PartialEq)]
+        pub struct $name {
+            pub x: Bytes<$len>,
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serialize_okp(serializer, $alg, $crv, &self.x)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let key = RawPublicKey::deserialize(deserializer)?;
+                key.check(KTY_OKP, $alg, $crv)?;
+                if key.y.is_some() {
+                    return Err(D::Error::custom("unexpected y coordinate"));
+                }
+                Ok(Self {
+                    x: exact_coordinate(key.x, "x")?,
+                })
+            }
+        }
+
+        impl From<$name> for PublicKey {
+            fn from(key: $name) -> Self {
+                Self::$variant(key)
+            }
+        }
+    };
+}
+
+okp_public_key! {
+    /// An X25519 public key with the ECDH-ES+HKDF-256 algorithm.
+    ///
+    /// This is the serializable counterpart of [`cosey::X25519PublicKey`][].
+    X25519PublicKey, X25519Key, ALG_ECDH_ES_HKDF_256, CRV_X25519, 32
+}
+
+okp_public_key! {
+    /// An Ed448 public key with the EdDSA algorithm.
+    Ed448PublicKey, Ed448Key, ALG_EDDSA, CRV_ED448, 57
+}
+
+impl From<cosey::X25519PublicKey> for X25519PublicKey {
+    fn from(key: cosey::X25519PublicKey) -> Self {
+        Self { x: key.pub_key }
+    }
+}
+
+impl From<X25519PublicKey> for cosey::X25519PublicKey {
+    fn from(key: X25519PublicKey) -> Self {
+        Self { pub_key: key.x }
+    }
+}
+
+/// Common functionality of the COSE public key types.
+///
+/// This trait is implemented by all public key types supported by this module, including the
+/// [`PublicKey`][] enum, so that generic code can access the key parameters and serialize the key,
+/// for example to embed it into the attested credential data.
+pub trait CosePublicKey: Serialize + Sized {
+    /// Returns the key type.
+    fn kty(&self) -> Kty;
+
+    /// Returns the algorithm.
+    fn alg(&self) -> Alg;
+
+    /// Returns the curve, or `None` if the key type does not use curves.
+    fn crv(&self) -> Option<Crv>;
+
+    /// Serializes the key into the given buffer and returns the number of bytes written.
+    fn serialize_into(&self, buffer: &mut [u8]) -> Result<usize, Error> {
+        crate::serde::cbor_serialize(self, buffer)
+            .map(|data| data.len())
+            .map_err(|_| Error::Other)
+    }
+}
+
+macro_rules! impl_cose_public_key {
+    ($($(#[$attr:meta])* $name:ty: $kty:ident, $alg:ident, $crv:expr;)+) => {
+        $(
+            $(#[$attr])*
+            impl CosePublicKey for $name {
+                fn kty(&self) -> Kty {
+                    Kty::$kty
+                }
+
+                fn alg(&self) -> Alg {
+                    Alg::$alg
+                }
+
+                fn crv(&self) -> Option<Crv> {
+                    $crv
+                }
+            }
+        )+
+    };
+}
+
+impl_cose_public_key! {
+    P256PublicKey: Ec2, Es256, Some(Crv::P256);
+    EcdhEsHkdf256PublicKey: Ec2, EcdhEsHkdf256, Some(Crv::P256);
+    EcdhEsHkdf256PublicKeyRef<'_>: Ec2, EcdhEsHkdf256, Some(Crv::P256);
+    Ed25519PublicKey: Okp, EdDsa, Some(Crv::Ed25519);
+    P384PublicKey: Ec2, Es384, Some(Crv::P384);
+    P521PublicKey: Ec2, Es512, Some(Crv::P521);
+    Secp256k1PublicKey: Ec2, Es256K, Some(Crv::Secp256k1);
+    X25519PublicKey: Okp, EcdhEsHkdf256, Some(Crv::X25519);
+    Ed448PublicKey: Okp, EdDsa, Some(Crv::Ed448);
+    RsaPublicKey: Rsa, Rs256, None;
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa44PublicKey: Akp, MlDsa44, None;
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa65PublicKey: Akp, MlDsa65, None;
+}
+
+macro_rules! with_public_key {
+    ($public_key:expr, $key:ident => $e:expr) => {
+        match $public_key {
+            PublicKey::P256Key($key) => $e,
+            PublicKey::EcdhEsHkdf256Key($key) => $e,
+            PublicKey::Ed25519Key($key) => $e,
+            PublicKey::P384Key($key) => $e,
+            PublicKey::P521Key($key) => $e,
+            PublicKey::Secp256k1Key($key) => $e,
+            PublicKey::X25519Key($key) => $e,
+            PublicKey::Ed448Key($key) => $e,
+            #[cfg(feature = "rsa-signatures")]
+            PublicKey::RsaKey($key) => $e,
+            #[cfg(feature = "ml-dsa-signatures")]
+            PublicKey::MlDsa44Key($key) => $e,
+            #[cfg(feature = "ml-dsa-signatures")]
+            PublicKey::MlDsa65Key($key) => $e,
+        }
+    };
+}
+
+impl CosePublicKey for PublicKey {
+    fn kty(&self) -> Kty {
+        with_public_key!(self, key => key.kty())
+    }
+
+    fn alg(&self) -> Alg {
+        with_public_key!(self, key => key.alg())
+    }
+
+    fn crv(&self) -> Option<Crv> {
+        with_public_key!(self, key => key.crv())
+    }
+}
+
+impl CosePublicKey for PublicKeyWithParameters {
+    fn kty(&self) -> Kty {
+        self.key.kty()
+    }
+
+    fn alg(&self) -> Alg {
+        self.key.alg()
+    }
+
+    fn crv(&self) -> Option<Crv> {
+        self.key.crv()
+    }
+}
+
+/// Conversions between EC2 public keys and the uncompressed SEC1 encoding `0x04 || x || y`.
+pub trait Ec2PublicKey: Sized {
+    /// The size of a coordinate in bytes.
+    const COORDINATE_LENGTH: usize;
+
+    /// The size of the uncompressed SEC1 encoding in bytes.
+    const SEC1_LENGTH: usize = 1 + 2 * Self::COORDINATE_LENGTH;
+
+    /// Returns the uncompressed SEC1 encoding of the key.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the encoding does not fit into `N` bytes.
+    fn to_uncompressed_sec1<const N: usize>(&self) -> Result<Bytes<N>, Error>;
+
+    /// Parses a key from its uncompressed SEC1 encoding.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the data does not have the size of an uncompressed
+    /// point and [`Error::InvalidParameter`][] if it does not start with `0x04`.  The point is not
+    /// validated.
+    fn from_uncompressed_sec1(data: &[u8]) -> Result<Self, Error>;
+}
+
+/// Conversions between OKP public keys and their raw encoding.
+pub trait OkpPublicKey: Sized {
+    /// Returns the raw encoding of the key.
+    fn as_raw(&self) -> &[u8];
+
+    /// Parses a key from its raw encoding.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the data does not have the size of a key.
+    fn from_raw(data: &[u8]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_ec2_public_key {
+    ($($name:ty: $len:literal),+) => {
+        $(
+            impl Ec2PublicKey for $name {
+                const COORDINATE_LENGTH: usize = $len;
+
+                fn to_uncompressed_sec1<const N: usize>(&self) -> Result<Bytes<N>, Error> {
+                    let mut data = Bytes::new();
+                    data.push(0x04).map_err(|_| Error::InvalidLength)?;
+                    data.extend_from_slice(&self.x)
+                        .map_err(|_| Error::InvalidLength)?;
+                    data.extend_from_slice(&self.y)
+                        .map_err(|_| Error::InvalidLength)?;
+                    Ok(data)
+                }
+
+                fn from_uncompressed_sec1(data: &[u8]) -> Result<Self, Error> {
+                    if data.len() != Self::SEC1_LENGTH {
+                        return Err(Error::InvalidLength);
+                    }
+                    let (x, y) = match data.split_first() {
+                        Some((0x04, point)) => point.split_at($len),
+                        _ => return Err(Error::InvalidParameter),
+                    };
+                    Ok(Self {
+                        x: Bytes::from_slice(x).map_err(|_| Error::InvalidLength)?,
+                        y: Bytes::from_slice(y).map_err(|_| Error::InvalidLength)?,
+                    })
+                }
+            }
+        )+
+    };
+}
+
+impl_ec2_public_key! {
+    P256PublicKey: 32,
+    EcdhEsHkdf256PublicKey: 32,
+    P384PublicKey: 48,
+    P521PublicKey: 66,
+    Secp256k1PublicKey: 32
+}
+
+macro_rules! impl_okp_public_key {
+    ($($name:ty: $len:literal),+) => {
+        $(
+            impl OkpPublicKey for $name {
+                fn as_raw(&self) -> &[u8] {
+                    &self.x
+                }
+
+                fn from_raw(data: &[u8]) -> Result<Self, Error> {
+                    if data.len() != $len {
+                        return Err(Error::InvalidLength);
+                    }
+                    Ok(Self {
+                        x: Bytes::from_slice(data).map_err(|_| Error::InvalidLength)?,
+                    })
+                }
+            }
+        )+
+    };
+}
+
+impl_okp_public_key! {
+    Ed25519PublicKey: 32,
+    X25519PublicKey: 32,
+    Ed448PublicKey: 57
+}
+
+/// Converts a P-256 public key into a [`p256::PublicKey`][].
+///
+/// This is a function instead of a `TryFrom` implementation because both types are defined in
+/// other crates.  Returns [`Error::InvalidParameter`][] if the key is not a valid point on the
+/// curve.  This function is only available if the `p256` feature is enabled.
+#[cfg(feature = "p256")]
+pub fn to_p256_public_key(key: &P256PublicKey) -> Result<p256::PublicKey, Error> {
+    let sec1: Bytes<65> = key.to_uncompressed_sec1()?;
+    p256::PublicKey::from_sec1_bytes(&sec1).map_err(|_| Error::InvalidParameter)
+}
+
+/// Converts an Ed25519 public key into an [`ed25519_dalek::VerifyingKey`][].
+///
+/// This is a function instead of a `TryFrom` implementation because both types are defined in
+/// other crates.  Returns [`Error::InvalidParameter`][] if the key is not a valid point.  This
+/// function is only available if the `ed25519-dalek` feature is enabled.
+#[cfg(feature = "ed25519-dalek")]
+pub fn to_ed25519_verifying_key(
+    key: &Ed25519PublicKey,
+) -> Result<ed25519_dalek::VerifyingKey, Error> {
+    let bytes: &[u8; 32] = key
+        .x
+        .as_slice()
+        .try_into()
+        .map_err(|_| Error::InvalidLength)?;
+    ed25519_dalek::VerifyingKey::from_bytes(bytes).map_err(|_| Error::InvalidParameter)
+}
+
+/// An RSA public key with the RS256 algorithm, see [RFC 8230][].
+///
+/// The modulus `n` and the exponent `e` are big-endian unsigned integers without leading zero
+/// bytes.  Moduli with up to 4096 bits are supported.  When deserializing, `alg` must be set to
+/// RS256 and `kty` must precede the other parameters.
+///
+/// [RFC 8230]: https://www.rfc-editor.org/rfc/rfc8230
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RsaPublicKey {
+    pub n: Bytes<512>,
+    pub e: Bytes<4>,
+}
+
+impl Serialize for RsaPublicKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(4))?;
+        map.serialize_entry(&KTY, &KTY_RSA)?;
+        map.serialize_entry(&ALG, &ALG_RS256)?;
+        // the labels for n and e are the same as for crv and x
+        map.serialize_entry(&CRV, serde_bytes::Bytes::new(&self.n))?;
+        map.serialize_entry(&X, serde_bytes::Bytes::new(&self.e))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for RsaPublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RawPublicKey::deserialize(deserializer)?.rsa()
+    }
+}
+
+#[cfg(feature = "rsa-signatures")]
+impl From<RsaPublicKey> for PublicKey {
+    fn from(key: RsaPublicKey) -> Self {
+        Self::RsaKey(key)
+    }
+}
+
+#[cfg(feature = "ml-dsa-signatures")]
+macro_rules! akp_public_key {
+    (
+        $(#[$outer:meta])*
+        $name:ident, $variant:ident, $alg:expr, $len:literal
+    ) => {
+        $(#[$outer])*
+        ///
+        /// The key uses the AKP key type and the `pub` parameter from the draft
+        /// [ML-DSA for JOSE and COSE][] specification.  As the identifiers are not final yet, this
+        /// type is only available if the `ml-dsa-signatures` feature is enabled.  When
+        #[doc = concat!("deserializing, `alg` must match and the public key must have exactly ", $len, " bytes.")]
+        ///
+        /// [ML-DSA for JOSE and COSE]: https://datatracker.ietf.org/doc/draft-ietf-cose-dilithium/
+        #[derive(Clone, Debug, Eq, PartialEq)]
+        pub struct $name {
+            pub pub_key: Bytes<$len>,
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry(&KTY, &KTY_AKP)?;
+                map.serialize_entry(&ALG, &$alg)?;
+                // the label for pub is the same as for crv
+                map.serialize_entry(&CRV, serde_bytes::Bytes::new(&self.pub_key))?;
+                map.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Ok(Self {
+                    pub_key: RawPublicKey::deserialize(deserializer)?.akp($alg)?,
+                })
+            }
+        }
+
+        impl From<$name> for PublicKey {
+            fn from(key: $name) -> Self {
+                Self::$variant(key)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "ml-dsa-signatures")]
+akp_public_key! {
+    /// An ML-DSA-44 public key.
+    MlDsa44PublicKey, MlDsa44Key, ALG_ML_DSA_44, 1312
+}
+
+#[cfg(feature = "ml-dsa-signatures")]
+akp_public_key! {
+    /// An ML-DSA-65 public key.
+    MlDsa65PublicKey, MlDsa65Key, ALG_ML_DSA_65, 1952
+}
+
+/// A COSE public key.
+///
+/// In addition to the key types of [`cosey::PublicKey`][], this supports the key types defined in
+/// this module.  The key is serialized without a tag.  When deserializing, the key type is
+/// determined from the `kty`, `alg` and `crv` parameters and the coordinates must have exactly the
+/// size required by the curve.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+#[non_exhaustive]
+pub enum PublicKey {
+    P256Key(P256PublicKey),
+    EcdhEsHkdf256Key(EcdhEsHkdf256PublicKey),
+    Ed25519Key(Ed25519PublicKey),
+    P384Key(P384PublicKey),
+    P521Key(P521PublicKey),
+    Secp256k1Key(Secp256k1PublicKey),
+    X25519Key(X25519PublicKey),
+    Ed448Key(Ed448PublicKey),
+    /// RSA keys are only supported if the `rsa-signatures` feature is enabled.
+    #[cfg(feature = "rsa-signatures")]
+    RsaKey(RsaPublicKey),
+    /// ML-DSA keys are only supported if the `ml-dsa-signatures` feature is enabled.
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa44Key(MlDsa44PublicKey),
+    /// ML-DSA keys are only supported if the `ml-dsa-signatures` feature is enabled.
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa65Key(MlDsa65PublicKey),
+}
+
+impl From<P256PublicKey> for PublicKey {
+    fn from(key: P256PublicKey) -> Self {
+        Self::P256Key(key)
+    }
+}
+
+impl From<EcdhEsHkdf256PublicKey> for PublicKey {
+    fn from(key: EcdhEsHkdf256PublicKey) -> Self {
+        Self::EcdhEsHkdf256Key(key)
+    }
+}
+
+impl From<Ed25519PublicKey> for PublicKey {
+    fn from(key: Ed25519PublicKey) -> Self {
+        Self::Ed25519Key(key)
+    }
+}
+
+/// Converts a [`cosey::PublicKey`][] into the corresponding key of this module.
+///
+/// Returns [`Error::InvalidParameter`][] for key types that are not supported by this module.
+impl TryFrom<cosey::PublicKey> for PublicKey {
+    type Error = Error;
+
+    fn try_from(key: cosey::PublicKey) -> Result<Self, Error> {
+        match key {
+            cosey::PublicKey::P256Key(key) => Ok(key.into()),
+            cosey::PublicKey::EcdhEsHkdf256Key(key) => Ok(key.into()),
+            cosey::PublicKey::Ed25519Key(key) => Ok(key.into()),
+            #[allow(unreachable_patterns)]
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+/// Converts a key of this module into a [`cosey::PublicKey`][].
+///
+/// Returns [`Error::InvalidParameter`][] for key types that are not supported by `cosey`.
+impl TryFrom<PublicKey> for cosey::PublicKey {
+    type Error = Error;
+
+    fn try_from(key: PublicKey) -> Result<Self, Error> {
+        match key {
+            PublicKey::P256Key(key) => Ok(Self::P256Key(key)),
+            PublicKey::EcdhEsHkdf256Key(key) => Ok(Self::EcdhEsHkdf256Key(key)),
+            PublicKey::Ed25519Key(key) => Ok(Self::Ed25519Key(key)),
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+impl PublicKey {
+    /// Returns the parameters of the key.
+    fn to_raw(&self) -> RawPublicKey<'_> {
+        fn ec2<'a>(alg: i16, crv: i8, x: &'a [u8], y: &'a [u8]) -> RawPublicKey<'a> {
+            RawPublicKey {
+                kty: Some(KTY_EC2),
+                alg: Some(alg),
+                crv: Some(crv),
+                x: Some(serde_bytes::Bytes::new(x)),
+                y: Some(serde_bytes::Bytes::new(y)),
+                ..Default::default()
+            }
+        }
+
+        fn okp(alg: i16, crv: i8, x: &[u8]) -> RawPublicKey<'_> {
+            RawPublicKey {
+                kty: Some(KTY_OKP),
+                alg: Some(alg),
+                crv: Some(crv),
+                x: Some(serde_bytes::Bytes::new(x)),
+                ..Default::default()
+            }
+        }
+
+        #[cfg(feature = "ml-dsa-signatures")]
+        fn akp(alg: i16, public_key: &[u8]) -> RawPublicKey<'_> {
+            RawPublicKey {
+                kty: Some(KTY_AKP),
+                alg: Some(alg),
+                n: Some(serde_bytes::Bytes::new(public_key)),
+                ..Default::default()
+            }
+        }
+
+        match self {
+            Self::P256Key(key) => ec2(ALG_ES256, CRV_P256, &key.x, &key.y),
+            Self::EcdhEsHkdf256Key(key) => ec2(ALG_ECDH_ES_HKDF_256, CRV_P256, &key.x, &key.y),
+            Self::Ed25519Key(key) => okp(ALG_EDDSA, CRV_ED25519, &key.x),
+            Self::P384Key(key) => ec2(ALG_ES384, CRV_P384, &key.x, &key.y),
+            Self::P521Key(key) => ec2(ALG_ES512, CRV_P521, &key.x, &key.y),
+            Self::Secp256k1Key(key) => ec2(ALG_ES256K, CRV_SECP256K1, &key.x, &key.y),
+            Self::X25519Key(key) => okp(ALG_ECDH_ES_HKDF_256, CRV_X25519, &key.x),
+            Self::Ed448Key(key) => okp(ALG_EDDSA, CRV_ED448, &key.x),
+            #[cfg(feature = "rsa-signatures")]
+            Self::RsaKey(key) => RawPublicKey {
+                kty: Some(KTY_RSA),
+                alg: Some(ALG_RS256),
+                n: Some(serde_bytes::Bytes::new(&key.n)),
+                x: Some(serde_bytes::Bytes::new(&key.e)),
+                ..Default::default()
+            },
+            #[cfg(feature = "ml-dsa-signatures")]
+            Self::MlDsa44Key(key) => akp(ALG_ML_DSA_44, &key.pub_key),
+            #[cfg(feature = "ml-dsa-signatures")]
+            Self::MlDsa65Key(key) => akp(ALG_ML_DSA_65, &key.pub_key),
+        }
+    }
+
+    /// Computes the COSE Key Thumbprint of the key, see [RFC 9679][].
+    ///
+    /// The thumbprint is the SHA-256 hash of the deterministic encoding of the required key
+    /// parameters.  The `alg` parameter is only included for AKP keys.
+    ///
+    /// [RFC 9679]: https://www.rfc-editor.org/rfc/rfc9679
+    #[cfg(feature = "sha2")]
+    pub fn thumbprint(&self) -> [u8; 32] {
+        use crate::cbor::{write_head, write_integer, Sink};
+        use sha2::{Digest, Sha256};
+
+        let key = self.to_raw();
+        let alg = key.alg.filter(|_| key.kty == Some(KTY_AKP));
+        let parameters = [
+            (KTY, key.kty.map(i64::from), None),
+            (ALG, alg.map(i64::from), None),
+            (CRV, key.crv.map(i64::from), key.n),
+            (X, None, key.x),
+            (Y, None, key.y),
+        ];
+        let len = parameters
+            .iter()
+            .filter(|(_, integer, bytes)| integer.is_some() || bytes.is_some())
+            .count();
+
+        let mut hasher = Sha256::new();
+        let write = |hasher: &mut Sha256| -> crate::Result<()> {
+            write_head(hasher, 5, len as u64)?;
+            for (label, integer, bytes) in parameters {
+                if let Some(integer) = integer {
+                    write_integer(hasher, label.into())?;
+                    write_integer(hasher, integer)?;
+                } else if let Some(bytes) = bytes {
+                    write_integer(hasher, label.into())?;
+                    write_head(hasher, 2, bytes.len() as u64)?;
+                    hasher.write(bytes)?;
+                }
+            }
+            Ok(())
+        };
+        // writing to the hasher cannot fail
+        write(&mut hasher).ok();
+        hasher.finalize().into()
+    }
+
+    /// Determines the key type from the `kty`, `alg` and `crv` parameters.
+    fn from_raw<E: serde::de::Error>(key: &RawPublicKey<'_>) -> Result<Self, E> {
+        let public_key = match (key.kty, key.alg, key.crv) {
+            (Some(KTY_EC2), Some(ALG_ES256), Some(CRV_P256)) => Self::P256Key(P256PublicKey {
+                x: exact_coordinate(key.x, "x")?,
+                y: exact_coordinate(key.y, "y")?,
+            }),
+            (Some(KTY_EC2), Some(ALG_ECDH_ES_HKDF_256), Some(CRV_P256)) => {
+                Self::EcdhEsHkdf256Key(EcdhEsHkdf256PublicKey {
+                    x: exact_coordinate(key.x, "x")?,
+                    y: exact_coordinate(key.y, "y")?,
+                })
+            }
+            (Some(KTY_OKP), Some(ALG_EDDSA), Some(CRV_ED25519)) => {
+                Self::Ed25519Key(Ed25519PublicKey {
+                    x: exact_coordinate(key.x, "x")?,
+                })
+            }
+            (Some(KTY_EC2), Some(ALG_ES384), Some(CRV_P384)) => Self::P384Key(P384PublicKey {
+                x: exact_coordinate(key.x, "x")?,
+                y: exact_coordinate(key.y, "y")?,
+            }),
+            (Some(KTY_EC2), Some(ALG_ES512), Some(CRV_P521)) => Self::P521Key(P521PublicKey {
+                x: exact_coordinate(key.x, "x")?,
+                y: exact_coordinate(key.y, "y")?,
+            }),
+            (Some(KTY_EC2), Some(ALG_ES256K), Some(CRV_SECP256K1)) => {
+                Self::Secp256k1Key(Secp256k1PublicKey {
+                    x: exact_coordinate(key.x, "x")?,
+                    y: exact_coordinate(key.y, "y")?,
+                })
+            }
+            (Some(KTY_OKP), Some(ALG_ECDH_ES_HKDF_256), Some(CRV_X25519)) => {
+                Self::X25519Key(X25519PublicKey {
+                    x: exact_coordinate(key.x, "x")?,
+                })
+            }
+            (Some(KTY_OKP), Some(ALG_EDDSA), Some(CRV_ED448)) => Self::Ed448Key(Ed448PublicKey {
+                x: exact_coordinate(key.x, "x")?,
+            }),
+            #[cfg(feature = "rsa-signatures")]
+            (Some(KTY_RSA), _, _) => Self::RsaKey(key.rsa()?),
+            #[cfg(feature = "ml-dsa-signatures")]
+            (Some(KTY_AKP), Some(ALG_ML_DSA_44), None) => Self::MlDsa44Key(MlDsa44PublicKey {
+                pub_key: key.akp(ALG_ML_DSA_44)?,
+            }),
+            #[cfg(feature = "ml-dsa-signatures")]
+            (Some(KTY_AKP), Some(ALG_ML_DSA_65), None) => Self::MlDsa65Key(MlDsa65PublicKey {
+                pub_key: key.akp(ALG_ML_DSA_65)?,
+            }),
+            _ => return Err(E::custom("unsupported public key")),
+        };
+        if key.kty == Some(KTY_OKP) && key.y.is_some() {
+            return Err(E::custom("unexpected y coordinate"));
+        }
+        Ok(public_key)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::from_raw(&RawPublicKey::deserialize(deserializer)?)
+    }
+}
+
+/// A [`PublicKey`][] with the optional `kid` and `key_ops` parameters.
+///
+/// The typed keys reject these parameters.  This type accepts them when deserializing and
+/// serializes them in the canonical CTAP2 order.  The `key_ops` values are not validated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct PublicKeyWithParameters {
+    pub key: PublicKey,
+    pub kid: Option<Bytes<MAX_KID_LENGTH>>,
+    pub key_ops: Option<Vec<i8, MAX_KEY_OPS_LENGTH>>,
+}
+
+impl PublicKeyWithParameters {
+    pub fn new(key: PublicKey) -> Self {
+        Self {
+            key,
+            kid: None,
+            key_ops: None,
+        }
+    }
+}
+
+impl From<PublicKey> for PublicKeyWithParameters {
+    fn from(key: PublicKey) -> Self {
+        Self::new(key)
+    }
+}
+
+impl Serialize for PublicKeyWithParameters {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut key = self.key.to_raw();
+        key.kid = self.kid.as_ref().map(|kid| serde_bytes::Bytes::new(kid));
+        key.key_ops = self.key_ops.clone();
+        key.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKeyWithParameters {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let key = RawPublicKey::deserialize_with_parameters(deserializer)?;
+        let kid = key
+            .kid
+            .map(|kid| {
+                Bytes::from_slice(kid).map_err(|_| D::Error::invalid_length(kid.len(), &"a key ID"))
+            })
+            .transpose()?;
+        Ok(Self {
+            key: PublicKey::from_raw(&key)?,
+            kid,
+            key_ops: key.key_ops,
+        })
+    }
+}
+
+/// The value of a [`CoseKey`][] parameter.
+///
+/// Byte and text strings can have up to `L` bytes.  Arrays can have up to eight integer elements,
+/// for example the `key_ops` parameter.  Other values, like maps or floating-point numbers, are
+/// not supported.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CoseKeyValue<const L: usize> {
+    Integer(i64),
+    Bytes(Bytes<L>),
+    Text(String<L>),
+    Bool(bool),
+    Array(Vec<i64, 8>),
+}
+
+impl<const L: usize> Serialize for CoseKeyValue<L> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Integer(value) => serializer.serialize_i64(*value),
+            Self::Bytes(value) => serializer.serialize_bytes(value),
+            Self::Text(value) => serializer.serialize_str(value),
+            Self::Bool(value) => serializer.serialize_bool(*value),
+            Self::Array(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, const L: usize> Deserialize<'de> for CoseKeyValue<L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ValueVisitor<const L: usize>;
+
+        impl<'de, const L: usize> Visitor<'de> for ValueVisitor<L> {
+            type Value = CoseKeyValue<L>;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a COSE key parameter value")
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                Ok(CoseKeyValue::Bool(value))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                Ok(CoseKeyValue::Integer(value))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                i64::try_from(value)
+                    .map(CoseKeyValue::Integer)
+                    .map_err(|_| E::custom("integer out of range"))
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+                Bytes::from_slice(value)
+                    .map(CoseKeyValue::Bytes)
+                    .map_err(|_| E::invalid_length(value.len(), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                value
+                    .parse()
+                    .map(CoseKeyValue::Text)
+                    .map_err(|_| E::invalid_length(value.len(), &self))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut array = Vec::new();
+                while let Some(element) = seq.next_element()? {
+                    array
+                        .push(element)
+                        .map_err(|_| A::Error::invalid_length(array.len() + 1, &self))?;
+                }
+                Ok(CoseKeyValue::Array(array))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// A COSE key with arbitrary integer labels.
+///
+/// In contrast to the typed keys in this module, this type retains all parameters, for example
+/// `kid`, `key_ops` or vendor-specific labels, in the order they were deserialized or inserted.
+/// It can store up to `N` parameters with values of up to `L` bytes, see [`CoseKeyValue`][].  Text
+/// labels are not supported.
+///
+/// Use the [`TryFrom`][] implementations to convert between this type and [`PublicKey`][].  The
+/// conversion to a [`PublicKey`][] fails if the key contains parameters that are not supported by
+/// the typed key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CoseKey<const N: usize = 8, const L: usize = 128> {
+    parameters: Vec<(i64, CoseKeyValue<L>), N>,
+}
+
+impl<const N: usize, const L: usize> CoseKey<N, L> {
+    /// Creates an empty key.
+    pub fn new() -> Self {
+        Self {
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Returns the value of the parameter with the given label.
+    pub fn get(&self, label: i64) -> Option<&CoseKeyValue<L>> {
+        self.parameters
+            .iter()
+            .find(|(l, _)| *l == label)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the value of the parameter with the given label and returns the previous value.
+    ///
+    /// New parameters are appended.  Returns [`Error::InvalidLength`][] if the key already has `N`
+    /// parameters.
+    pub fn insert(
+        &mut self,
+        label: i64,
+        value: CoseKeyValue<L>,
+    ) -> Result<Option<CoseKeyValue<L>>, Error> {
+        if let Some((_, existing)) = self.parameters.iter_mut().find(|(l, _)| *l == label) {
+            return Ok(Some(core::mem::replace(existing, value)));
+        }
+        self.parameters
+            .push((label, value))
+            .map_err(|_| Error::InvalidLength)?;
+        Ok(None)
+    }
+
+    /// Removes the parameter with the given label and returns its value.
+    pub fn remove(&mut self, label: i64) -> Option<CoseKeyValue<L>> {
+        let index = self.parameters.iter().position(|(l, _)| *l == label)?;
+        Some(self.parameters.remove(index).1)
+    }
+
+    /// Returns an iterator over the labels and values of the parameters.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, &CoseKeyValue<L>)> {
+        self.parameters.iter().map(|(label, value)| (*label, value))
+    }
+
+    fn insert_bytes(&mut self, label: i8, value: Option<&[u8]>) -> Result<(), Error> {
+        if let Some(value) = value {
+            let value = Bytes::from_slice(value).map_err(|_| Error::InvalidLength)?;
+            self.insert(label.into(), CoseKeyValue::Bytes(value))?;
+        }
+        Ok(())
+    }
+
+    fn insert_integer(&mut self, label: i8, value: Option<impl Into<i64>>) -> Result<(), Error> {
+        if let Some(value) = value {
+            self.insert(label.into(), CoseKeyValue::Integer(value.into()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize, const L: usize> Serialize for CoseKey<N, L> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.parameters.len()))?;
+        for (label, value) in &self.parameters {
+            map.serialize_entry(label, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, const N: usize, const L: usize> Deserialize<'de> for CoseKey<N, L> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CoseKeyVisitor<const N: usize, const L: usize>;
+
+        impl<'de, const N: usize, const L: usize> Visitor<'de> for CoseKeyVisitor<N, L> {
+            type Value = CoseKey<N, L>;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a COSE key")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut key = CoseKey::new();
+                while let Some(label) = map.next_key()? {
+                    if key.get(label).is_some() {
+                        return Err(A::Error::custom("duplicate key parameter"));
+                    }
+                    let value = map.next_value()?;
+                    key.parameters
+                        .push((label, value))
+                        .map_err(|_| A::Error::invalid_length(key.parameters.len() + 1, &self))?;
+                }
+                Ok(key)
+            }
+        }
+
+        deserializer.deserialize_map(CoseKeyVisitor)
+    }
+}
+
+impl<const N: usize, const L: usize> TryFrom<&CoseKey<N, L>> for PublicKey {
+    type Error = Error;
+
+    fn try_from(key: &CoseKey<N, L>) -> Result<Self, Error> {
+        let mut raw = RawPublicKey::default();
+        for (label, value) in key.iter() {
+            let label = i8::try_from(label).map_err(|_| Error::InvalidParameter)?;
+            let integer = |value: i64| value.try_into().map_err(|_| Error::InvalidParameter);
+            match (label, value) {
+                (KTY, CoseKeyValue::Integer(kty)) => raw.kty = Some(integer(*kty)?),
+                (ALG, CoseKeyValue::Integer(alg)) => {
+                    raw.alg = Some(i16::try_from(*alg).map_err(|_| Error::InvalidParameter)?)
+                }
+                (CRV, CoseKeyValue::Integer(crv)) => raw.crv = Some(integer(*crv)?),
+                // n for RSA keys and pub for AKP keys
+                (CRV, CoseKeyValue::Bytes(n)) => raw.n = Some(serde_bytes::Bytes::new(n)),
+                (X, CoseKeyValue::Bytes(x)) => raw.x = Some(serde_bytes::Bytes::new(x)),
+                (Y, CoseKeyValue::Bytes(y)) => raw.y = Some(serde_bytes::Bytes::new(y)),
+                _ => return Err(Error::InvalidParameter),
+            }
+        }
+        Self::from_raw::<serde::de::value::Error>(&raw).map_err(|_| Error::InvalidParameter)
+    }
+}
+
+impl<const N: usize, const L: usize> TryFrom<&PublicKey> for CoseKey<N, L> {
+    type Error = Error;
+
+    fn try_from(public_key: &PublicKey) -> Result<Self, Error> {
+        let raw = public_key.to_raw();
+        let mut key = Self::new();
+        key.insert_integer(KTY, raw.kty)?;
+        key.insert_integer(ALG, raw.alg)?;
+        key.insert_integer(CRV, raw.crv)?;
+        // n for RSA keys and pub for AKP keys
+        key.insert_bytes(CRV, raw.n.map(|n| &**n))?;
+        key.insert_bytes(X, raw.x.map(|x| &**x))?;
+        key.insert_bytes(Y, raw.y.map(|y| &**y))?;
+        Ok(key)
+    }
+}
+
+macro_rules! private_key {
+    (
+        $(#[$outer:meta])*
+        $name:ident, $kty:expr, $alg:expr, $crv:expr, $len:literal
+    ) => {
+        $(#[$outer])*
+        ///
+        /// The key is serialized with the `kty`, `alg`, `crv` and `d` parameters in the canonical
+        /// CTAP2 order.  When deserializing, the `alg` and `crv` parameters must match, the
+        #[doc = concat!("private key must have exactly ", $len, " bytes and the public key must not be included.")]
+        ///
+        /// The [`Debug`][] implementation does not print the private key.  If the `zeroize`
+        /// feature is enabled, the private key is zeroized on drop.
+        #[derive(Clone, Default, Eq, PartialEq)]
+        pub struct $name {
+            pub d: Bytes<$len>,
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(4))?;
+                map.serialize_entry(&KTY, &$kty)?;
+                map.serialize_entry(&ALG, &$alg)?;
+                map.serialize_entry(&CRV, &$crv)?;
+                map.serialize_entry(&D, serde_bytes::Bytes::new(&self.d))?;
+                map.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let key = RawPublicKey::deserialize_private(deserializer)?;
+                key.check($kty, $alg, $crv)?;
+                if key.x.is_some() || key.y.is_some() {
+                    return Err(D::Error::custom("unexpected key parameter"));
+                }
+                Ok(Self {
+                    d: exact_coordinate(key.d, "d")?,
+                })
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::Zeroize for $name {
+            fn zeroize(&mut self) {
+                let d: &mut [u8] = &mut self.d;
+                zeroize::Zeroize::zeroize(d);
+                self.d.clear();
+            }
+        }
+
+        // Drop is implemented independent of the zeroize feature so that enabling the feature
+        // does not prevent moving out of the key.
+        impl Drop for $name {
+            fn drop(&mut self) {
+                #[cfg(feature = "zeroize")]
+                zeroize::Zeroize::zeroize(self);
+            }
+        }
+
+        #[cfg(feature = "zeroize")]
+        impl zeroize::ZeroizeOnDrop for $name {}
+    };
+}
+
+private_key! {
+    /// An ECDSA private key on the P-256 curve with the ES256 algorithm.
+    P256PrivateKey, KTY_EC2, ALG_ES256, CRV_P256, 32
+}
+
+private_key! {
+    /// An ECDSA private key on the P-384 curve with the ES384 algorithm.
+    P384PrivateKey, KTY_EC2, ALG_ES384, CRV_P384, 48
+}
+
+private_key! {
+    /// An ECDSA private key on the P-521 curve with the ES512 algorithm.
+    P521PrivateKey, KTY_EC2, ALG_ES512, CRV_P521, 66
+}
+
+private_key! {
+    /// An ECDSA private key on the secp256k1 curve with the ES256K algorithm.
+    Secp256k1PrivateKey, KTY_EC2, ALG_ES256K, CRV_SECP256K1, 32
+}
+
+private_key! {
+    /// An Ed25519 private key with the EdDSA algorithm.
+    Ed25519PrivateKey, KTY_OKP, ALG_EDDSA, CRV_ED25519, 32
+}
+
+private_key! {
+    /// An X25519 private key with the ECDH-ES+HKDF-256 algorithm.
+    X25519PrivateKey, KTY_OKP, ALG_ECDH_ES_HKDF_256, CRV_X25519, 32
+}
+
+private_key! {
+    /// An Ed448 private key with the EdDSA algorithm.
+    Ed448PrivateKey, KTY_OKP, ALG_EDDSA, CRV_ED448, 57
+}
+
+/// Serializes an EC2 key with the parameters in the canonical CTAP2 order.
+fn serialize_ec2<S: Serializer>(
+    serializer: S,
+    alg: i16,
+    crv: i8,
+    x: &[u8],
+    y: &[u8],
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(5))?;
+    map.serialize_entry(&KTY, &KTY_EC2)?;
+    map.serialize_entry(&ALG, &alg)?;
+    map.serialize_entry(&CRV, &crv)?;
+    map.serialize_entry(&X, serde_bytes::Bytes::new(x))?;
+    map.serialize_entry(&Y, serde_bytes::Bytes::new(y))?;
+    map.end()
+}
+
+/// Serializes an OKP key with the parameters in the canonical CTAP2 order.
+fn serialize_okp<S: Serializer>(
+    serializer: S,
+    alg: i16,
+    crv: i8,
+    x: &[u8],
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(4))?;
+    map.serialize_entry(&KTY, &KTY_OKP)?;
+    map.serialize_entry(&ALG, &alg)?;
+    map.serialize_entry(&CRV, &crv)?;
+    map.serialize_entry(&X, serde_bytes::Bytes::new(x))?;
+    map.end()
+}
+
+/// Copies a coordinate that must have exactly `N` bytes.
+fn exact_coordinate<E: serde::de::Error, const N: usize>(
+    value: Option<&serde_bytes::Bytes>,
+    name: &'static str,
+) -> Result<Bytes<N>, E> {
+    let value = value.ok_or_else(|| E::missing_field(name))?;
+    if value.len() != N {
+        return Err(E::invalid_length(
+            value.len(),
+            &"a coordinate of the curve size",
+        ));
+    }
+    Bytes::from_slice(value)
+        .map_err(|_| E::invalid_length(value.len(), &"a coordinate of the curve size"))
+}
+
+/// The common parameters of the supported COSE keys.
+///
+/// Unknown parameters are rejected.  The `kid` and `key_ops` parameters are only accepted by
+/// [`RawPublicKey::deserialize_with_parameters`][], and the private key `d` is only accepted by
+/// [`RawPublicKey::deserialize_private`][].
+///
+/// For RSA keys, the labels -1 and -2 are used for the modulus `n` and the exponent `e`.  For AKP
+/// keys, the label -1 is used for the public key `pub` and stored in `n`.  As the type of the value
+/// for label -1 depends on the key type, `kty` must precede it.  This is always the case in the
+/// canonical CTAP2 encoding.
+#[derive(Default)]
+struct RawPublicKey<'a> {
+    kty: Option<i8>,
+    kid: Option<&'a serde_bytes::Bytes>,
+    alg: Option<i16>,
+    key_ops: Option<Vec<i8, MAX_KEY_OPS_LENGTH>>,
+    crv: Option<i8>,
+    n: Option<&'a serde_bytes::Bytes>,
+    x: Option<&'a serde_bytes::Bytes>,
+    y: Option<&'a serde_bytes::Bytes>,
+    d: Option<&'a serde_bytes::Bytes>,
+}
+
+impl<'a> RawPublicKey<'a> {
+    fn deserialize_with_parameters<'de: 'a, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RawPublicKeyVisitor {
+            parameters: true,
+            private: false,
+        })
+    }
+
+    fn deserialize_private<'de: 'a, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RawPublicKeyVisitor {
+            parameters: false,
+            private: true,
+        })
+    }
+}
+
+impl Serialize for RawPublicKey<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = [
+            self.kty.is_some(),
+            self.kid.is_some(),
+            self.alg.is_some(),
+            self.key_ops.is_some(),
+            self.crv.is_some() || self.n.is_some(),
+            self.x.is_some(),
+            self.y.is_some(),
+        ]
+        .into_iter()
+        .filter(|present| *present)
+        .count();
+        let mut map = serializer.serialize_map(Some(len))?;
+        if let Some(kty) = &self.kty {
+            map.serialize_entry(&KTY, kty)?;
+        }
+        if let Some(kid) = &self.kid {
+            map.serialize_entry(&KID, kid)?;
+        }
+        if let Some(alg) = &self.alg {
+            map.serialize_entry(&ALG, alg)?;
+        }
+        if let Some(key_ops) = &self.key_ops {
+            map.serialize_entry(&KEY_OPS, key_ops)?;
+        }
+        if let Some(crv) = &self.crv {
+            map.serialize_entry(&CRV, crv)?;
+        } else if let Some(n) = &self.n {
+            map.serialize_entry(&CRV, n)?;
+        }
+        if let Some(x) = &self.x {
+            map.serialize_entry(&X, x)?;
+        }
+        if let Some(y) = &self.y {
+            map.serialize_entry(&Y, y)?;
+        }
+        map.end()
+    }
+}
+
+impl RawPublicKey<'_> {
+    /// Checks that the key has the given `kty`, `alg` and `crv` parameters.
+    fn check<E: serde::de::Error>(&self, kty: i8, alg: i16, crv: i8) -> Result<(), E> {
+        if self.kty != Some(kty) {
+            return Err(E::custom("unexpected kty"));
+        }
+        if self.alg != Some(alg) {
+            return Err(E::custom("unexpected alg"));
+        }
+        if self.crv != Some(crv) {
+            return Err(E::custom("unexpected crv"));
+        }
+        Ok(())
+    }
+
+    fn rsa<E: serde::de::Error>(&self) -> Result<RsaPublicKey, E> {
+        if self.kty != Some(KTY_RSA) {
+            return Err(E::custom("unexpected kty"));
+        }
+        if self.alg != Some(ALG_RS256) {
+            return Err(E::custom("unexpected alg"));
+        }
+        if self.y.is_some() {
+            return Err(E::custom("unexpected key parameter"));
+        }
+        let integer = |value: Option<&serde_bytes::Bytes>, name| {
+            let value = value.ok_or_else(|| E::missing_field(name))?;
+            if value.first().copied().unwrap_or_default() == 0 {
+                return Err(E::custom("invalid RSA key parameter"));
+            }
+            Bytes::from_slice(value)
+                .map_err(|_| E::invalid_length(value.len(), &"an RSA key parameter"))
+        };
+        Ok(RsaPublicKey {
+            n: integer(self.n, "n")?,
+            e: integer(self.x, "e")?,
+        })
+    }
+
+    #[cfg(feature = "ml-dsa-signatures")]
+    fn akp<E: serde::de::Error, const N: usize>(&self, alg: i16) -> Result<Bytes<N>, E> {
+        if self.kty != Some(KTY_AKP) {
+            return Err(E::custom("unexpected kty"));
+        }
+        if self.alg != Some(alg) {
+            return Err(E::custom("unexpected alg"));
+        }
+        if self.x.is_some() || self.y.is_some() {
+            return Err(E::custom("unexpected key parameter"));
+        }
+        exact_coordinate(self.n, "pub")
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawPublicKey<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RawPublicKeyVisitor {
+            parameters: false,
+            private: false,
+        })
+    }
+}
+
+struct RawPublicKeyVisitor {
+    /// Whether the `kid` and `key_ops` parameters are accepted.
+    parameters: bool,
+    /// Whether the private key `d` is accepted.
+    private: bool,
+}
+
+impl<'de> Visitor<'de> for RawPublicKeyVisitor {
+    type Value = RawPublicKey<'de>;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a COSE key")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut key = RawPublicKey::default();
+        while let Some(label) = map.next_key::<i8>()? {
+            match label {
+                KTY => key.kty = Some(map.next_value()?),
+                KID if self.parameters => key.kid = Some(map.next_value()?),
+                ALG => key.alg = Some(map.next_value()?),
+                KEY_OPS if self.parameters => key.key_ops = Some(map.next_value()?),
+                CRV if matches!(key.kty, Some(KTY_RSA | KTY_AKP)) => {
+                    key.n = Some(map.next_value()?)
+                }
+                CRV => key.crv = Some(map.next_value()?),
+                X => key.x = Some(map.next_value()?),
+                Y => key.y = Some(map.next_value()?),
+                D if self.private => key.d = Some(map.next_value()?),
+                _ => return Err(A::Error::custom("unexpected key parameter")),
+            }
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_ecdh_es_hkdf_256_public_key_ref() {
+        let x = [0x01; 32];
+        let y = [0x02; 32];
+        let owned = EcdhEsHkdf256PublicKey {
+            x: Bytes::from_slice(&x).unwrap(),
+            y: Bytes::from_slice(&y).unwrap(),
+        };
+        let key = EcdhEsHkdf256PublicKeyRef::from(&owned);
+
+        let mut buffer = [0; 128];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        let mut owned_buffer = [0; 128];
+        assert_eq!(
+            serialized,
+            crate::serde::cbor_serialize(&owned, &mut owned_buffer).unwrap()
+        );
+
+        let deserialized: EcdhEsHkdf256PublicKeyRef<'_> =
+            crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        assert_eq!(deserialized.try_to_owned(), Some(owned));
+
+        // wrong curve
+        let mut data = serialized.to_vec();
+        data[7] = 0x02;
+        assert!(crate::serde::cbor_deserialize::<EcdhEsHkdf256PublicKeyRef<'_>>(&data).is_err());
+        // missing y
+        assert!(
+            crate::serde::cbor_deserialize::<EcdhEsHkdf256PublicKeyRef<'_>>(&hex!(
+                "a3010220012158200101010101010101010101010101010101010101010101010101010101010101"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_p384_public_key() {
+        let key = P384PublicKey {
+            x: Bytes::from_slice(&[0x01; 48]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 48]).unwrap(),
+        };
+        let mut buffer = [0; 128];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        let mut expected = hex!("a501020338222002215830010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101225830020202020202020202020202020202020202020202020202020202020202020202020202020202020202020202020202").to_vec();
+        assert_eq!(serialized, expected);
+        let deserialized: P384PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+
+        // wrong curve
+        expected[7] = 0x01;
+        assert!(crate::serde::cbor_deserialize::<P384PublicKey>(&expected).is_err());
+        // wrong algorithm
+        expected[7] = 0x02;
+        expected[5] = 0x23;
+        assert!(crate::serde::cbor_deserialize::<P384PublicKey>(&expected).is_err());
+        // coordinates too short
+        let expected = hex!("a5010203382220022158200101010101010101010101010101010101010101010101010101010101010101225820020202020202020202020202020202020202020202020202020202020202020202");
+        assert!(crate::serde::cbor_deserialize::<P384PublicKey>(&expected).is_err());
+    }
+
+    #[test]
+    fn test_p521_public_key() {
+        let key = P521PublicKey {
+            x: Bytes::from_slice(&[0x01; 66]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 66]).unwrap(),
+        };
+        let mut buffer = [0; 160];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..11], hex!("a501020338232003215842"));
+        assert_eq!(serialized[11..77], [0x01; 66]);
+        assert_eq!(serialized[77..80], hex!("225842"));
+        assert_eq!(serialized[80..], [0x02; 66]);
+        let deserialized: P521PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+
+        // a P-384 key is rejected
+        let key = P384PublicKey {
+            x: Bytes::from_slice(&[0x01; 48]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 48]).unwrap(),
+        };
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert!(crate::serde::cbor_deserialize::<P521PublicKey>(serialized).is_err());
+    }
+
+    #[test]
+    fn test_secp256k1_public_key() {
+        let key = Secp256k1PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+        };
+        let mut buffer = [0; 128];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..11], hex!("a5010203382e2008215820"));
+        let deserialized: Secp256k1PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, PublicKey::Secp256k1Key(key));
+
+        // a P-256 key with the same coordinates is rejected
+        let key = P256PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+        };
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert!(crate::serde::cbor_deserialize::<Secp256k1PublicKey>(serialized).is_err());
+    }
+
+    #[test]
+    fn test_x25519_public_key() {
+        let key = X25519PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        };
+        let mut buffer = [0; 64];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..11], hex!("a401010338182004215820"));
+        assert_eq!(serialized[11..], [0x01; 32]);
+        let deserialized: X25519PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, PublicKey::X25519Key(key.clone()));
+        let cosey_key: cosey::X25519PublicKey = key.clone().into();
+        assert_eq!(X25519PublicKey::from(cosey_key), key);
+
+        // an Ed25519 key with the same public key is rejected
+        let key = Ed25519PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        };
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert!(crate::serde::cbor_deserialize::<X25519PublicKey>(serialized).is_err());
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn test_to_p256_public_key() {
+        use p256::elliptic_curve::sec1::ToEncodedPoint as _;
+
+        // the generator of the curve
+        let key = P256PublicKey {
+            x: Bytes::from_slice(&hex!(
+                "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296"
+            ))
+            .unwrap(),
+            y: Bytes::from_slice(&hex!(
+                "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"
+            ))
+            .unwrap(),
+        };
+        let public_key = to_p256_public_key(&key).unwrap();
+        let point = public_key.to_encoded_point(false);
+        assert_eq!(point.x().unwrap().as_slice(), key.x.as_slice());
+        assert_eq!(point.y().unwrap().as_slice(), key.y.as_slice());
+
+        let key = P256PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+            y: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        };
+        assert_eq!(to_p256_public_key(&key), Err(Error::InvalidParameter));
+    }
+
+    #[cfg(feature = "ed25519-dalek")]
+    #[test]
+    fn test_to_ed25519_verifying_key() {
+        // test 1 from RFC 8032, section 7.1
+        let x = hex!("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        let key = Ed25519PublicKey {
+            x: Bytes::from_slice(&x).unwrap(),
+        };
+        assert_eq!(to_ed25519_verifying_key(&key).unwrap().as_bytes(), &x);
+
+        let key = Ed25519PublicKey {
+            x: Bytes::from_slice(&[0x01; 31]).unwrap(),
+        };
+        assert_eq!(to_ed25519_verifying_key(&key), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_cosey_conversions() {
+        let key = PublicKey::from(Ed25519PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        });
+        let cosey_key = cosey::PublicKey::try_from(key.clone()).unwrap();
+        assert_eq!(PublicKey::try_from(cosey_key), Ok(key));
+
+        let key = PublicKey::from(Ed448PublicKey {
+            x: Bytes::from_slice(&[0x01; 57]).unwrap(),
+        });
+        assert_eq!(
+            cosey::PublicKey::try_from(key),
+            Err(Error::InvalidParameter)
+        );
+    }
+
+    #[test]
+    fn test_ed448_public_key() {
+        let key = Ed448PublicKey {
+            x: Bytes::from_slice(&[0x01; 57]).unwrap(),
+        };
+        let mut buffer = [0; 80];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..10], hex!("a4010103272007215839"));
+        assert_eq!(serialized[10..], [0x01; 57]);
+        let deserialized: Ed448PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, PublicKey::Ed448Key(key));
+
+        // wrong crv
+        let mut invalid = [0; 67];
+        invalid.copy_from_slice(serialized);
+        invalid[6] = 0x06;
+        assert!(crate::serde::cbor_deserialize::<Ed448PublicKey>(&invalid).is_err());
+        assert!(crate::serde::cbor_deserialize::<PublicKey>(&invalid).is_err());
+        // wrong kty
+        invalid.copy_from_slice(serialized);
+        invalid[2] = 0x02;
+        assert!(crate::serde::cbor_deserialize::<Ed448PublicKey>(&invalid).is_err());
+        // wrong alg
+        invalid.copy_from_slice(serialized);
+        invalid[4] = 0x26;
+        assert!(crate::serde::cbor_deserialize::<Ed448PublicKey>(&invalid).is_err());
+        // an Ed25519 public key is too short
+        assert!(crate::serde::cbor_deserialize::<Ed448PublicKey>(&hex!(
+            "a40101032720072158200101010101010101010101010101010101010101010101010101010101010101"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_sec1_conversion() {
+        let mut data = [0x04; 65];
+        data[1..33].fill(0x01);
+        data[33..].fill(0x02);
+        let key = P256PublicKey::from_uncompressed_sec1(&data).unwrap();
+        assert_eq!(key.x, Bytes::<32>::from_slice(&[0x01; 32]).unwrap());
+        assert_eq!(key.y, Bytes::<32>::from_slice(&[0x02; 32]).unwrap());
+        assert_eq!(
+            key.to_uncompressed_sec1::<65>().unwrap(),
+            Bytes::from_slice(&data).unwrap()
+        );
+        assert_eq!(key.to_uncompressed_sec1::<64>(), Err(Error::InvalidLength));
+
+        assert_eq!(P384PublicKey::SEC1_LENGTH, 97);
+        assert_eq!(
+            P384PublicKey::from_uncompressed_sec1(&data),
+            Err(Error::InvalidLength)
+        );
+        data[0] = 0x02;
+        assert_eq!(
+            P256PublicKey::from_uncompressed_sec1(&data),
+            Err(Error::InvalidParameter)
+        );
+
+        let key = X25519PublicKey::from_raw(&[0x03; 32]).unwrap();
+        assert_eq!(key.as_raw(), [0x03; 32]);
+        assert_eq!(
+            Ed448PublicKey::from_raw(&[0x03; 32]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_rsa_public_key() {
+        let mut n = [0xab; 256];
+        n[0] = 0xc0;
+        let key = RsaPublicKey {
+            n: Bytes::from_slice(&n).unwrap(),
+            e: Bytes::from_slice(&[0x01, 0x00, 0x01]).unwrap(),
+        };
+        let mut buffer = [0; 300];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..11], hex!("a401030339010020590100"));
+        assert_eq!(serialized[11..267], n);
+        assert_eq!(serialized[267..], hex!("2143010001"));
+        let deserialized: RsaPublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        #[cfg(feature = "rsa-signatures")]
+        {
+            let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+            assert_eq!(deserialized, PublicKey::RsaKey(key));
+        }
+
+        // leading zero bytes are rejected
+        assert!(crate::serde::cbor_deserialize::<RsaPublicKey>(&hex!(
+            "a4010303390100204200012143010001"
+        ))
+        .is_err());
+        // kty must precede n
+        assert!(crate::serde::cbor_deserialize::<RsaPublicKey>(&hex!(
+            "a42041010103033901002143010001"
+        ))
+        .is_err());
+    }
+
+    #[cfg(feature = "ml-dsa-signatures")]
+    #[test]
+    fn test_ml_dsa_public_key() {
+        let key = MlDsa44PublicKey {
+            pub_key: Bytes::from_slice(&[0x01; 1312]).unwrap(),
+        };
+        let mut buffer = [0; 1400];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..9], hex!("a3010703382f20590520"));
+        assert_eq!(serialized[9..], [0x01; 1312]);
+        let deserialized: MlDsa44PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, PublicKey::MlDsa44Key(key));
+        // the public key size depends on the algorithm
+        assert!(crate::serde::cbor_deserialize::<MlDsa65PublicKey>(serialized).is_err());
+
+        let key = MlDsa65PublicKey {
+            pub_key: Bytes::from_slice(&[0x02; 1952]).unwrap(),
+        };
+        let mut buffer = [0; 2000];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..9], hex!("a30107033830205907a0"));
+        let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, PublicKey::MlDsa65Key(key));
+    }
+
+    #[test]
+    fn test_cose_key() {
+        // a P-256 key with kid (2), key_ops (4) and a vendor-specific label
+        let data = hex!(
+            "a8010202426b6904810203262001215820010101010101010101010101010101010101010101010101010101010101010122582002020202020202020202020202020202020202020202020202020202020202023a000100006676656e646f72"
+        );
+        let mut key: CoseKey = crate::serde::cbor_deserialize(&data).unwrap();
+        assert_eq!(
+            key.get(2),
+            Some(&CoseKeyValue::Bytes(Bytes::from_slice(b"ki").unwrap()))
+        );
+        assert_eq!(
+            key.get(4),
+            Some(&CoseKeyValue::Array(Vec::from_slice(&[2]).unwrap()))
+        );
+        assert_eq!(
+            key.get(-65537),
+            Some(&CoseKeyValue::Text("vendor".parse().unwrap()))
+        );
+        let mut buffer = [0; 128];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized, data);
+
+        // the typed keys do not support the additional parameters
+        assert_eq!(PublicKey::try_from(&key), Err(Error::InvalidParameter));
+        key.remove(2).unwrap();
+        key.remove(4).unwrap();
+        key.remove(-65537).unwrap();
+        let public_key = PublicKey::try_from(&key).unwrap();
+        assert_eq!(
+            public_key,
+            PublicKey::P256Key(P256PublicKey {
+                x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+                y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+            })
+        );
+
+        let key: CoseKey = (&public_key).try_into().unwrap();
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        let mut expected = [0; 128];
+        let expected = crate::serde::cbor_serialize(&public_key, &mut expected).unwrap();
+        assert_eq!(serialized, expected);
+
+        // too many parameters or too long values
+        assert!(crate::serde::cbor_deserialize::<CoseKey<4>>(&data).is_err());
+        assert!(crate::serde::cbor_deserialize::<CoseKey<8, 16>>(&data).is_err());
+        assert_eq!(
+            CoseKey::<4>::try_from(&public_key),
+            Err(Error::InvalidLength)
+        );
+        // duplicate labels
+        assert!(crate::serde::cbor_deserialize::<CoseKey>(&hex!("a201020102")).is_err());
+    }
+
+    #[test]
+    fn test_public_key_with_parameters() {
+        let data = hex!(
+            "a7010202426b690326048102200121582001010101010101010101010101010101010101010101010101010101010101012258200202020202020202020202020202020202020202020202020202020202020202"
+        );
+        let key: PublicKeyWithParameters = crate::serde::cbor_deserialize(&data).unwrap();
+        assert_eq!(
+            key.key,
+            PublicKey::P256Key(P256PublicKey {
+                x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+                y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+            })
+        );
+        assert_eq!(key.kid.as_ref().map(|kid| kid.as_slice()), Some(&b"ki"[..]));
+        assert_eq!(key.key_ops.as_deref(), Some(&[2][..]));
+        let mut buffer = [0; 128];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized, data);
+
+        // the typed keys reject the additional parameters
+        assert!(crate::serde::cbor_deserialize::<PublicKey>(&data).is_err());
+
+        // without the additional parameters, the encoding matches the typed key
+        let key = PublicKeyWithParameters::from(key.key);
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        let mut expected = [0; 128];
+        let expected = crate::serde::cbor_serialize(&key.key, &mut expected).unwrap();
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_private_key() {
+        let key = P256PrivateKey {
+            d: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        };
+        let mut buffer = [0; 64];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..10], hex!("a4010203262001235820"));
+        assert_eq!(serialized[10..], [0x01; 32]);
+        let deserialized: P256PrivateKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+        assert_eq!(std::format!("{key:?}"), "P256PrivateKey { .. }");
+
+        // the curve and the key size must match
+        assert!(crate::serde::cbor_deserialize::<Secp256k1PrivateKey>(serialized).is_err());
+        assert!(crate::serde::cbor_deserialize::<P384PrivateKey>(serialized).is_err());
+        // public keys do not accept the private key
+        assert!(crate::serde::cbor_deserialize::<PublicKey>(serialized).is_err());
+
+        let key = Ed448PrivateKey {
+            d: Bytes::from_slice(&[0x02; 57]).unwrap(),
+        };
+        let mut buffer = [0; 80];
+        let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized[..10], hex!("a4010103272007235839"));
+        let deserialized: Ed448PrivateKey = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_private_key_zeroize() {
+        use zeroize::Zeroize as _;
+
+        let mut key = Ed25519PrivateKey {
+            d: Bytes::from_slice(&[0x01; 32]).unwrap(),
+        };
+        key.zeroize();
+        assert!(key.d.is_empty());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_thumbprint() {
+        // example from RFC 9679, section 6
+        let key = PublicKey::P256Key(P256PublicKey {
+            x: Bytes::from_slice(&hex!(
+                "65eda5a12577c2bae829437fe338701a10aaa375e1bb5b5de108de439c08551d"
+            ))
+            .unwrap(),
+            y: Bytes::from_slice(&hex!(
+                "1e52ed75701163f7f9e40ddf9f341b3dc9ba860af7e0ca7ca7e9eecd0084d19c"
+            ))
+            .unwrap(),
+        });
+        assert_eq!(
+            key.thumbprint(),
+            hex!("496bd8afadf307e5b08c64b0421bf9dc01528a344a43bda88fadd1669da253ec")
+        );
+    }
+
+    #[test]
+    fn test_cose_public_key() {
+        fn check<K: CosePublicKey>(key: &K, kty: Kty, alg: Alg, crv: Option<Crv>) {
+            assert_eq!(key.kty(), kty);
+            assert_eq!(key.alg(), alg);
+            assert_eq!(key.crv(), crv);
+
+            let mut buffer = [0; 128];
+            let len = key.serialize_into(&mut buffer).unwrap();
+            let mut expected = [0; 128];
+            let expected = crate::serde::cbor_serialize(key, &mut expected).unwrap();
+            assert_eq!(&buffer[..len], expected);
+            assert_eq!(
+                key.serialize_into(&mut buffer[..len - 1]),
+                Err(Error::Other)
+            );
+        }
+
+        let key = Secp256k1PublicKey {
+            x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+            y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+        };
+        check(&key, Kty::Ec2, Alg::Es256K, Some(Crv::Secp256k1));
+        check(
+            &PublicKey::from(key),
+            Kty::Ec2,
+            Alg::Es256K,
+            Some(Crv::Secp256k1),
+        );
+        let key = Ed25519PublicKey {
+            x: Bytes::from_slice(&[0x03; 32]).unwrap(),
+        };
+        check(&key, Kty::Okp, Alg::EdDsa, Some(Crv::Ed25519));
+        check(
+            &PublicKeyWithParameters::new(key.into()),
+            Kty::Okp,
+            Alg::EdDsa,
+            Some(Crv::Ed25519),
+        );
+        let key = RsaPublicKey {
+            n: Bytes::from_slice(&[0x04; 64]).unwrap(),
+            e: Bytes::from_slice(&[0x01, 0x00, 0x01]).unwrap(),
+        };
+        check(&key, Kty::Rsa, Alg::Rs256, None);
+    }
+
+    #[test]
+    fn test_public_key() {
+        let keys: [PublicKey; 4] = [
+            P256PublicKey {
+                x: Bytes::from_slice(&[0x01; 32]).unwrap(),
+                y: Bytes::from_slice(&[0x02; 32]).unwrap(),
+            }
+            .into(),
+            Ed25519PublicKey {
+                x: Bytes::from_slice(&[0x03; 32]).unwrap(),
+            }
+            .into(),
+            P384PublicKey {
+                x: Bytes::from_slice(&[0x04; 48]).unwrap(),
+                y: Bytes::from_slice(&[0x05; 48]).unwrap(),
+            }
+            .into(),
+            P521PublicKey {
+                x: Bytes::from_slice(&[0x06; 66]).unwrap(),
+                y: Bytes::from_slice(&[0x07; 66]).unwrap(),
+            }
+            .into(),
+        ];
+        for key in keys {
+            let mut buffer = [0; 160];
+            let serialized = crate::serde::cbor_serialize(&key, &mut buffer).unwrap();
+            let deserialized: PublicKey = crate::serde::cbor_deserialize(serialized).unwrap();
+            assert_eq!(deserialized, key);
+        }
+
+        // coordinates must have the curve size
+        assert!(
+            crate::serde::cbor_deserialize::<PublicKey>(&hex!("a5010203262001214101224102"))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! IANA-registered COSE identifiers used by the key types in this crate.
+//!
+//! See the [COSE registries](https://www.iana.org/assignments/cose/cose.xhtml).  Only the
+//! identifiers that are relevant for CTAP are included.
+
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+use crate::ctap2::{Error, Result};
+
+macro_rules! impl_conversions {
+    ($name:ident: $repr:ty { $($variant:ident),+ $(,)? }) => {
+        impl TryFrom<$repr> for $name {
+            type Error = Error;
+
+            fn try_from(value: $repr) -> Result<Self> {
+                $(
+                    if value == Self::$variant as $repr {
+                        return Ok(Self::$variant);
+                    }
+                )+
+                Err(Error::InvalidParameter)
+            }
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> $repr {
+                value as $repr
+            }
+        }
+    };
+}
+
+/// A COSE key parameter label.
+///
+/// The labels -1 to -4 are key type specific.  This enum uses the names for the EC2 and OKP key
+/// types.  For RSA keys, -1 is `n` and -2 is `e`.  For AKP keys, -1 is `pub`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[repr(i8)]
+pub enum Label {
+    Kty = 1,
+    Kid = 2,
+    Alg = 3,
+    KeyOps = 4,
+    Crv = -1,
+    X = -2,
+    Y = -3,
+    D = -4,
+}
+
+impl_conversions!(Label: i8 { Kty, Kid, Alg, KeyOps, Crv, X, Y, D });
+
+/// A COSE key type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[repr(i8)]
+pub enum Kty {
+    Okp = 1,
+    Ec2 = 2,
+    Rsa = 3,
+    /// The algorithm key pair type from the draft ML-DSA specification.
+    Akp = 7,
+}
+
+impl_conversions!(Kty: i8 { Okp, Ec2, Rsa, Akp });
+
+/// A COSE algorithm.
+///
+/// The ML-DSA identifiers are drafts and only available if the `ml-dsa-signatures` feature is
+/// enabled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[repr(i16)]
+pub enum Alg {
+    /// ECDSA w/ SHA-256
+    Es256 = -7,
+    /// EdDSA
+    EdDsa = -8,
+    /// ECDH-ES + HKDF-256
+    EcdhEsHkdf256 = -25,
+    /// ECDSA w/ SHA-384
+    Es384 = -35,
+    /// ECDSA w/ SHA-512
+    Es512 = -36,
+    /// ECDSA using secp256k1 curve and SHA-256
+    Es256K = -47,
+    /// ML-DSA-44
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa44 = -48,
+    /// ML-DSA-65
+    #[cfg(feature = "ml-dsa-signatures")]
+    MlDsa65 = -49,
+    /// RSASSA-PKCS1-v1_5 w/ SHA-256
+    Rs256 = -257,
+}
+
+#[cfg(not(feature = "ml-dsa-signatures"))]
+impl_conversions!(Alg: i16 { Es256, EdDsa, EcdhEsHkdf256, Es384, Es512, Es256K, Rs256 });
+#[cfg(feature = "ml-dsa-signatures")]
+impl_conversions!(Alg: i16 {
+    Es256, EdDsa, EcdhEsHkdf256, Es384, Es512, Es256K, MlDsa44, MlDsa65, Rs256
+});
+
+/// A COSE elliptic curve.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[repr(i8)]
+pub enum Crv {
+    P256 = 1,
+    P384 = 2,
+    P521 = 3,
+    X25519 = 4,
+    X448 = 5,
+    Ed25519 = 6,
+    Ed448 = 7,
+    Secp256k1 = 8,
+}
+
+impl_conversions!(Crv: i8 { P256, P384, P521, X25519, X448, Ed25519, Ed448, Secp256k1 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Alg::try_from(-7), Ok(Alg::Es256));
+        assert_eq!(Alg::try_from(-257), Ok(Alg::Rs256));
+        assert_eq!(Alg::try_from(-6), Err(Error::InvalidParameter));
+        assert_eq!(i16::from(Alg::EcdhEsHkdf256), -25);
+        assert_eq!(Crv::try_from(8), Ok(Crv::Secp256k1));
+        assert_eq!(Kty::try_from(4), Err(Error::InvalidParameter));
+        assert_eq!(Label::try_from(-4), Ok(Label::D));
+        assert_eq!(i8::from(Label::KeyOps), 4);
+    }
+
+    #[test]
+    fn test_serde() {
+        let mut buffer = [0; 4];
+        let serialized = crate::serde::cbor_serialize(&Alg::Rs256, &mut buffer).unwrap();
+        assert_eq!(serialized, [0x39, 0x01, 0x00]);
+        let deserialized: Alg = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, Alg::Rs256);
+        let deserialized: Crv = crate::serde::cbor_deserialize(&[0x06]).unwrap();
+        assert_eq!(deserialized, Crv::Ed25519);
+        assert!(crate::serde::cbor_deserialize::<Kty>(&[0x05]).is_err());
+    }
+}
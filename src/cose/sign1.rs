@@ -0,0 +1,196 @@
+use crate::{
+    cbor::{head, item_len, write_head},
+    Bytes, Error, Result,
+};
+
+/// The CBOR tag for a `COSE_Sign1` structure.
+const TAG: u64 = 18;
+
+/// The CBOR encoding of an empty map.
+const EMPTY_MAP: &[u8] = &[0xa0];
+
+/// A `COSE_Sign1` structure, see [RFC 9052, section 4.2][].
+///
+/// The header maps are kept in their serialized form.  The protected header is the content of
+/// the byte string, i.e. a serialized map or an empty slice if there are no protected header
+/// parameters.  The unprotected header is the raw encoding of the map.
+///
+/// [RFC 9052, section 4.2]: https://www.rfc-editor.org/rfc/rfc9052#section-4.2
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Sign1<'a> {
+    pub protected: &'a [u8],
+    pub unprotected: &'a [u8],
+    /// The payload, or `None` if the payload is detached.
+    pub payload: Option<&'a [u8]>,
+    pub signature: &'a [u8],
+}
+
+impl<'a> Sign1<'a> {
+    /// Creates a structure with an empty unprotected header.
+    pub fn new(protected: &'a [u8], payload: Option<&'a [u8]>, signature: &'a [u8]) -> Self {
+        Self {
+            protected,
+            unprotected: EMPTY_MAP,
+            payload,
+            signature,
+        }
+    }
+
+    /// Parses a serialized `COSE_Sign1` structure with or without the `COSE_Sign1` tag.
+    ///
+    /// Returns [`Error::InvalidCbor`][] if the data is not a well-formed `COSE_Sign1` structure
+    /// or if it is followed by trailing data.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let data = match head(data) {
+            Some((6, TAG, head_len)) => &data[head_len..],
+            _ => data,
+        };
+        match head(data) {
+            Some((4, 4, head_len)) => {
+                let (protected, rest) = split_bytes(&data[head_len..])?;
+                if !protected.is_empty() && !is_map(protected) {
+                    return Err(Error::InvalidCbor);
+                }
+                let unprotected_len = item_len(rest).ok_or(Error::InvalidCbor)?;
+                let (unprotected, rest) = rest.split_at(unprotected_len);
+                if !is_map(unprotected) {
+                    return Err(Error::InvalidCbor);
+                }
+                let (payload, rest) = match rest.split_first() {
+                    // null
+                    Some((0xf6, rest)) => (None, rest),
+                    _ => {
+                        let (payload, rest) = split_bytes(rest)?;
+                        (Some(payload), rest)
+                    }
+                };
+                let (signature, rest) = split_bytes(rest)?;
+                if !rest.is_empty() {
+                    return Err(Error::InvalidCbor);
+                }
+                Ok(Self {
+                    protected,
+                    unprotected,
+                    payload,
+                    signature,
+                })
+            }
+            _ => Err(Error::InvalidCbor),
+        }
+    }
+
+    /// Serializes the structure into the given buffer, optionally with the `COSE_Sign1` tag.
+    ///
+    /// Returns the number of bytes written.  If serialization fails, the buffer is restored to
+    /// its previous length.  Returns [`Error::InvalidParameter`][] if a header is not a
+    /// well-formed map and [`Error::Other`][] if the buffer is too small.
+    pub fn serialize_into<const N: usize>(
+        &self,
+        buffer: &mut Bytes<N>,
+        tagged: bool,
+    ) -> Result<usize> {
+        let start = buffer.len();
+        if let Err(err) = self.serialize_to(buffer, tagged) {
+            buffer.truncate(start);
+            return Err(err);
+        }
+        Ok(buffer.len() - start)
+    }
+
+    fn serialize_to<const N: usize>(&self, buffer: &mut Bytes<N>, tagged: bool) -> Result<()> {
+        if (!self.protected.is_empty() && !is_map(self.protected)) || !is_map(self.unprotected) {
+            return Err(Error::InvalidParameter);
+        }
+        if tagged {
+            write_head(buffer, 6, TAG)?;
+        }
+        write_head(buffer, 4, 4)?;
+        write_bytes(buffer, self.protected)?;
+        buffer
+            .extend_from_slice(self.unprotected)
+            .map_err(|_| Error::Other)?;
+        match self.payload {
+            Some(payload) => write_bytes(buffer, payload)?,
+            None => buffer.push(0xf6).map_err(|_| Error::Other)?,
+        }
+        write_bytes(buffer, self.signature)
+    }
+}
+
+/// Checks whether `data` consists of exactly one well-formed CBOR map.
+fn is_map(data: &[u8]) -> bool {
+    matches!(head(data), Some((5, _, _))) && item_len(data) == Some(data.len())
+}
+
+/// Splits a byte string at the start of `data` and returns its content and the remaining data.
+fn split_bytes(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    match head(data) {
+        Some((2, _, head_len)) => {
+            let len = item_len(data).ok_or(Error::InvalidCbor)?;
+            Ok((&data[head_len..len], &data[len..]))
+        }
+        _ => Err(Error::InvalidCbor),
+    }
+}
+
+fn write_bytes<const N: usize>(buffer: &mut Bytes<N>, data: &[u8]) -> Result<()> {
+    write_head(buffer, 2, data.len() as u64)?;
+    buffer.extend_from_slice(data).map_err(|_| Error::Other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn test_sign1() {
+        // structure of the example from RFC 9052, appendix C.2.1, with a dummy signature
+        let mut data = [0; 98];
+        data[..34].copy_from_slice(&hex!(
+            "d28443a10126a10442313154546869732069732074686520636f6e74656e742e5840"
+        ));
+        data[34..].fill(0x01);
+        let sign1 = Sign1::parse(&data).unwrap();
+        assert_eq!(sign1.protected, hex!("a10126"));
+        assert_eq!(sign1.unprotected, hex!("a104423131"));
+        assert_eq!(sign1.payload, Some(&b"This is the content."[..]));
+        assert_eq!(sign1.signature, [0x01; 64]);
+
+        let mut buffer = Bytes::<128>::new();
+        assert_eq!(sign1.serialize_into(&mut buffer, true), Ok(data.len()));
+        assert_eq!(buffer.as_slice(), data);
+        buffer.clear();
+        assert_eq!(sign1.serialize_into(&mut buffer, false), Ok(data.len() - 1));
+        assert_eq!(buffer.as_slice(), &data[1..]);
+        assert_eq!(Sign1::parse(&data[1..]), Ok(sign1));
+
+        // detached payload and empty headers
+        let sign1 = Sign1::new(&[], None, &[0x02; 4]);
+        buffer.clear();
+        sign1.serialize_into(&mut buffer, false).unwrap();
+        assert_eq!(buffer.as_slice(), hex!("8440a0f64402020202"));
+        assert_eq!(Sign1::parse(&buffer), Ok(sign1));
+
+        // the buffer is restored on error
+        let mut buffer = Bytes::<8>::from_slice(&[0xff]).unwrap();
+        assert_eq!(sign1.serialize_into(&mut buffer, true), Err(Error::Other));
+        assert_eq!(buffer.as_slice(), [0xff]);
+
+        // trailing data, wrong tag and wrong array length
+        assert_eq!(
+            Sign1::parse(&hex!("8440a0f64402020202a0")),
+            Err(Error::InvalidCbor)
+        );
+        assert_eq!(
+            Sign1::parse(&hex!("d18440a0f64402020202")),
+            Err(Error::InvalidCbor)
+        );
+        assert_eq!(Sign1::parse(&hex!("8340a0f6")), Err(Error::InvalidCbor));
+        // the unprotected header must be a map
+        assert_eq!(
+            Sign1::parse(&hex!("844080f64402020202")),
+            Err(Error::InvalidCbor)
+        );
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Note that all ctap1::Authenticators automatically implement RPC with [`Request`] and
 //! [`Response`].
+use crate::sizes::MAX_U2F_VERSION_LENGTH;
+use crate::webauthn::{ClientDataHash, RpIdHash};
 use crate::Bytes;
 
 pub const NO_ERROR: u16 = 0x9000;
@@ -10,31 +12,95 @@ pub const NO_ERROR: u16 = 0x9000;
 pub use iso7816::Status as Error;
 
 pub mod authenticate {
-    use super::{Bytes, ControlByte};
+    use super::{Bytes, ClientDataHash, ControlByte, Error, Result, RpIdHash};
 
     #[derive(Clone, Debug, Eq, PartialEq)]
     pub struct Request<'a> {
         pub control_byte: ControlByte,
-        pub challenge: &'a [u8; 32],
-        pub app_id: &'a [u8; 32],
+        pub challenge: &'a ClientDataHash,
+        pub app_id: &'a RpIdHash,
         pub key_handle: &'a [u8],
     }
 
+    impl Request<'_> {
+        /// Returns the data that is signed for this request, see the [U2F raw message formats][].
+        ///
+        /// This is the concatenation of the application parameter, the user presence byte, the
+        /// big-endian counter and the challenge parameter.
+        ///
+        /// [U2F raw message formats]: https://fidoalliance.org/specs/fido-u2f-v1.2-ps-20170411/fido-u2f-raw-message-formats-v1.2-ps-20170411.html#authentication-response-message-success
+        pub fn signed_data(&self, user_presence: u8, count: u32) -> [u8; 69] {
+            let mut data = [0; 69];
+            data[..32].copy_from_slice(self.app_id.as_bytes());
+            data[32] = user_presence;
+            data[33..37].copy_from_slice(&count.to_be_bytes());
+            data[37..].copy_from_slice(self.challenge.as_bytes());
+            data
+        }
+    }
+
     #[derive(Clone, Debug, Eq, PartialEq)]
     pub struct Response {
         pub user_presence: u8,
         pub count: u32,
         pub signature: Bytes<72>,
     }
+
+    impl Response {
+        /// Parses a response from its wire format.
+        ///
+        /// Returns [`Error::IncorrectDataParameter`][] if the data is truncated or if the
+        /// signature is empty or too long.
+        pub fn parse(data: &[u8]) -> Result<Self> {
+            if data.len() < 5 {
+                return Err(Error::IncorrectDataParameter);
+            }
+            let (header, signature) = data.split_at(5);
+            if signature.is_empty() {
+                return Err(Error::IncorrectDataParameter);
+            }
+            Ok(Self {
+                user_presence: header[0],
+                count: u32::from_be_bytes(header[1..].try_into().unwrap()),
+                signature: Bytes::from_slice(signature)
+                    .map_err(|_| Error::IncorrectDataParameter)?,
+            })
+        }
+    }
 }
 
 pub mod register {
-    use super::Bytes;
+    use super::{der_item_len, Bytes, ClientDataHash, Error, Result, RpIdHash};
+    use crate::cose::Ec2PublicKey as _;
 
     #[derive(Clone, Debug, Eq, PartialEq)]
     pub struct Request<'a> {
-        pub challenge: &'a [u8; 32],
-        pub app_id: &'a [u8; 32],
+        pub challenge: &'a ClientDataHash,
+        pub app_id: &'a RpIdHash,
+    }
+
+    impl Request<'_> {
+        /// Returns the data that is signed for this request, see the [U2F raw message formats][].
+        ///
+        /// This is the concatenation of the reserved byte `0x00`, the application parameter, the
+        /// challenge parameter, the key handle and the uncompressed public key.  Returns
+        /// [`Error::IncorrectDataParameter`][] if the key handle is longer than 255 bytes or if the
+        /// public key does not have 65 bytes.
+        ///
+        /// [U2F raw message formats]: https://fidoalliance.org/specs/fido-u2f-v1.2-ps-20170411/fido-u2f-raw-message-formats-v1.2-ps-20170411.html#registration-response-message-success
+        pub fn signed_data(&self, key_handle: &[u8], public_key: &[u8]) -> Result<Bytes<385>> {
+            if key_handle.len() > 255 || public_key.len() != 65 {
+                return Err(Error::IncorrectDataParameter);
+            }
+            let mut data = Bytes::new();
+            // cannot fail as the buffer has the maximum size of the signed data
+            data.push(0x00).ok();
+            data.extend_from_slice(self.app_id.as_bytes()).ok();
+            data.extend_from_slice(self.challenge.as_bytes()).ok();
+            data.extend_from_slice(key_handle).ok();
+            data.extend_from_slice(public_key).ok();
+            Ok(data)
+        }
     }
 
     #[derive(Clone, Debug, Eq, PartialEq)]
@@ -49,25 +115,81 @@ pub mod register {
     impl Response {
         pub fn new(
             header_byte: u8,
-            public_key: &cosey::EcdhEsHkdf256PublicKey,
+            public_key: &crate::cose::EcdhEsHkdf256PublicKey,
             key_handle: Bytes<255>,
             signature: Bytes<72>,
             attestation_certificate: Bytes<1024>,
         ) -> Self {
-            let mut public_key_bytes = Bytes::new();
-            public_key_bytes.push(0x04).unwrap();
-            public_key_bytes.extend_from_slice(&public_key.x).unwrap();
-            public_key_bytes.extend_from_slice(&public_key.y).unwrap();
-
             Self {
                 header_byte,
-                public_key: public_key_bytes,
+                // cannot fail as the buffer has the size of an uncompressed P-256 point
+                public_key: public_key.to_uncompressed_sec1().unwrap(),
                 key_handle,
                 attestation_certificate,
                 signature,
             }
         }
+
+        /// Parses a response from its wire format.
+        ///
+        /// The boundary between the attestation certificate and the signature is determined from
+        /// the DER encoding of the certificate.  Returns [`Error::IncorrectDataParameter`][] if
+        /// the data is truncated, if the certificate is not a well-formed DER item or if a field
+        /// is too long.
+        pub fn parse(data: &[u8]) -> Result<Self> {
+            if data.len() < 67 {
+                return Err(Error::IncorrectDataParameter);
+            }
+            let header_byte = data[0];
+            let public_key = &data[1..66];
+            let key_handle_len = usize::from(data[66]);
+            let rest = &data[67..];
+            if rest.len() < key_handle_len {
+                return Err(Error::IncorrectDataParameter);
+            }
+            let (key_handle, rest) = rest.split_at(key_handle_len);
+            let certificate_len = der_item_len(rest).ok_or(Error::IncorrectDataParameter)?;
+            let (attestation_certificate, signature) = rest.split_at(certificate_len);
+            if signature.is_empty() {
+                return Err(Error::IncorrectDataParameter);
+            }
+            Ok(Self {
+                header_byte,
+                public_key: Bytes::from_slice(public_key)
+                    .map_err(|_| Error::IncorrectDataParameter)?,
+                key_handle: Bytes::from_slice(key_handle)
+                    .map_err(|_| Error::IncorrectDataParameter)?,
+                attestation_certificate: Bytes::from_slice(attestation_certificate)
+                    .map_err(|_| Error::IncorrectDataParameter)?,
+                signature: Bytes::from_slice(signature)
+                    .map_err(|_| Error::IncorrectDataParameter)?,
+            })
+        }
+    }
+}
+
+/// Returns the length of the DER item at the start of `data`, including its header.
+///
+/// Only single-byte tags and lengths with up to two length bytes are supported, which is
+/// sufficient for the attestation certificates used by CTAP1.
+fn der_item_len(data: &[u8]) -> Option<usize> {
+    let (header_len, len) = match *data.get(1)? {
+        len @ 0..=0x7f => (2, usize::from(len)),
+        0x81 => (3, usize::from(*data.get(2)?)),
+        0x82 => (
+            4,
+            usize::from(u16::from_be_bytes([*data.get(2)?, *data.get(3)?])),
+        ),
+        _ => return None,
+    };
+    if data[0] & 0x1f == 0x1f {
+        return None;
     }
+    let item_len = header_len + len;
+    if data.len() < item_len {
+        return None;
+    }
+    Some(item_len)
 }
 
 #[repr(u8)]
@@ -99,6 +221,71 @@ impl TryFrom<u8> for ControlByte {
 
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// Maps a CTAP2 error to the closest matching status word.
+impl From<crate::ctap2::Error> for Error {
+    fn from(error: crate::ctap2::Error) -> Self {
+        use crate::ctap2::Error as Ctap2Error;
+        match error {
+            Ctap2Error::Success => Self::Success,
+            Ctap2Error::InvalidCommand => Self::InstructionNotSupportedOrInvalid,
+            Ctap2Error::InvalidLength | Ctap2Error::RequestTooLarge => Self::WrongLength,
+            Ctap2Error::InvalidParameter
+            | Ctap2Error::CborUnexpectedType
+            | Ctap2Error::InvalidCbor
+            | Ctap2Error::MissingParameter
+            | Ctap2Error::UnsupportedOption
+            | Ctap2Error::InvalidOption
+            | Ctap2Error::InvalidSubcommand
+            | Ctap2Error::InvalidCredential
+            | Ctap2Error::NoCredentials => Self::IncorrectDataParameter,
+            Ctap2Error::UpRequired
+            | Ctap2Error::UserActionPending
+            | Ctap2Error::UserActionTimeout
+            | Ctap2Error::ActionTimeout
+            | Ctap2Error::OperationDenied
+            | Ctap2Error::KeepaliveCancel
+            | Ctap2Error::NotAllowed => Self::ConditionsOfUseNotSatisfied,
+            Ctap2Error::PinInvalid
+            | Ctap2Error::PinBlocked
+            | Ctap2Error::PinAuthInvalid
+            | Ctap2Error::PinAuthBlocked
+            | Ctap2Error::PinNotSet
+            | Ctap2Error::PinRequired
+            | Ctap2Error::PinPolicyViolation
+            | Ctap2Error::PinTokenExpired
+            | Ctap2Error::UvBlocked
+            | Ctap2Error::UvInvalid
+            | Ctap2Error::UnauthorizedPermission => Self::SecurityStatusNotSatisfied,
+            Ctap2Error::FingerprintDatabaseFull
+            | Ctap2Error::LargeBlobStorageFull
+            | Ctap2Error::KeyStoreFull => Self::NotEnoughMemory,
+            _ => Self::UnspecifiedCheckingError,
+        }
+    }
+}
+
+/// Best-effort mapping of a status word to a CTAP2 error.
+///
+/// Status words do not carry as much information as CTAP2 errors, so this conversion is lossy.
+impl From<Error> for crate::ctap2::Error {
+    fn from(status: Error) -> Self {
+        match status {
+            Error::Success => Self::Success,
+            Error::InstructionNotSupportedOrInvalid | Error::ClassNotSupported => {
+                Self::InvalidCommand
+            }
+            Error::WrongLength => Self::InvalidLength,
+            Error::IncorrectDataParameter | Error::IncorrectP1OrP2Parameter => {
+                Self::InvalidParameter
+            }
+            Error::ConditionsOfUseNotSatisfied => Self::UpRequired,
+            Error::SecurityStatusNotSatisfied => Self::NotAllowed,
+            Error::NotEnoughMemory => Self::KeyStoreFull,
+            _ => Self::Other,
+        }
+    }
+}
+
 /// Type alias for convenience.
 pub type Register<'a> = register::Request<'a>;
 /// Type alias for convenience.
@@ -119,41 +306,147 @@ pub enum Request<'a> {
     Version,
 }
 
+impl Request<'_> {
+    /// Returns the instruction byte (`INS`) of this request.
+    pub fn instruction(&self) -> u8 {
+        match self {
+            Self::Register(_) => 0x01,
+            Self::Authenticate(_) => 0x02,
+            Self::Version => 0x03,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[allow(clippy::large_enum_variant)]
 /// Enum of all CTAP1 responses.
 pub enum Response {
     Register(register::Response),
     Authenticate(authenticate::Response),
-    Version([u8; 6]),
+    Version(Bytes<MAX_U2F_VERSION_LENGTH>),
+}
+
+/// An error that occurs when serializing a [`Response`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SerializeError {
+    /// The buffer is too small for the response.
+    BufferFull,
+    /// The response is invalid, e.g. the public key of a register response does not have 65
+    /// bytes or the signature is empty.
+    InvalidResponse,
+}
+
+/// An error that occurs when serializing a [`Response`][] into a writer.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WriteError<E> {
+    /// The underlying writer returned an error.
+    Io(E),
+    /// The response is invalid, see [`SerializeError::InvalidResponse`][].
+    InvalidResponse,
 }
 
 impl Response {
-    #[allow(clippy::result_unit_err)]
+    /// Serializes the response into the given buffer without the status word.
+    ///
+    /// If serialization fails, the buffer is restored to its previous length.
     #[inline(never)]
     pub fn serialize<const S: usize>(
         &self,
         buf: &mut iso7816::Data<S>,
-    ) -> core::result::Result<(), ()> {
+    ) -> core::result::Result<(), SerializeError> {
+        if !self.is_valid() {
+            return Err(SerializeError::InvalidResponse);
+        }
+        let len = buf.len();
+        let result = self.write_parts(|data| {
+            buf.extend_from_slice(data)
+                .map_err(|_| SerializeError::BufferFull)
+        });
+        if result.is_err() {
+            buf.truncate(len);
+        }
+        result
+    }
+
+    /// Serializes the response into the given buffer followed by the status word `0x9000`.
+    ///
+    /// Use [`serialize_status`][] to serialize an error.  If serialization fails, the buffer is
+    /// restored to its previous length.
+    pub fn serialize_with_status<const S: usize>(
+        &self,
+        buf: &mut iso7816::Data<S>,
+    ) -> core::result::Result<(), SerializeError> {
+        let len = buf.len();
+        self.serialize(buf)?;
+        if let Err(err) = serialize_status(Error::Success, buf) {
+            buf.truncate(len);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            Response::Register(reg) => reg.public_key.len() == 65 && !reg.signature.is_empty(),
+            Response::Authenticate(auth) => !auth.signature.is_empty(),
+            Response::Version(_) => true,
+        }
+    }
+
+    /// Serializes the response into the given writer.
+    ///
+    /// In contrast to [`Response::serialize`][], this does not require a buffer that can hold the
+    /// entire response.  The response is validated before anything is written.
+    #[cfg(feature = "embedded-io")]
+    #[inline(never)]
+    pub fn serialize_to_writer<W: embedded_io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> core::result::Result<(), WriteError<W::Error>> {
+        if !self.is_valid() {
+            return Err(WriteError::InvalidResponse);
+        }
+        self.write_parts(|data| writer.write_all(data).map_err(WriteError::Io))
+    }
+
+    fn write_parts<E, F>(&self, mut write: F) -> core::result::Result<(), E>
+    where
+        F: FnMut(&[u8]) -> core::result::Result<(), E>,
+    {
         match self {
             Response::Register(reg) => {
-                buf.push(reg.header_byte).map_err(drop)?;
-                buf.extend_from_slice(&reg.public_key)?;
-                buf.push(reg.key_handle.len() as u8).map_err(drop)?;
-                buf.extend_from_slice(&reg.key_handle)?;
-                buf.extend_from_slice(&reg.attestation_certificate)?;
-                buf.extend_from_slice(&reg.signature)
+                write(&[reg.header_byte])?;
+                write(&reg.public_key)?;
+                write(&[reg.key_handle.len() as u8])?;
+                write(&reg.key_handle)?;
+                write(&reg.attestation_certificate)?;
+                write(&reg.signature)
             }
             Response::Authenticate(auth) => {
-                buf.push(auth.user_presence).map_err(drop)?;
-                buf.extend_from_slice(&auth.count.to_be_bytes())?;
-                buf.extend_from_slice(&auth.signature)
+                write(&[auth.user_presence])?;
+                write(&auth.count.to_be_bytes())?;
+                write(&auth.signature)
             }
-            Response::Version(version) => buf.extend_from_slice(version),
+            Response::Version(version) => write(version),
         }
     }
 }
 
+/// Serializes the given status word into the buffer.
+///
+/// This can be used to send an error status, or to send the result of an
+/// [`Authenticator`][] call together with [`Response::serialize_with_status`][].
+pub fn serialize_status<const S: usize>(
+    status: Error,
+    buf: &mut iso7816::Data<S>,
+) -> core::result::Result<(), SerializeError> {
+    buf.extend_from_slice(&u16::from(status).to_be_bytes())
+        .map_err(|_| SerializeError::BufferFull)
+}
+
 impl<'a, const S: usize> TryFrom<&'a iso7816::Command<S>> for Request<'a> {
     type Error = Error;
     fn try_from(apdu: &'a iso7816::Command<S>) -> Result<Request> {
@@ -192,8 +485,8 @@ impl<'a> TryFrom<iso7816::command::CommandView<'a>> for Request<'a> {
                     return Err(Error::IncorrectDataParameter);
                 }
                 Ok(Request::Register(Register {
-                    challenge: (&request[..32]).try_into().unwrap(),
-                    app_id: (&request[32..]).try_into().unwrap(),
+                    challenge: ClientDataHash::from_ref((&request[..32]).try_into().unwrap()),
+                    app_id: RpIdHash::from_ref((&request[32..]).try_into().unwrap()),
                 }))
             }
 
@@ -209,8 +502,8 @@ impl<'a> TryFrom<iso7816::command::CommandView<'a>> for Request<'a> {
                 }
                 Ok(Request::Authenticate(Authenticate {
                     control_byte,
-                    challenge: (&request[..32]).try_into().unwrap(),
-                    app_id: (&request[32..64]).try_into().unwrap(),
+                    challenge: ClientDataHash::from_ref((&request[..32]).try_into().unwrap()),
+                    app_id: RpIdHash::from_ref((&request[32..64]).try_into().unwrap()),
                     key_handle: &request[65..],
                 }))
             }
@@ -236,8 +529,12 @@ pub trait Authenticator {
         request: &authenticate::Request<'_>,
     ) -> Result<authenticate::Response>;
     /// Supported U2F version.
-    fn version() -> [u8; 6] {
-        *b"U2F_V2"
+    ///
+    /// The default implementation returns `U2F_V2`.  Authenticators can override this to return
+    /// a vendor-specific version string.
+    fn version() -> Bytes<MAX_U2F_VERSION_LENGTH> {
+        // cannot fail as the version string is shorter than the maximum length
+        Bytes::from_slice(b"U2F_V2").unwrap()
     }
 
     #[inline(never)]
@@ -306,14 +603,28 @@ mod tests {
         let Request::Register(request) = request else {
             panic!("expected register request, got: {:?}", request);
         };
-        assert_eq!(request.challenge, &input[..32]);
-        assert_eq!(request.app_id, &input[32..]);
+        assert_eq!(request.challenge.as_bytes(), &input[..32]);
+        assert_eq!(request.app_id.as_bytes(), &input[32..]);
+
+        let key_handle = [0x01; 64];
+        let public_key = [0x04; 65];
+        let signed_data = request.signed_data(&key_handle, &public_key).unwrap();
+        assert_eq!(signed_data.len(), 1 + 64 + 64 + 65);
+        assert_eq!(signed_data[0], 0x00);
+        assert_eq!(&signed_data[1..33], &input[32..]);
+        assert_eq!(&signed_data[33..65], &input[..32]);
+        assert_eq!(&signed_data[65..129], &key_handle);
+        assert_eq!(&signed_data[129..], &public_key);
+        assert_eq!(
+            request.signed_data(&key_handle, &public_key[1..]),
+            Err(Error::IncorrectDataParameter)
+        );
     }
 
     #[test]
     fn test_register_response() {
         let public_key = hex!("b174bc49c7ca254b70d2e5c207cee9cf174820ebd77ea3c65508c26da51b657c1cc6b952f8621697936482da0a6d3d3826a59095daf6cd7c03e2e60385d2f6d9");
-        let public_key = cosey::EcdhEsHkdf256PublicKey {
+        let public_key = crate::cose::EcdhEsHkdf256PublicKey {
             x: Bytes::from_slice(&public_key[..32]).unwrap(),
             y: Bytes::from_slice(&public_key[32..]).unwrap(),
         };
@@ -331,7 +642,19 @@ mod tests {
             attestation_certificate,
         );
         let mut output = Vec::<_, 1024>::new();
-        Response::Register(response).serialize(&mut output).unwrap();
+        Response::Register(response.clone())
+            .serialize(&mut output)
+            .unwrap();
+        assert_eq!(register::Response::parse(&output), Ok(response));
+        // truncated signature and certificate
+        assert_eq!(
+            register::Response::parse(&output[..output.len() - 71]),
+            Err(Error::IncorrectDataParameter)
+        );
+        assert_eq!(
+            register::Response::parse(&output[..200]),
+            Err(Error::IncorrectDataParameter)
+        );
         assert_eq!(
             output.as_slice(),
             &hex!("0504b174bc49c7ca254b70d2e5c207cee9cf174820ebd77ea3c65508c26da51b657c1cc6b952f8621697936482da0a6d3d3826a59095daf6cd7c03e2e60385d2f6d9402a552dfdb7477ed65fd84133f86196010b2215b57da75d315b7b9e8fe2e3925a6019551bab61d16591659cbaf00b4950f7abfe6660e2e006f76868b772d70c253082013c3081e4a003020102020a47901280001155957352300a06082a8648ce3d0403023017311530130603550403130c476e756262792050696c6f74301e170d3132303831343138323933325a170d3133303831343138323933325a3031312f302d0603550403132650696c6f74476e756262792d302e342e312d34373930313238303030313135353935373335323059301306072a8648ce3d020106082a8648ce3d030107034200048d617e65c9508e64bcc5673ac82a6799da3c1446682c258c463fffdf58dfd2fa3e6c378b53d795c4a4dffb4199edd7862f23abaf0203b4b8911ba0569994e101300a06082a8648ce3d0403020347003044022060cdb6061e9c22262d1aac1d96d8c70829b2366531dda268832cb836bcd30dfa0220631b1459f09e6330055722c8d89b7f48883b9089b88d60d1d9795902b30410df304502201471899bcc3987e62e8202c9b39c33c19033f7340352dba80fcab017db9230e402210082677d673d891933ade6f617e5dbde2e247e70423fd5ad7804a6d3d3961ef871"),
@@ -362,9 +685,14 @@ mod tests {
                 panic!("expected authenticate request, got: {:?}", request);
             };
             assert_eq!(request.control_byte, variant);
-            assert_eq!(request.challenge, challenge);
-            assert_eq!(request.app_id, application);
+            assert_eq!(request.challenge.as_bytes(), challenge);
+            assert_eq!(request.app_id.as_bytes(), application);
             assert_eq!(request.key_handle, key_handle);
+
+            let signed_data = request.signed_data(1, 0x01020304);
+            assert_eq!(&signed_data[..32], application);
+            assert_eq!(signed_data[32..37], [0x01, 0x01, 0x02, 0x03, 0x04]);
+            assert_eq!(&signed_data[37..], challenge);
         }
     }
 
@@ -378,15 +706,115 @@ mod tests {
             signature,
         };
         let mut output = Vec::<_, 1024>::new();
-        Response::Authenticate(response)
+        Response::Authenticate(response.clone())
             .serialize(&mut output)
             .unwrap();
+        assert_eq!(authenticate::Response::parse(&output), Ok(response.clone()));
+
+        let response = Response::Authenticate(response);
+        let mut with_status = Vec::<_, 1024>::new();
+        response.serialize_with_status(&mut with_status).unwrap();
+        assert_eq!(&with_status[..output.len()], output.as_slice());
+        assert_eq!(&with_status[output.len()..], &[0x90, 0x00]);
+
+        // the buffer is restored if the status word does not fit
+        let mut buffer = Vec::<_, 76>::from_slice(&[0xff]).unwrap();
+        assert_eq!(
+            response.serialize_with_status(&mut buffer),
+            Err(SerializeError::BufferFull)
+        );
+        assert_eq!(buffer.as_slice(), &[0xff]);
+
+        let mut buffer = Vec::<_, 2>::new();
+        serialize_status(Error::ConditionsOfUseNotSatisfied, &mut buffer).unwrap();
+        assert_eq!(buffer.as_slice(), &[0x69, 0x85]);
+
+        let response = Response::Authenticate(authenticate::Response {
+            user_presence: 1,
+            count: 1,
+            signature: Bytes::new(),
+        });
+        assert_eq!(
+            response.serialize(&mut Vec::<_, 16>::new()),
+            Err(SerializeError::InvalidResponse)
+        );
+        assert_eq!(
+            authenticate::Response::parse(&output[..5]),
+            Err(Error::IncorrectDataParameter)
+        );
         assert_eq!(
             output.as_slice(),
             &hex!("0100000001304402204b5f0cd17534cedd8c34ee09570ef542a353df4436030ce43d406de870b847780220267bb998fac9b7266eb60e7cb0b5eabdfd5ba9614f53c7b22272ec10047a923f"),
         );
     }
 
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_serialize_to_writer() {
+        let signature = &hex!("304402204b5f0cd17534cedd8c34ee09570ef542a353df4436030ce43d406de870b847780220267bb998fac9b7266eb60e7cb0b5eabdfd5ba9614f53c7b22272ec10047a923f");
+        let response = Response::Authenticate(authenticate::Response {
+            user_presence: 1,
+            count: 1,
+            signature: Bytes::from_slice(signature).unwrap(),
+        });
+        let mut expected = Vec::<_, 1024>::new();
+        response.serialize(&mut expected).unwrap();
+
+        let mut output = [0; 1024];
+        let mut writer = output.as_mut_slice();
+        response.serialize_to_writer(&mut writer).unwrap();
+        let n = 1024 - writer.len();
+        assert_eq!(&output[..n], expected.as_slice());
+
+        let mut output = [0; 16];
+        assert!(matches!(
+            response.serialize_to_writer(&mut output.as_mut_slice()),
+            Err(WriteError::Io(_))
+        ));
+
+        let response = Response::Authenticate(authenticate::Response {
+            user_presence: 1,
+            count: 1,
+            signature: Bytes::new(),
+        });
+        let mut output = [0; 16];
+        let mut writer = output.as_mut_slice();
+        assert!(matches!(
+            response.serialize_to_writer(&mut writer),
+            Err(WriteError::InvalidResponse)
+        ));
+        assert_eq!(writer.len(), 16);
+    }
+
+    #[test]
+    fn test_ctap2_error_mapping() {
+        use crate::ctap2::Error as Ctap2Error;
+
+        assert_eq!(Error::from(Ctap2Error::Success), Error::Success);
+        assert_eq!(
+            Error::from(Ctap2Error::UpRequired),
+            Error::ConditionsOfUseNotSatisfied
+        );
+        assert_eq!(
+            Error::from(Ctap2Error::Vendor(
+                crate::ctap2::VendorErrorCode::new(0xF0).unwrap()
+            )),
+            Error::UnspecifiedCheckingError
+        );
+
+        let errors = [
+            Ctap2Error::Success,
+            Ctap2Error::InvalidCommand,
+            Ctap2Error::InvalidLength,
+            Ctap2Error::InvalidParameter,
+            Ctap2Error::UpRequired,
+            Ctap2Error::KeyStoreFull,
+        ];
+        for error in errors {
+            assert_eq!(Ctap2Error::from(Error::from(error)), error);
+        }
+    }
+
     #[test]
     fn test_version_request() {
         let command = command(3, 0, 0, &[]);
@@ -396,7 +824,7 @@ mod tests {
 
     #[test]
     fn test_version_response() {
-        let response = Response::Version(*b"U2F_V2");
+        let response = Response::Version(Bytes::from_slice(b"U2F_V2").unwrap());
         let mut output = Vec::<_, 1024>::new();
         response.serialize(&mut output).unwrap();
         assert_eq!(output.as_slice(), b"U2F_V2");
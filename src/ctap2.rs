@@ -4,18 +4,28 @@
 //! [`Response`].
 use bitflags::bitflags;
 use cbor_smol::cbor_deserialize;
+use core::fmt::{self, Display, Formatter};
 use serde::{Deserialize, Serialize};
 
-use crate::{sizes::*, Bytes, TryFromStrError, Vec};
+use crate::{sizes::*, webauthn::RpIdHash, Bytes, Vec};
 
 pub use crate::operation::{Operation, VendorOperation};
 
+pub mod attestation;
 pub mod client_pin;
 pub mod credential_management;
 pub mod get_assertion;
 pub mod get_info;
 pub mod large_blobs;
 pub mod make_credential;
+#[cfg(feature = "pin-protocol")]
+pub mod pin_protocol;
+
+pub use attestation::{
+    AttestationFormatsPreference, AttestationStatement, AttestationStatementFormat,
+    CertificateChain, NoneAttestationStatement, PackedAttestationStatement,
+    PreferredAttestationFormat,
+};
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -49,16 +59,73 @@ pub enum Request<'a> {
     Vendor(crate::operation::VendorOperation),
 }
 
+#[derive(Debug)]
 pub enum CtapMappingError {
     InvalidCommand(u8),
     ParsingError(cbor_smol::Error),
+    /// The parameters of a request could not be parsed.
+    InvalidParameters {
+        operation: Operation,
+        /// The missing required key, if the request could not be parsed because of a missing
+        /// parameter.
+        missing_key: Option<u8>,
+        error: cbor_smol::Error,
+    },
+}
+
+impl CtapMappingError {
+    fn invalid_parameters(operation: Operation, data: &[u8], error: cbor_smol::Error) -> Self {
+        let missing_key = if matches!(error, cbor_smol::Error::SerdeMissingField) {
+            let required_keys: &[u8] = match operation {
+                Operation::MakeCredential => &[0x01, 0x02, 0x03, 0x04],
+                Operation::GetAssertion => &[0x01, 0x02],
+                Operation::ClientPin => &[0x01, 0x02],
+                Operation::CredentialManagement | Operation::PreviewCredentialManagement => &[0x01],
+                Operation::LargeBlobs => &[0x03],
+                _ => &[],
+            };
+            required_keys
+                .iter()
+                .copied()
+                .find(|&key| crate::cbor::map_contains_key(data, key.into()) == Some(false))
+        } else {
+            None
+        };
+        Self::InvalidParameters {
+            operation,
+            missing_key,
+            error,
+        }
+    }
+}
+
+impl Display for CtapMappingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCommand(cmd) => write!(f, "invalid command {cmd:#04x}"),
+            Self::ParsingError(error) => write!(f, "failed to parse request: {error}"),
+            Self::InvalidParameters {
+                operation,
+                missing_key: Some(key),
+                ..
+            } => write!(f, "{operation} request is missing key {key:#04x}"),
+            Self::InvalidParameters {
+                operation, error, ..
+            } => write!(f, "failed to parse {operation} request: {error}"),
+        }
+    }
 }
 
+impl core::error::Error for CtapMappingError {}
+
 impl From<CtapMappingError> for Error {
     fn from(mapping_error: CtapMappingError) -> Error {
         match mapping_error {
             CtapMappingError::InvalidCommand(_cmd) => Error::InvalidCommand,
-            CtapMappingError::ParsingError(cbor_error) => match cbor_error {
+            CtapMappingError::ParsingError(cbor_error)
+            | CtapMappingError::InvalidParameters {
+                error: cbor_error, ..
+            } => match cbor_error {
                 cbor_smol::Error::SerdeMissingField => Error::MissingParameter,
                 _ => Error::InvalidCbor,
             },
@@ -67,13 +134,81 @@ impl From<CtapMappingError> for Error {
 }
 
 impl<'a> Request<'a> {
+    /// Returns the operation of this request.
+    ///
+    /// Requests received with the preview credential management command are reported as
+    /// [`Operation::CredentialManagement`][].
+    pub fn operation(&self) -> Operation {
+        match self {
+            Self::MakeCredential(_) => Operation::MakeCredential,
+            Self::GetAssertion(_) => Operation::GetAssertion,
+            Self::GetNextAssertion => Operation::GetNextAssertion,
+            Self::GetInfo => Operation::GetInfo,
+            Self::ClientPin(_) => Operation::ClientPin,
+            Self::Reset => Operation::Reset,
+            Self::CredentialManagement(_) => Operation::CredentialManagement,
+            Self::Selection => Operation::Selection,
+            Self::LargeBlobs(_) => Operation::LargeBlobs,
+            Self::Vendor(operation) => Operation::Vendor(*operation),
+        }
+    }
+
     /// Deserialize from CBOR where the first byte denotes the operation.
+    ///
+    /// See the [`TryFrom`][] implementation for a variant of this method that returns a more
+    /// detailed error.
     #[inline(never)]
     pub fn deserialize(data: &'a [u8]) -> Result<Self> {
+        Self::try_from(data).map_err(From::from)
+    }
+
+    /// Deserialize from CBOR where the first byte denotes the operation, rejecting messages that
+    /// are longer than `max_msg_size`.
+    ///
+    /// `max_msg_size` should be the value advertised in [`get_info::Response::max_msg_size`][].
+    /// If the message is too long, [`Error::RequestTooLarge`][] is returned.
+    #[inline(never)]
+    pub fn deserialize_with_limit(data: &'a [u8], max_msg_size: usize) -> Result<Self> {
+        if data.len() > max_msg_size {
+            return Err(Error::RequestTooLarge);
+        }
+        Self::deserialize(data)
+    }
+
+    /// Deserialize from CBOR where the first byte denotes the operation, enforcing the CTAP2
+    /// canonical CBOR encoding.
+    ///
+    /// In addition to [`Request::deserialize`][], this rejects request parameters with
+    /// non-canonical map key ordering, duplicate map keys, non-minimal integer or length
+    /// encodings and trailing data with [`Error::InvalidCbor`][].
+    #[inline(never)]
+    pub fn deserialize_strict(data: &'a [u8]) -> Result<Self> {
+        let request = Self::deserialize(data)?;
+        let has_parameters = matches!(
+            request,
+            Self::MakeCredential(_)
+                | Self::GetAssertion(_)
+                | Self::ClientPin(_)
+                | Self::CredentialManagement(_)
+                | Self::LargeBlobs(_)
+        );
+        if has_parameters && !crate::cbor::is_canonical(&data[1..]) {
+            return Err(Error::InvalidCbor);
+        }
+        Ok(request)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Request<'a> {
+    type Error = CtapMappingError;
+
+    /// Deserialize from CBOR where the first byte denotes the operation.
+    #[inline(never)]
+    fn try_from(data: &'a [u8]) -> core::result::Result<Self, CtapMappingError> {
         if data.is_empty() {
-            return Err(
-                CtapMappingError::ParsingError(cbor_smol::Error::DeserializeUnexpectedEnd).into(),
-            );
+            return Err(CtapMappingError::ParsingError(
+                cbor_smol::Error::DeserializeUnexpectedEnd,
+            ));
         }
 
         let (&op, data) = data.split_first().ok_or(CtapMappingError::ParsingError(
@@ -86,21 +221,29 @@ impl<'a> Request<'a> {
         })?;
 
         info!("deser {:?}", operation);
+        let parsing_error = |error| CtapMappingError::invalid_parameters(operation, data, error);
         Ok(match operation {
-            Operation::MakeCredential => Request::MakeCredential(
-                cbor_deserialize(data).map_err(CtapMappingError::ParsingError)?,
-            ),
+            Operation::MakeCredential => {
+                let mut request: make_credential::Request<'a> =
+                    cbor_deserialize(data).map_err(parsing_error)?;
+                request.capture_exclude_list(data);
+                Request::MakeCredential(request)
+            }
 
-            Operation::GetAssertion => Request::GetAssertion(
-                cbor_deserialize(data).map_err(CtapMappingError::ParsingError)?,
-            ),
+            Operation::GetAssertion => {
+                let mut request: get_assertion::Request<'a> =
+                    cbor_deserialize(data).map_err(parsing_error)?;
+                request.capture_allow_list(data);
+                Request::GetAssertion(request)
+            }
 
             Operation::GetNextAssertion => Request::GetNextAssertion,
 
             Operation::CredentialManagement | Operation::PreviewCredentialManagement => {
-                Request::CredentialManagement(
-                    cbor_deserialize(data).map_err(CtapMappingError::ParsingError)?,
-                )
+                let mut request: credential_management::Request<'a> =
+                    cbor_deserialize(data).map_err(parsing_error)?;
+                request.capture_sub_command_params(data);
+                Request::CredentialManagement(request)
             }
 
             Operation::Reset => Request::Reset,
@@ -110,11 +253,11 @@ impl<'a> Request<'a> {
             Operation::GetInfo => Request::GetInfo,
 
             Operation::ClientPin => {
-                Request::ClientPin(cbor_deserialize(data).map_err(CtapMappingError::ParsingError)?)
+                Request::ClientPin(cbor_deserialize(data).map_err(parsing_error)?)
             }
 
             Operation::LargeBlobs => {
-                Request::LargeBlobs(cbor_deserialize(data).map_err(CtapMappingError::ParsingError)?)
+                Request::LargeBlobs(cbor_deserialize(data).map_err(parsing_error)?)
             }
 
             // NB: FIDO Alliance "stole" 0x40 and 0x41, so these are not available
@@ -122,7 +265,7 @@ impl<'a> Request<'a> {
 
             Operation::BioEnrollment | Operation::PreviewBioEnrollment | Operation::Config => {
                 debug_now!("unhandled CBOR operation {:?}", operation);
-                return Err(CtapMappingError::InvalidCommand(op).into());
+                return Err(CtapMappingError::InvalidCommand(op));
             }
         })
     }
@@ -149,32 +292,257 @@ pub enum Response {
 impl Response {
     #[inline(never)]
     pub fn serialize<const N: usize>(&self, buffer: &mut Vec<u8, N>) {
-        buffer.resize_default(buffer.capacity()).ok();
-        let (status, data) = buffer.split_first_mut().unwrap();
         use cbor_smol::cbor_serialize;
         use Response::*;
-        let outcome = match self {
-            GetInfo(response) => cbor_serialize(response, data),
-            MakeCredential(response) => cbor_serialize(response, data),
-            ClientPin(response) => cbor_serialize(response, data),
-            GetAssertion(response) | GetNextAssertion(response) => cbor_serialize(response, data),
-            CredentialManagement(response) => cbor_serialize(response, data),
-            LargeBlobs(response) => cbor_serialize(response, data),
-            Reset | Selection | Vendor => Ok([].as_slice()),
+        serialize_response(buffer, |data| {
+            let slice = match self {
+                GetInfo(response) => cbor_serialize(response, data),
+                MakeCredential(response) => cbor_serialize(response, data),
+                ClientPin(response) => cbor_serialize(response, data),
+                GetAssertion(response) | GetNextAssertion(response) => {
+                    cbor_serialize(response, data)
+                }
+                CredentialManagement(response) => cbor_serialize(response, data),
+                LargeBlobs(response) => cbor_serialize(response, data),
+                Reset | Selection | Vendor => Ok([].as_slice()),
+            }?;
+            Ok(slice.len())
+        })
+    }
+}
+
+impl Response {
+    /// Returns the length of the serialized response including the status byte.
+    ///
+    /// This is the number of bytes that [`Response::serialize`][] writes if the buffer is large
+    /// enough.
+    pub fn serialized_len(&self) -> Result<usize> {
+        use Response::*;
+        let len = match self {
+            GetInfo(response) => response.serialized_len()?,
+            MakeCredential(response) => response.serialized_len()?,
+            ClientPin(response) => response.serialized_len()?,
+            GetAssertion(response) | GetNextAssertion(response) => response.serialized_len()?,
+            CredentialManagement(response) => response.serialized_len()?,
+            LargeBlobs(response) => response.serialized_len()?,
+            Reset | Selection | Vendor => 0,
         };
-        if let Ok(slice) = outcome {
-            *status = 0;
-            // Instead of an empty CBOR map (0xA0), we return an empty response
-            if slice == [0xA0] {
-                buffer.resize_default(1).ok();
-            } else {
-                let l = slice.len();
-                buffer.resize_default(l + 1).ok();
+        Ok(response_len(len))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+/// Enum of CTAP2 responses that borrow their large buffers.
+///
+/// This can be used instead of [`Response`] if the authenticator data, the signature or the large
+/// blob fragment are already stored in a buffer owned by the caller, so that they do not have to
+/// be copied into the owned response types.  It is serialized in the same format as
+/// [`Response`].
+pub enum ResponseRef<'a> {
+    MakeCredential(make_credential::ResponseRef<'a>),
+    GetAssertion(get_assertion::ResponseRef<'a>),
+    GetNextAssertion(get_assertion::ResponseRef<'a>),
+    LargeBlobs(large_blobs::ResponseRef<'a>),
+}
+
+impl ResponseRef<'_> {
+    /// Serializes the response including the status byte into the given buffer.
+    ///
+    /// See [`Response::serialize`][].
+    #[inline(never)]
+    pub fn serialize<const N: usize>(&self, buffer: &mut Vec<u8, N>) {
+        use cbor_smol::cbor_serialize;
+        use ResponseRef::*;
+        serialize_response(buffer, |data| {
+            let slice = match self {
+                MakeCredential(response) => cbor_serialize(response, data),
+                GetAssertion(response) | GetNextAssertion(response) => {
+                    cbor_serialize(response, data)
+                }
+                LargeBlobs(response) => cbor_serialize(response, data),
+            }?;
+            Ok(slice.len())
+        })
+    }
+
+    /// Returns the length of the serialized response including the status byte.
+    ///
+    /// See [`Response::serialized_len`][].
+    pub fn serialized_len(&self) -> Result<usize> {
+        use ResponseRef::*;
+        let len = match self {
+            MakeCredential(response) => response.serialized_len()?,
+            GetAssertion(response) | GetNextAssertion(response) => response.serialized_len()?,
+            LargeBlobs(response) => response.serialized_len()?,
+        };
+        Ok(response_len(len))
+    }
+}
+
+/// Returns the length of a response with the status byte and CBOR data of the given length.
+fn response_len(data_len: usize) -> usize {
+    // an empty CBOR map (0xA0) is replaced with an empty response, see serialize_response
+    if data_len == 1 {
+        1
+    } else {
+        data_len + 1
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl Response {
+    /// Serializes the response including the status byte into the given writer.
+    ///
+    /// In contrast to [`Response::serialize`][], this does not require a buffer that can hold the
+    /// entire response.  Note that parts of the response may already have been written if an
+    /// error occurs.
+    #[inline(never)]
+    pub fn serialize_to_writer<W: embedded_io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> core::result::Result<(), WriteError<W::Error>> {
+        use Response::*;
+        match self {
+            GetInfo(response) => write_response(writer, response),
+            MakeCredential(response) => write_response(writer, response),
+            ClientPin(response) => write_response(writer, response),
+            GetAssertion(response) | GetNextAssertion(response) => write_response(writer, response),
+            CredentialManagement(response) => write_response(writer, response),
+            LargeBlobs(response) => write_response(writer, response),
+            Reset | Selection | Vendor => writer.write_all(&[0]).map_err(WriteError::Io),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl ResponseRef<'_> {
+    /// Serializes the response including the status byte into the given writer.
+    ///
+    /// See [`Response::serialize_to_writer`][].
+    #[inline(never)]
+    pub fn serialize_to_writer<W: embedded_io::Write>(
+        &self,
+        writer: &mut W,
+    ) -> core::result::Result<(), WriteError<W::Error>> {
+        use ResponseRef::*;
+        match self {
+            MakeCredential(response) => write_response(writer, response),
+            GetAssertion(response) | GetNextAssertion(response) => write_response(writer, response),
+            LargeBlobs(response) => write_response(writer, response),
+        }
+    }
+}
+
+/// Error returned by [`Response::serialize_to_writer`][] and [`ResponseRef::serialize_to_writer`][].
+#[cfg(feature = "embedded-io")]
+#[derive(Debug)]
+pub enum WriteError<E> {
+    /// The underlying writer returned an error.
+    Io(E),
+    /// The response could not be serialized.
+    Serialization,
+}
+
+#[cfg(feature = "embedded-io")]
+fn write_response<W, T>(
+    writer: &mut W,
+    response: &T,
+) -> core::result::Result<(), WriteError<W::Error>>
+where
+    W: embedded_io::Write,
+    T: Serialize,
+{
+    writer.write_all(&[0]).map_err(WriteError::Io)?;
+    let mut serializer = cbor_smol::ser::Serializer::new(ResponseWriter {
+        writer,
+        first: None,
+        flushed: false,
+        error: None,
+    });
+    let result = response.serialize(&mut serializer);
+    let mut response_writer = serializer.into_inner();
+    if let Some(error) = response_writer.error.take() {
+        return Err(WriteError::Io(error));
+    }
+    result.map_err(|_| WriteError::Serialization)?;
+    response_writer.finish()
+}
+
+/// Adapter that writes CBOR data into an [`embedded_io::Write`][] implementation.
+///
+/// The first byte is held back so that an empty map can be replaced with an empty response, see
+/// [`Response::serialize`][].
+#[cfg(feature = "embedded-io")]
+struct ResponseWriter<'w, W: embedded_io::Write> {
+    writer: &'w mut W,
+    first: Option<u8>,
+    flushed: bool,
+    error: Option<W::Error>,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: embedded_io::Write> ResponseWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> core::result::Result<(), cbor_smol::Error> {
+        self.writer.write_all(data).map_err(|error| {
+            self.error = Some(error);
+            cbor_smol::Error::SerializeBufferFull
+        })
+    }
+
+    fn finish(self) -> core::result::Result<(), WriteError<W::Error>> {
+        match self.first {
+            Some(first) if !self.flushed && first != 0xA0 => {
+                self.writer.write_all(&[first]).map_err(WriteError::Io)
             }
-        } else {
-            *status = Error::Other as u8;
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: embedded_io::Write> cbor_smol::ser::Writer for ResponseWriter<'_, W> {
+    type Error = cbor_smol::Error;
+
+    fn write_all(&mut self, mut buf: &[u8]) -> core::result::Result<(), Self::Error> {
+        if !self.flushed {
+            if self.first.is_none() {
+                let Some((&first, rest)) = buf.split_first() else {
+                    return Ok(());
+                };
+                self.first = Some(first);
+                buf = rest;
+            }
+            if buf.is_empty() {
+                return Ok(());
+            }
+            if let Some(first) = self.first {
+                self.write(&[first])?;
+            }
+            self.flushed = true;
+        }
+        self.write(buf)
+    }
+}
+
+/// Writes the status byte and the CBOR data produced by `serialize` into `buffer`.
+fn serialize_response<const N: usize, F>(buffer: &mut Vec<u8, N>, serialize: F)
+where
+    F: FnOnce(&mut [u8]) -> core::result::Result<usize, cbor_smol::Error>,
+{
+    buffer.resize_default(buffer.capacity()).ok();
+    let (status, data) = buffer.split_first_mut().unwrap();
+    if let Ok(l) = serialize(data) {
+        *status = 0;
+        // Instead of an empty CBOR map (0xA0), we return an empty response
+        if l == 1 && data[0] == 0xA0 {
             buffer.resize_default(1).ok();
+        } else {
+            buffer.resize_default(l + 1).ok();
         }
+    } else {
+        *status = Error::Other.into();
+        buffer.resize_default(1).ok();
     }
 }
 
@@ -197,18 +565,57 @@ bitflags! {
     pub struct AuthenticatorDataFlags: u8 {
         const USER_PRESENCE = 1 << 0;
         const USER_VERIFIED = 1 << 2;
+        /// The credential is eligible to be backed up (BE).
+        const BACKUP_ELIGIBILITY = 1 << 3;
+        /// The credential is currently backed up (BS).
+        const BACKUP_STATE = 1 << 4;
         const ATTESTED_CREDENTIAL_DATA = 1 << 6;
         const EXTENSION_DATA = 1 << 7;
     }
 }
 
+impl AuthenticatorDataFlags {
+    /// Returns the backup flags for a credential with the given backup eligibility and state.
+    ///
+    /// Returns [`Error::InvalidParameter`][] if the credential is backed up but not eligible for
+    /// backups.
+    pub fn backup(eligible: bool, backed_up: bool) -> Result<Self> {
+        let mut flags = Self::empty();
+        flags.set(Self::BACKUP_ELIGIBILITY, eligible);
+        flags.set(Self::BACKUP_STATE, backed_up);
+        if flags.is_valid() {
+            Ok(flags)
+        } else {
+            Err(Error::InvalidParameter)
+        }
+    }
+
+    /// Sets the backup flags, see [`AuthenticatorDataFlags::backup`][].
+    pub fn with_backup(self, eligible: bool, backed_up: bool) -> Result<Self> {
+        Ok((self - Self::BACKUP_ELIGIBILITY - Self::BACKUP_STATE)
+            | Self::backup(eligible, backed_up)?)
+    }
+
+    /// Checks that the flag combination is valid, i. e. that the backup state flag is only set
+    /// together with the backup eligibility flag.
+    pub fn is_valid(&self) -> bool {
+        !self.contains(Self::BACKUP_STATE) || self.contains(Self::BACKUP_ELIGIBILITY)
+    }
+}
+
 pub trait SerializeAttestedCredentialData {
-    fn serialize(&self, buffer: &mut SerializedAuthenticatorData) -> Result<()>;
+    fn serialize<const N: usize>(&self, buffer: &mut Bytes<N>) -> Result<()>;
+}
+
+pub trait DeserializeAttestedCredentialData<'a>: Sized {
+    /// Parses the attested credential data at the start of `data` and returns the remaining
+    /// data.
+    fn deserialize(data: &'a [u8]) -> Result<(Self, &'a [u8])>;
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct AuthenticatorData<'a, A, E> {
-    pub rp_id_hash: &'a [u8; 32],
+    pub rp_id_hash: &'a RpIdHash,
     pub flags: AuthenticatorDataFlags,
     pub sign_count: u32,
     pub attested_credential_data: Option<A>,
@@ -223,10 +630,30 @@ impl<'a, A: SerializeAttestedCredentialData, E: serde::Serialize> AuthenticatorD
     #[inline(never)]
     pub fn serialize(&self) -> Result<SerializedAuthenticatorData> {
         let mut bytes = SerializedAuthenticatorData::new();
+        self.serialize_into(&mut bytes)?;
+        Ok(bytes)
+    }
 
+    /// Serializes the authenticator data by appending it to the given buffer.
+    ///
+    /// This makes it possible to write the authenticator data directly into a response buffer
+    /// instead of copying it from the buffer returned by [`AuthenticatorData::serialize`][].
+    /// Returns the number of bytes written.  If serialization fails, the buffer is restored to
+    /// its previous length.
+    #[inline(never)]
+    pub fn serialize_into<const N: usize>(&self, buffer: &mut Bytes<N>) -> Result<usize> {
+        let start = buffer.len();
+        if let Err(err) = self.serialize_to(buffer) {
+            buffer.truncate(start);
+            return Err(err);
+        }
+        Ok(buffer.len() - start)
+    }
+
+    fn serialize_to<const N: usize>(&self, bytes: &mut Bytes<N>) -> Result<()> {
         // 32 bytes, the RP id's hash
         bytes
-            .extend_from_slice(self.rp_id_hash)
+            .extend_from_slice(self.rp_id_hash.as_bytes())
             .map_err(|_| Error::Other)?;
         // flags
         bytes.push(self.flags.bits()).map_err(|_| Error::Other)?;
@@ -237,183 +664,489 @@ impl<'a, A: SerializeAttestedCredentialData, E: serde::Serialize> AuthenticatorD
 
         // the attested credential data
         if let Some(attested_credential_data) = &self.attested_credential_data {
-            attested_credential_data.serialize(&mut bytes)?;
+            attested_credential_data.serialize(bytes)?;
         }
 
         // the extensions data
         if let Some(extensions) = self.extensions.as_ref() {
-            cbor_smol::cbor_serialize_to(extensions, &mut bytes).map_err(|_| Error::Other)?;
+            cbor_smol::cbor_serialize_to(extensions, bytes).map_err(|_| Error::Other)?;
         }
 
-        Ok(bytes)
+        Ok(())
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
-#[non_exhaustive]
-#[serde(untagged)]
-#[allow(clippy::large_enum_variant)]
-pub enum AttestationStatement {
-    None(NoneAttestationStatement),
-    Packed(PackedAttestationStatement),
+impl<'a, A: DeserializeAttestedCredentialData<'a>> AuthenticatorData<'a, A, &'a [u8]> {
+    /// Parses serialized authenticator data.
+    ///
+    /// The attested credential data and the extensions are only parsed if the corresponding flags
+    /// are set.  The extensions are returned as raw CBOR data.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        // 32 bytes RP ID hash, 1 byte flags, 4 bytes signature counter
+        if data.len() < 37 {
+            return Err(Error::InvalidLength);
+        }
+        let (rp_id_hash, data) = data.split_at(32);
+        let (header, mut data) = data.split_at(5);
+        let flags = AuthenticatorDataFlags::from_bits_truncate(header[0]);
+        let sign_count = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+
+        let attested_credential_data =
+            if flags.contains(AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA) {
+                let (attested_credential_data, rest) = A::deserialize(data)?;
+                data = rest;
+                Some(attested_credential_data)
+            } else {
+                None
+            };
+
+        let extensions = if flags.contains(AuthenticatorDataFlags::EXTENSION_DATA) {
+            let len = crate::cbor::item_len(data).ok_or(Error::InvalidCbor)?;
+            let (extensions, rest) = data.split_at(len);
+            data = rest;
+            Some(extensions)
+        } else {
+            None
+        };
+
+        if !data.is_empty() {
+            return Err(Error::InvalidLength);
+        }
+
+        Ok(Self {
+            rp_id_hash: RpIdHash::from_ref(rp_id_hash.try_into().unwrap()),
+            flags,
+            sign_count,
+            attested_credential_data,
+            extensions,
+        })
+    }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+impl<'a, A> AuthenticatorData<'a, A, &'a [u8]> {
+    /// Returns the raw CBOR value of the extension output with the given identifier.
+    ///
+    /// Returns [`Error::InvalidCbor`][] if the extensions are not a well-formed CBOR map.
+    pub fn extension(&self, identifier: &str) -> Result<Option<&'a [u8]>> {
+        match self.extensions {
+            Some(extensions) => {
+                crate::cbor::map_text_value(extensions, identifier).ok_or(Error::InvalidCbor)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Deserializes the extension output with the given identifier.
+    ///
+    /// Returns [`Error::InvalidCbor`][] if the extensions are not a well-formed CBOR map or if
+    /// the output cannot be deserialized as `T`.
+    pub fn extension_output<T: Deserialize<'a>>(&self, identifier: &str) -> Result<Option<T>> {
+        self.extension(identifier)?
+            .map(|value| cbor_deserialize(value).map_err(|_| Error::InvalidCbor))
+            .transpose()
+    }
+
+    /// Returns the `credProtect` output of a makeCredential response.
+    pub fn cred_protect(
+        &self,
+    ) -> Result<Option<credential_management::CredentialProtectionPolicy>> {
+        self.extension_output("credProtect")
+    }
+
+    /// Returns the `hmac-secret` output of a makeCredential response, i.e. whether the credential
+    /// supports the extension.
+    pub fn hmac_secret(&self) -> Result<Option<bool>> {
+        self.extension_output("hmac-secret")
+    }
+
+    /// Returns the `hmac-secret` output of a getAssertion response, i.e. the encrypted outputs.
+    pub fn hmac_secret_outputs(&self) -> Result<Option<get_assertion::EncryptedOutputs>> {
+        self.extension_output("hmac-secret")
+    }
+
+    /// Returns the `credBlob` output of a makeCredential response, i.e. whether the credBlob has
+    /// been stored.
+    pub fn cred_blob_stored(&self) -> Result<Option<bool>> {
+        self.extension_output("credBlob")
+    }
+
+    /// Returns the `credBlob` output of a getAssertion response, i.e. the stored credBlob.
+    pub fn cred_blob(&self) -> Result<Option<&'a [u8]>> {
+        self.extension_output::<&serde_bytes::Bytes>("credBlob")
+            .map(|cred_blob| cred_blob.map(|cred_blob| &**cred_blob))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[non_exhaustive]
-#[serde(into = "&str", try_from = "&str")]
-pub enum AttestationStatementFormat {
-    None,
-    Packed,
+pub enum Error {
+    // 0x00
+    Success,
+    // 0x01
+    InvalidCommand,
+    // 0x02
+    InvalidParameter,
+    // 0x03
+    InvalidLength,
+    // 0x04
+    InvalidSeq,
+    // 0x05
+    Timeout,
+    // 0x06
+    ChannelBusy,
+    // 0x0A
+    LockRequired,
+    // 0x0B
+    InvalidChannel,
+    // 0x11
+    CborUnexpectedType,
+    // 0x12
+    InvalidCbor,
+    // 0x14
+    MissingParameter,
+    // 0x15
+    LimitExceeded,
+    // 0x16
+    UnsupportedExtension,
+    // 0x17
+    FingerprintDatabaseFull,
+    // 0x18
+    LargeBlobStorageFull,
+    // 0x19
+    CredentialExcluded,
+    // 0x21
+    Processing,
+    // 0x22
+    InvalidCredential,
+    // 0x23
+    UserActionPending,
+    // 0x24
+    OperationPending,
+    // 0x25
+    NoOperations,
+    // 0x26
+    UnsupportedAlgorithm,
+    // 0x27
+    OperationDenied,
+    // 0x28
+    KeyStoreFull,
+    // 0x29
+    NotBusy,
+    // 0x2A
+    NoOperationPending,
+    // 0x2B
+    UnsupportedOption,
+    // 0x2C
+    InvalidOption,
+    // 0x2D
+    KeepaliveCancel,
+    // 0x2E
+    NoCredentials,
+    // 0x2F
+    UserActionTimeout,
+    // 0x30
+    NotAllowed,
+    // 0x31
+    PinInvalid,
+    // 0x32
+    PinBlocked,
+    // 0x33
+    PinAuthInvalid,
+    // 0x34
+    PinAuthBlocked,
+    // 0x35
+    PinNotSet,
+    // 0x36
+    PinRequired,
+    // 0x37
+    PinPolicyViolation,
+    // 0x38
+    PinTokenExpired,
+    // 0x39
+    RequestTooLarge,
+    // 0x3A
+    ActionTimeout,
+    // 0x3B
+    UpRequired,
+    // 0x3C
+    UvBlocked,
+    // 0x3D
+    IntegrityFailure,
+    // 0x3E
+    InvalidSubcommand,
+    // 0x3F
+    UvInvalid,
+    // 0x40
+    UnauthorizedPermission,
+    // 0x7F
+    Other,
+    // 0xDF
+    SpecLast,
+    /// Extension specific error, from 0xE0 to 0xEF
+    Extension(ExtensionErrorCode),
+    /// Vendor specific error, from 0xF0 to 0xFF
+    Vendor(VendorErrorCode),
 }
 
-impl AttestationStatementFormat {
-    const NONE: &'static str = "none";
-    const PACKED: &'static str = "packed";
+impl Error {
+    pub const EXTENSION_FIRST: u8 = 0xE0;
+    pub const EXTENSION_LAST: u8 = 0xEF;
+    pub const VENDOR_FIRST: u8 = 0xF0;
+    pub const VENDOR_LAST: u8 = 0xFF;
 }
 
-impl From<AttestationStatementFormat> for &str {
-    fn from(format: AttestationStatementFormat) -> Self {
-        match format {
-            AttestationStatementFormat::None => AttestationStatementFormat::NONE,
-            AttestationStatementFormat::Packed => AttestationStatementFormat::PACKED,
+/// An extension specific error code in the range 0xE0 to 0xEF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExtensionErrorCode(u8);
+
+impl ExtensionErrorCode {
+    /// Returns `None` if `code` is not in the range for extension specific errors.
+    pub const fn new(code: u8) -> Option<Self> {
+        match code {
+            Error::EXTENSION_FIRST..=Error::EXTENSION_LAST => Some(Self(code)),
+            _ => None,
         }
     }
-}
 
-impl TryFrom<&str> for AttestationStatementFormat {
-    type Error = TryFromStrError;
+    pub const fn code(self) -> u8 {
+        self.0
+    }
+}
 
-    fn try_from(s: &str) -> core::result::Result<Self, Self::Error> {
-        match s {
-            Self::NONE => Ok(Self::None),
-            Self::PACKED => Ok(Self::Packed),
-            _ => Err(TryFromStrError),
-        }
+impl From<ExtensionErrorCode> for u8 {
+    fn from(code: ExtensionErrorCode) -> u8 {
+        code.0
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
-pub struct NoneAttestationStatement {}
+/// A vendor specific error code in the range 0xF0 to 0xFF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VendorErrorCode(u8);
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
-pub struct PackedAttestationStatement {
-    pub alg: i32,
-    pub sig: Bytes<ASN1_SIGNATURE_LENGTH>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub x5c: Option<Vec<Bytes<1024>, 1>>,
-}
+impl VendorErrorCode {
+    /// Returns `None` if `code` is not in the range for vendor specific errors.
+    pub const fn new(code: u8) -> Option<Self> {
+        match code {
+            Error::VENDOR_FIRST..=Error::VENDOR_LAST => Some(Self(code)),
+            _ => None,
+        }
+    }
 
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct AttestationFormatsPreference {
-    pub(crate) known_formats: Vec<AttestationStatementFormat, 2>,
-    pub(crate) unknown: bool,
+    pub const fn code(self) -> u8 {
+        self.0
+    }
 }
 
-impl AttestationFormatsPreference {
-    pub fn known_formats(&self) -> &[AttestationStatementFormat] {
-        &self.known_formats
+impl From<VendorErrorCode> for u8 {
+    fn from(code: VendorErrorCode) -> u8 {
+        code.0
     }
+}
+
+/// Error returned by the [`TryFrom<u8>`][] implementation of [`Error`][] for codes that are not
+/// defined by the specification.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UnknownErrorCode(pub u8);
 
-    pub fn includes_unknown_formats(&self) -> bool {
-        self.unknown
+impl Display for UnknownErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown CTAP2 error code {:#04x}", self.0)
     }
 }
 
-impl<'de> Deserialize<'de> for AttestationFormatsPreference {
-    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        struct ValueVisitor;
+impl core::error::Error for UnknownErrorCode {}
 
-        impl<'de> serde::de::Visitor<'de> for ValueVisitor {
-            type Value = AttestationFormatsPreference;
+impl From<Error> for u8 {
+    fn from(error: Error) -> u8 {
+        use Error::*;
+        match error {
+            Success => 0x00,
+            InvalidCommand => 0x01,
+            InvalidParameter => 0x02,
+            InvalidLength => 0x03,
+            InvalidSeq => 0x04,
+            Timeout => 0x05,
+            ChannelBusy => 0x06,
+            LockRequired => 0x0A,
+            InvalidChannel => 0x0B,
+            CborUnexpectedType => 0x11,
+            InvalidCbor => 0x12,
+            MissingParameter => 0x14,
+            LimitExceeded => 0x15,
+            UnsupportedExtension => 0x16,
+            FingerprintDatabaseFull => 0x17,
+            LargeBlobStorageFull => 0x18,
+            CredentialExcluded => 0x19,
+            Processing => 0x21,
+            InvalidCredential => 0x22,
+            UserActionPending => 0x23,
+            OperationPending => 0x24,
+            NoOperations => 0x25,
+            UnsupportedAlgorithm => 0x26,
+            OperationDenied => 0x27,
+            KeyStoreFull => 0x28,
+            NotBusy => 0x29,
+            NoOperationPending => 0x2A,
+            UnsupportedOption => 0x2B,
+            InvalidOption => 0x2C,
+            KeepaliveCancel => 0x2D,
+            NoCredentials => 0x2E,
+            UserActionTimeout => 0x2F,
+            NotAllowed => 0x30,
+            PinInvalid => 0x31,
+            PinBlocked => 0x32,
+            PinAuthInvalid => 0x33,
+            PinAuthBlocked => 0x34,
+            PinNotSet => 0x35,
+            PinRequired => 0x36,
+            PinPolicyViolation => 0x37,
+            PinTokenExpired => 0x38,
+            RequestTooLarge => 0x39,
+            ActionTimeout => 0x3A,
+            UpRequired => 0x3B,
+            UvBlocked => 0x3C,
+            IntegrityFailure => 0x3D,
+            InvalidSubcommand => 0x3E,
+            UvInvalid => 0x3F,
+            UnauthorizedPermission => 0x40,
+            Other => 0x7F,
+            SpecLast => 0xDF,
+            Extension(code) => code.into(),
+            Vendor(code) => code.into(),
+        }
+    }
+}
 
-            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
-                formatter.write_str("a sequence")
-            }
+impl TryFrom<u8> for Error {
+    type Error = UnknownErrorCode;
 
-            fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
-            where
-                A: serde::de::SeqAccess<'de>,
-            {
-                let mut preference = AttestationFormatsPreference::default();
-                while let Some(value) = seq.next_element::<&str>()? {
-                    if let Ok(format) = AttestationStatementFormat::try_from(value) {
-                        preference.known_formats.push(format).ok();
-                    } else {
-                        preference.unknown = true;
-                    }
-                }
-                Ok(preference)
+    fn try_from(from: u8) -> core::result::Result<Error, UnknownErrorCode> {
+        use Error::*;
+        Ok(match from {
+            0x00 => Success,
+            0x01 => InvalidCommand,
+            0x02 => InvalidParameter,
+            0x03 => InvalidLength,
+            0x04 => InvalidSeq,
+            0x05 => Timeout,
+            0x06 => ChannelBusy,
+            0x0A => LockRequired,
+            0x0B => InvalidChannel,
+            0x11 => CborUnexpectedType,
+            0x12 => InvalidCbor,
+            0x14 => MissingParameter,
+            0x15 => LimitExceeded,
+            0x16 => UnsupportedExtension,
+            0x17 => FingerprintDatabaseFull,
+            0x18 => LargeBlobStorageFull,
+            0x19 => CredentialExcluded,
+            0x21 => Processing,
+            0x22 => InvalidCredential,
+            0x23 => UserActionPending,
+            0x24 => OperationPending,
+            0x25 => NoOperations,
+            0x26 => UnsupportedAlgorithm,
+            0x27 => OperationDenied,
+            0x28 => KeyStoreFull,
+            0x29 => NotBusy,
+            0x2A => NoOperationPending,
+            0x2B => UnsupportedOption,
+            0x2C => InvalidOption,
+            0x2D => KeepaliveCancel,
+            0x2E => NoCredentials,
+            0x2F => UserActionTimeout,
+            0x30 => NotAllowed,
+            0x31 => PinInvalid,
+            0x32 => PinBlocked,
+            0x33 => PinAuthInvalid,
+            0x34 => PinAuthBlocked,
+            0x35 => PinNotSet,
+            0x36 => PinRequired,
+            0x37 => PinPolicyViolation,
+            0x38 => PinTokenExpired,
+            0x39 => RequestTooLarge,
+            0x3A => ActionTimeout,
+            0x3B => UpRequired,
+            0x3C => UvBlocked,
+            0x3D => IntegrityFailure,
+            0x3E => InvalidSubcommand,
+            0x3F => UvInvalid,
+            0x40 => UnauthorizedPermission,
+            0x7F => Other,
+            0xDF => SpecLast,
+            code @ Self::EXTENSION_FIRST..=Self::EXTENSION_LAST => {
+                Extension(ExtensionErrorCode(code))
             }
-        }
-
-        deserializer.deserialize_seq(ValueVisitor)
+            code @ Self::VENDOR_FIRST..=Self::VENDOR_LAST => Vendor(VendorErrorCode(code)),
+            code => return Err(UnknownErrorCode(code)),
+        })
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-#[non_exhaustive]
-pub enum Error {
-    Success = 0x00,
-    InvalidCommand = 0x01,
-    InvalidParameter = 0x02,
-    InvalidLength = 0x03,
-    InvalidSeq = 0x04,
-    Timeout = 0x05,
-    ChannelBusy = 0x06,
-    LockRequired = 0x0A,
-    InvalidChannel = 0x0B,
-    CborUnexpectedType = 0x11,
-    InvalidCbor = 0x12,
-    MissingParameter = 0x14,
-    LimitExceeded = 0x15,
-    UnsupportedExtension = 0x16,
-    FingerprintDatabaseFull = 0x17,
-    LargeBlobStorageFull = 0x18,
-    CredentialExcluded = 0x19,
-    Processing = 0x21,
-    InvalidCredential = 0x22,
-    UserActionPending = 0x23,
-    OperationPending = 0x24,
-    NoOperations = 0x25,
-    UnsupportedAlgorithm = 0x26,
-    OperationDenied = 0x27,
-    KeyStoreFull = 0x28,
-    NotBusy = 0x29,
-    NoOperationPending = 0x2A,
-    UnsupportedOption = 0x2B,
-    InvalidOption = 0x2C,
-    KeepaliveCancel = 0x2D,
-    NoCredentials = 0x2E,
-    UserActionTimeout = 0x2F,
-    NotAllowed = 0x30,
-    PinInvalid = 0x31,
-    PinBlocked = 0x32,
-    PinAuthInvalid = 0x33,
-    PinAuthBlocked = 0x34,
-    PinNotSet = 0x35,
-    PinRequired = 0x36,
-    PinPolicyViolation = 0x37,
-    PinTokenExpired = 0x38,
-    RequestTooLarge = 0x39,
-    ActionTimeout = 0x3A,
-    UpRequired = 0x3B,
-    UvBlocked = 0x3C,
-    IntegrityFailure = 0x3D,
-    InvalidSubcommand = 0x3E,
-    UvInvalid = 0x3F,
-    UnauthorizedPermission = 0x40,
-    Other = 0x7F,
-    SpecLast = 0xDF,
-    ExtensionFirst = 0xE0,
-    ExtensionLast = 0xEF,
-    VendorFirst = 0xF0,
-    VendorLast = 0xFF,
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        let mnemonic = match self {
+            Success => "CTAP2_OK",
+            InvalidCommand => "CTAP1_ERR_INVALID_COMMAND",
+            InvalidParameter => "CTAP1_ERR_INVALID_PARAMETER",
+            InvalidLength => "CTAP1_ERR_INVALID_LENGTH",
+            InvalidSeq => "CTAP1_ERR_INVALID_SEQ",
+            Timeout => "CTAP1_ERR_TIMEOUT",
+            ChannelBusy => "CTAP1_ERR_CHANNEL_BUSY",
+            LockRequired => "CTAP1_ERR_LOCK_REQUIRED",
+            InvalidChannel => "CTAP1_ERR_INVALID_CHANNEL",
+            CborUnexpectedType => "CTAP2_ERR_CBOR_UNEXPECTED_TYPE",
+            InvalidCbor => "CTAP2_ERR_INVALID_CBOR",
+            MissingParameter => "CTAP2_ERR_MISSING_PARAMETER",
+            LimitExceeded => "CTAP2_ERR_LIMIT_EXCEEDED",
+            UnsupportedExtension => "CTAP2_ERR_UNSUPPORTED_EXTENSION",
+            FingerprintDatabaseFull => "CTAP2_ERR_FP_DATABASE_FULL",
+            LargeBlobStorageFull => "CTAP2_ERR_LARGE_BLOB_STORAGE_FULL",
+            CredentialExcluded => "CTAP2_ERR_CREDENTIAL_EXCLUDED",
+            Processing => "CTAP2_ERR_PROCESSING",
+            InvalidCredential => "CTAP2_ERR_INVALID_CREDENTIAL",
+            UserActionPending => "CTAP2_ERR_USER_ACTION_PENDING",
+            OperationPending => "CTAP2_ERR_OPERATION_PENDING",
+            NoOperations => "CTAP2_ERR_NO_OPERATIONS",
+            UnsupportedAlgorithm => "CTAP2_ERR_UNSUPPORTED_ALGORITHM",
+            OperationDenied => "CTAP2_ERR_OPERATION_DENIED",
+            KeyStoreFull => "CTAP2_ERR_KEY_STORE_FULL",
+            NotBusy => "CTAP2_ERR_NOT_BUSY",
+            NoOperationPending => "CTAP2_ERR_NO_OPERATION_PENDING",
+            UnsupportedOption => "CTAP2_ERR_UNSUPPORTED_OPTION",
+            InvalidOption => "CTAP2_ERR_INVALID_OPTION",
+            KeepaliveCancel => "CTAP2_ERR_KEEPALIVE_CANCEL",
+            NoCredentials => "CTAP2_ERR_NO_CREDENTIALS",
+            UserActionTimeout => "CTAP2_ERR_USER_ACTION_TIMEOUT",
+            NotAllowed => "CTAP2_ERR_NOT_ALLOWED",
+            PinInvalid => "CTAP2_ERR_PIN_INVALID",
+            PinBlocked => "CTAP2_ERR_PIN_BLOCKED",
+            PinAuthInvalid => "CTAP2_ERR_PIN_AUTH_INVALID",
+            PinAuthBlocked => "CTAP2_ERR_PIN_AUTH_BLOCKED",
+            PinNotSet => "CTAP2_ERR_PIN_NOT_SET",
+            PinRequired => "CTAP2_ERR_PUAT_REQUIRED",
+            PinPolicyViolation => "CTAP2_ERR_PIN_POLICY_VIOLATION",
+            PinTokenExpired => "CTAP2_ERR_PIN_TOKEN_EXPIRED",
+            RequestTooLarge => "CTAP2_ERR_REQUEST_TOO_LARGE",
+            ActionTimeout => "CTAP2_ERR_ACTION_TIMEOUT",
+            UpRequired => "CTAP2_ERR_UP_REQUIRED",
+            UvBlocked => "CTAP2_ERR_UV_BLOCKED",
+            IntegrityFailure => "CTAP2_ERR_INTEGRITY_FAILURE",
+            InvalidSubcommand => "CTAP2_ERR_INVALID_SUBCOMMAND",
+            UvInvalid => "CTAP2_ERR_UV_INVALID",
+            UnauthorizedPermission => "CTAP2_ERR_UNAUTHORIZED_PERMISSION",
+            Other => "CTAP1_ERR_OTHER",
+            SpecLast => "CTAP2_ERR_SPEC_LAST",
+            Extension(code) => return write!(f, "CTAP2_ERR_EXTENSION({:#04x})", code.code()),
+            Vendor(code) => return write!(f, "CTAP2_ERR_VENDOR({:#04x})", code.code()),
+        };
+        f.write_str(mnemonic)
+    }
 }
 
+impl core::error::Error for Error {}
+
 /// CTAP2 authenticator API
 ///
 /// Note that all Authenticators automatically implement [`crate::Rpc`] with [`Request`] and
@@ -496,11 +1229,13 @@ pub trait Authenticator {
             // 0x6
             Request::ClientPin(request) => {
                 debug_now!("CTAP2.PIN");
-                Ok(Response::ClientPin(self.client_pin(request).inspect_err(
-                    |_e| {
-                        debug!("error: {:?}", _e);
-                    },
-                )?))
+                let response = self.client_pin(request).inspect_err(|_e| {
+                    debug!("error: {:?}", _e);
+                })?;
+                response.validate(request).inspect_err(|_e| {
+                    debug!("invalid response: {:?}", _e);
+                })?;
+                Ok(Response::ClientPin(response))
             }
 
             // 0xA
@@ -551,3 +1286,356 @@ impl<'a, A: Authenticator> crate::Rpc<Error, Request<'a>, Response> for A {
         self.call_ctap2(request)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webauthn::{CredentialId, ParsedAuthenticatorData, PublicKeyCredentialDescriptor};
+
+    #[test]
+    fn test_response_serialized_len() {
+        let responses = [
+            Response::Reset,
+            Response::CredentialManagement(Default::default()),
+            Response::MakeCredential(
+                make_credential::ResponseBuilder {
+                    fmt: AttestationStatementFormat::None,
+                    auth_data: Bytes::from_slice(&[0xad; 37]).unwrap(),
+                }
+                .build(),
+            ),
+        ];
+        for response in responses {
+            let mut buffer = Vec::<u8, 1024>::new();
+            response.serialize(&mut buffer);
+            assert_eq!(response.serialized_len(), Ok(buffer.len()));
+        }
+    }
+
+    #[test]
+    fn test_response_ref_round_trip() {
+        let auth_data = [0xad; 37];
+        let signature = [0x5e; 72];
+        let response = get_assertion::ResponseBuilder {
+            credential: PublicKeyCredentialDescriptor {
+                id: CredentialId::from_slice(&[0xc1; 64]).unwrap(),
+                key_type: "public-key".into(),
+                transports: None,
+            },
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
+            signature: Bytes::from_slice(&signature).unwrap(),
+        }
+        .build();
+
+        let mut owned = Vec::<u8, 1024>::new();
+        Response::GetAssertion(response.clone()).serialize(&mut owned);
+
+        let response_ref = ResponseRef::GetAssertion((&response).into());
+        let mut borrowed = Vec::<u8, 1024>::new();
+        response_ref.serialize(&mut borrowed);
+        assert_eq!(borrowed, owned);
+        assert_eq!(response_ref.serialized_len(), Ok(borrowed.len()));
+
+        assert_eq!(borrowed[0], 0);
+        #[cfg(feature = "platform")]
+        {
+            let deserialized: get_assertion::Response =
+                cbor_smol::cbor_deserialize(&borrowed[1..]).unwrap();
+            assert_eq!(deserialized, response);
+        }
+
+        let response = make_credential::ResponseBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
+        }
+        .build();
+        let mut owned = Vec::<u8, 1024>::new();
+        Response::MakeCredential(response.clone()).serialize(&mut owned);
+        let response_ref = ResponseRef::MakeCredential((&response).into());
+        let mut borrowed = Vec::<u8, 1024>::new();
+        response_ref.serialize(&mut borrowed);
+        assert_eq!(borrowed, owned);
+        assert_eq!(response_ref.serialized_len(), Ok(borrowed.len()));
+        #[cfg(feature = "platform")]
+        {
+            let deserialized: make_credential::Response =
+                cbor_smol::cbor_deserialize(&borrowed[1..]).unwrap();
+            assert_eq!(deserialized, response);
+        }
+    }
+
+    #[test]
+    fn test_request_operation() {
+        let request = Request::deserialize(&[0x04]).unwrap();
+        assert_eq!(request.operation(), Operation::GetInfo);
+        assert_eq!(request.operation().to_string(), "authenticatorGetInfo");
+
+        let request = Request::deserialize(&[0x42]).unwrap();
+        let operation = request.operation();
+        assert_eq!(operation.into_u8(), 0x42);
+        assert_eq!(operation.to_string(), "vendor operation 0x42");
+    }
+
+    #[test]
+    fn test_request_parsing_error() {
+        // getAssertion with rpId but without clientDataHash
+        let data = b"\x02\xa1\x01kexample.com";
+        let error = Request::try_from(data.as_slice()).unwrap_err();
+        assert!(matches!(
+            error,
+            CtapMappingError::InvalidParameters {
+                operation: Operation::GetAssertion,
+                missing_key: Some(0x02),
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "authenticatorGetAssertion request is missing key 0x02"
+        );
+        assert_eq!(Request::deserialize(data), Err(Error::MissingParameter));
+    }
+
+    #[test]
+    fn test_deserialize_with_limit() {
+        let data = b"\x02\xa1\x01kexample.com";
+        assert_eq!(
+            Request::deserialize_with_limit(data, data.len() - 1),
+            Err(Error::RequestTooLarge)
+        );
+        assert_eq!(
+            Request::deserialize_with_limit(data, data.len()),
+            Err(Error::MissingParameter)
+        );
+        assert_eq!(
+            Request::deserialize_with_limit(&[0x04], 1),
+            Ok(Request::GetInfo)
+        );
+    }
+
+    #[test]
+    fn test_deserialize_strict() {
+        let mut data = Vec::<u8, 64>::new();
+        data.extend_from_slice(b"\x02\xa2\x01kexample.com\x02\x58\x20")
+            .unwrap();
+        data.extend_from_slice(&[0xcd; 32]).unwrap();
+        assert!(Request::deserialize_strict(&data).is_ok());
+
+        let mut trailing = data.clone();
+        trailing.push(0xa0).unwrap();
+        assert_eq!(
+            Request::deserialize_strict(&trailing),
+            Err(Error::InvalidCbor)
+        );
+
+        let mut reordered = Vec::<u8, 64>::new();
+        reordered
+            .extend_from_slice(b"\x02\xa2\x02\x58\x20")
+            .unwrap();
+        reordered.extend_from_slice(&[0xcd; 32]).unwrap();
+        reordered.extend_from_slice(b"\x01kexample.com").unwrap();
+        assert_eq!(
+            Request::deserialize_strict(&reordered),
+            Err(Error::InvalidCbor)
+        );
+    }
+
+    #[test]
+    fn test_error_code_roundtrip() {
+        for code in 0..=u8::MAX {
+            if let Ok(error) = Error::try_from(code) {
+                assert_eq!(u8::from(error), code);
+            }
+        }
+        assert_eq!(Error::try_from(0x33), Ok(Error::PinAuthInvalid));
+        assert_eq!(
+            Error::try_from(0xE5),
+            Ok(Error::Extension(ExtensionErrorCode::new(0xE5).unwrap()))
+        );
+        assert_eq!(
+            Error::try_from(0xF1),
+            Ok(Error::Vendor(VendorErrorCode::new(0xF1).unwrap()))
+        );
+        assert_eq!(Error::try_from(0x07), Err(UnknownErrorCode(0x07)));
+
+        assert_eq!(ExtensionErrorCode::new(0xDF), None);
+        assert_eq!(ExtensionErrorCode::new(0xF0), None);
+        assert_eq!(VendorErrorCode::new(0x02), None);
+        assert_eq!(VendorErrorCode::new(0xEF), None);
+        assert_eq!(VendorErrorCode::new(0xFF).map(u8::from), Some(0xFF));
+    }
+
+    #[test]
+    fn test_authenticator_data_flags_backup() {
+        let flags = AuthenticatorDataFlags::backup(true, true).unwrap();
+        assert_eq!(flags.bits(), 0b0001_1000);
+        assert!(flags.is_valid());
+
+        let flags = AuthenticatorDataFlags::USER_PRESENCE
+            .with_backup(true, false)
+            .unwrap();
+        assert_eq!(flags.bits(), 0b0000_1001);
+
+        assert_eq!(
+            AuthenticatorDataFlags::backup(false, true),
+            Err(Error::InvalidParameter)
+        );
+        assert!(!AuthenticatorDataFlags::BACKUP_STATE.is_valid());
+    }
+
+    #[test]
+    fn test_parse_authenticator_data() {
+        let public_key = crate::cose::PublicKey::P256Key(crate::cose::P256PublicKey {
+            x: Bytes::from_slice(&[0x11; 32]).unwrap(),
+            y: Bytes::from_slice(&[0x22; 32]).unwrap(),
+        });
+        let mut buffer = [0; 128];
+        let public_key = cbor_smol::cbor_serialize(&public_key, &mut buffer).unwrap();
+        let mut extensions = make_credential::ExtensionsOutput::default();
+        extensions.cred_protect =
+            Some(credential_management::CredentialProtectionPolicy::OptionalWithCredentialIdList);
+        extensions.hmac_secret = Some(true);
+
+        let rp_id_hash = RpIdHash::new([0xaa; 32]);
+        let attested_credential_data = make_credential::AttestedCredentialData {
+            aaguid: &[0xbb; 16],
+            credential_id: crate::webauthn::CredentialIdRef::new(&[0xcc; 48]),
+            credential_public_key: public_key,
+        };
+        let authenticator_data = make_credential::AuthenticatorData {
+            rp_id_hash: &rp_id_hash,
+            flags: AuthenticatorDataFlags::USER_PRESENCE
+                | AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA
+                | AuthenticatorDataFlags::EXTENSION_DATA,
+            sign_count: 0x01020304,
+            attested_credential_data: Some(attested_credential_data.clone()),
+            extensions: Some(extensions.clone()),
+        };
+        let serialized = authenticator_data.serialize().unwrap();
+
+        let parsed = ParsedAuthenticatorData::parse(&serialized).unwrap();
+        assert_eq!(parsed.rp_id_hash, &rp_id_hash);
+        assert_eq!(parsed.flags, authenticator_data.flags);
+        assert_eq!(parsed.sign_count, 0x01020304);
+        assert_eq!(
+            parsed.attested_credential_data,
+            Some(attested_credential_data)
+        );
+        let mut buffer = [0; 64];
+        let extensions = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+        assert_eq!(parsed.extensions, Some(extensions));
+        assert_eq!(
+            parsed.cred_protect(),
+            Ok(Some(
+                credential_management::CredentialProtectionPolicy::OptionalWithCredentialIdList
+            ))
+        );
+        assert_eq!(parsed.hmac_secret(), Ok(Some(true)));
+        assert_eq!(parsed.cred_blob_stored(), Ok(None));
+        assert_eq!(
+            parsed.extension("credProtect"),
+            Ok(Some(b"\x02".as_slice()))
+        );
+        assert_eq!(parsed.hmac_secret_outputs(), Err(Error::InvalidCbor));
+
+        assert_eq!(
+            ParsedAuthenticatorData::parse(&serialized[..serialized.len() - 1]),
+            Err(Error::InvalidCbor)
+        );
+        assert_eq!(
+            ParsedAuthenticatorData::parse(&serialized[..36]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_parse_authenticator_data_extensions() {
+        let mut extensions = get_assertion::ExtensionsOutput::default();
+        extensions.cred_blob = Some(Bytes::from_slice(b"blob").unwrap());
+        extensions.hmac_secret =
+            Some(get_assertion::EncryptedOutputs::from_slice(&[0xee; 48]).unwrap());
+
+        let rp_id_hash = RpIdHash::new([0xaa; 32]);
+        let authenticator_data = get_assertion::AuthenticatorData {
+            rp_id_hash: &rp_id_hash,
+            flags: AuthenticatorDataFlags::USER_PRESENCE | AuthenticatorDataFlags::EXTENSION_DATA,
+            sign_count: 1,
+            attested_credential_data: None,
+            extensions: Some(extensions),
+        };
+        let serialized = authenticator_data.serialize().unwrap();
+
+        let parsed = ParsedAuthenticatorData::parse(&serialized).unwrap();
+        assert_eq!(parsed.attested_credential_data, None);
+        assert_eq!(parsed.cred_blob(), Ok(Some(b"blob".as_slice())));
+        assert_eq!(
+            parsed.hmac_secret_outputs().unwrap().unwrap().as_bytes(),
+            &[0xee; 48]
+        );
+        assert_eq!(parsed.cred_protect(), Ok(None));
+        assert_eq!(parsed.extension("unknown"), Ok(None));
+
+        // replace the extensions with an empty array
+        let mut serialized = serialized;
+        serialized.truncate(37);
+        serialized.push(0x80).unwrap();
+        let parsed = ParsedAuthenticatorData::parse(&serialized).unwrap();
+        assert_eq!(parsed.extension("credBlob"), Err(Error::InvalidCbor));
+    }
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(
+            Error::PinAuthInvalid.to_string(),
+            "CTAP2_ERR_PIN_AUTH_INVALID"
+        );
+        assert_eq!(Error::Other.to_string(), "CTAP1_ERR_OTHER");
+        assert_eq!(
+            Error::Vendor(VendorErrorCode::new(0xF1).unwrap()).to_string(),
+            "CTAP2_ERR_VENDOR(0xf1)"
+        );
+        assert_eq!(
+            CtapMappingError::InvalidCommand(0x42).to_string(),
+            "invalid command 0x42"
+        );
+    }
+
+    #[cfg(feature = "embedded-io")]
+    fn assert_serialize_to_writer(response: Response) {
+        let mut expected = Vec::<u8, 1024>::new();
+        response.serialize(&mut expected);
+
+        let mut output = [0; 1024];
+        let mut writer = output.as_mut_slice();
+        response.serialize_to_writer(&mut writer).unwrap();
+        let n = 1024 - writer.len();
+        assert_eq!(&output[..n], expected.as_slice());
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_serialize_to_writer() {
+        assert_serialize_to_writer(Response::Reset);
+        assert_serialize_to_writer(Response::ClientPin(Default::default()));
+        assert_serialize_to_writer(Response::ClientPin(client_pin::Response {
+            retries: Some(8),
+            ..Default::default()
+        }));
+        assert_serialize_to_writer(Response::GetInfo(Default::default()));
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_serialize_ref_to_writer() {
+        let response = large_blobs::ResponseRef::new(&[0x42; 17]);
+        let response = ResponseRef::LargeBlobs(response);
+        let mut expected = Vec::<u8, 1024>::new();
+        response.serialize(&mut expected);
+
+        let mut output = [0; 1024];
+        let mut writer = output.as_mut_slice();
+        response.serialize_to_writer(&mut writer).unwrap();
+        let n = 1024 - writer.len();
+        assert_eq!(&output[..n], expected.as_slice());
+    }
+}
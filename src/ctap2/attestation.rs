@@ -0,0 +1,462 @@
+//! Attestation statements and formats.
+//!
+//! See the [WebAuthn specification](https://www.w3.org/TR/webauthn-3/#sctn-defined-attestation-formats)
+//! for the defined attestation statement formats.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{sizes::*, Bytes, TryFromStrError, Vec};
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum AttestationStatement {
+    None(NoneAttestationStatement),
+    Packed(PackedAttestationStatement),
+    #[cfg(feature = "tpm-attestation")]
+    Tpm(TpmAttestationStatement),
+    #[cfg(feature = "android-key-attestation")]
+    AndroidKey(AndroidKeyAttestationStatement),
+    #[cfg(feature = "apple-attestation")]
+    Apple(AppleAttestationStatement),
+}
+
+impl AttestationStatement {
+    /// Returns the format of this attestation statement.
+    pub fn format(&self) -> AttestationStatementFormat {
+        match self {
+            Self::None(_) => AttestationStatementFormat::None,
+            Self::Packed(_) => AttestationStatementFormat::Packed,
+            #[cfg(feature = "tpm-attestation")]
+            Self::Tpm(_) => AttestationStatementFormat::Tpm,
+            #[cfg(feature = "android-key-attestation")]
+            Self::AndroidKey(_) => AttestationStatementFormat::AndroidKey,
+            #[cfg(feature = "apple-attestation")]
+            Self::Apple(_) => AttestationStatementFormat::Apple,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[serde(into = "&str", try_from = "&str")]
+pub enum AttestationStatementFormat {
+    None,
+    Packed,
+    #[cfg(feature = "tpm-attestation")]
+    Tpm,
+    #[cfg(feature = "android-key-attestation")]
+    AndroidKey,
+    #[cfg(feature = "apple-attestation")]
+    Apple,
+}
+
+impl AttestationStatementFormat {
+    const NONE: &'static str = "none";
+    const PACKED: &'static str = "packed";
+    #[cfg(feature = "tpm-attestation")]
+    const TPM: &'static str = "tpm";
+    #[cfg(feature = "android-key-attestation")]
+    const ANDROID_KEY: &'static str = "android-key";
+    #[cfg(feature = "apple-attestation")]
+    const APPLE: &'static str = "apple";
+}
+
+impl From<AttestationStatementFormat> for &str {
+    fn from(format: AttestationStatementFormat) -> Self {
+        match format {
+            AttestationStatementFormat::None => AttestationStatementFormat::NONE,
+            AttestationStatementFormat::Packed => AttestationStatementFormat::PACKED,
+            #[cfg(feature = "tpm-attestation")]
+            AttestationStatementFormat::Tpm => AttestationStatementFormat::TPM,
+            #[cfg(feature = "android-key-attestation")]
+            AttestationStatementFormat::AndroidKey => AttestationStatementFormat::ANDROID_KEY,
+            #[cfg(feature = "apple-attestation")]
+            AttestationStatementFormat::Apple => AttestationStatementFormat::APPLE,
+        }
+    }
+}
+
+impl TryFrom<&str> for AttestationStatementFormat {
+    type Error = TryFromStrError;
+
+    fn try_from(s: &str) -> core::result::Result<Self, Self::Error> {
+        match s {
+            Self::NONE => Ok(Self::None),
+            Self::PACKED => Ok(Self::Packed),
+            #[cfg(feature = "tpm-attestation")]
+            Self::TPM => Ok(Self::Tpm),
+            #[cfg(feature = "android-key-attestation")]
+            Self::ANDROID_KEY => Ok(Self::AndroidKey),
+            #[cfg(feature = "apple-attestation")]
+            Self::APPLE => Ok(Self::Apple),
+            _ => Err(TryFromStrError),
+        }
+    }
+}
+
+/// An X.509 certificate chain (`x5c`) in DER format, starting with the attestation certificate.
+///
+/// The maximum length of the chain and of the certificates are defined in [`crate::sizes`][].
+pub type CertificateChain =
+    Vec<Bytes<MAX_ATTESTATION_CERTIFICATE_LENGTH>, MAX_ATTESTATION_CERTIFICATE_CHAIN_LENGTH>;
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct NoneAttestationStatement {}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct PackedAttestationStatement {
+    pub alg: i32,
+    pub sig: Bytes<MAX_SIGNATURE_LENGTH>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x5c: Option<CertificateChain>,
+}
+
+/// Attestation statement for the `tpm` format.
+///
+/// See the [WebAuthn specification](https://www.w3.org/TR/webauthn-3/#sctn-tpm-attestation).
+#[cfg(feature = "tpm-attestation")]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TpmAttestationStatement {
+    pub alg: i32,
+    pub sig: Bytes<TPM_SIGNATURE_LENGTH>,
+    /// The TPM specification version, always `"2.0"`.
+    pub ver: &'static str,
+    pub x5c: CertificateChain,
+    #[serde(rename = "pubArea")]
+    pub pub_area: Bytes<TPM_PUB_AREA_LENGTH>,
+    #[serde(rename = "certInfo")]
+    pub cert_info: Bytes<TPM_CERT_INFO_LENGTH>,
+}
+
+#[cfg(feature = "tpm-attestation")]
+impl TpmAttestationStatement {
+    pub const VERSION: &'static str = "2.0";
+}
+
+/// Attestation statement for the `android-key` format.
+///
+/// See the [WebAuthn specification](https://www.w3.org/TR/webauthn-3/#sctn-android-key-attestation).
+#[cfg(feature = "android-key-attestation")]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AndroidKeyAttestationStatement {
+    pub alg: i32,
+    pub sig: Bytes<MAX_SIGNATURE_LENGTH>,
+    pub x5c: CertificateChain,
+}
+
+/// Attestation statement for the `apple` format.
+///
+/// See the [WebAuthn specification](https://www.w3.org/TR/webauthn-3/#sctn-apple-anonymous-attestation).
+#[cfg(feature = "apple-attestation")]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct AppleAttestationStatement {
+    pub x5c: CertificateChain,
+}
+
+/// The union of the fields of the supported attestation statements.
+///
+/// As [`AttestationStatement`][] is untagged, its variant can only be determined from the `fmt`
+/// field of the surrounding response.  This type collects all fields so that they can be
+/// converted once the format is known.
+#[cfg(feature = "platform")]
+#[derive(Deserialize)]
+pub(crate) struct RawAttestationStatement<'a> {
+    alg: Option<i32>,
+    #[serde(borrow)]
+    sig: Option<&'a serde_bytes::Bytes>,
+    x5c: Option<CertificateChain>,
+    #[cfg(feature = "tpm-attestation")]
+    #[serde(borrow)]
+    ver: Option<&'a str>,
+    #[cfg(feature = "tpm-attestation")]
+    #[serde(rename = "pubArea", borrow)]
+    pub_area: Option<&'a serde_bytes::Bytes>,
+    #[cfg(feature = "tpm-attestation")]
+    #[serde(rename = "certInfo", borrow)]
+    cert_info: Option<&'a serde_bytes::Bytes>,
+}
+
+#[cfg(feature = "platform")]
+impl RawAttestationStatement<'_> {
+    /// Guesses the format of this statement from the fields that are present.
+    ///
+    /// This is used if the format is not transmitted, e.g. in a getAssertion response.  The
+    /// `android-key` format cannot be distinguished from the `packed` format, so statements with
+    /// `alg` and `sig` are always treated as `packed`.
+    pub(crate) fn infer_format(&self) -> Option<AttestationStatementFormat> {
+        #[cfg(feature = "tpm-attestation")]
+        if self.ver.is_some() {
+            return Some(AttestationStatementFormat::Tpm);
+        }
+        match (self.alg, self.sig, &self.x5c) {
+            (None, None, None) => Some(AttestationStatementFormat::None),
+            (Some(_), Some(_), _) => Some(AttestationStatementFormat::Packed),
+            #[cfg(feature = "apple-attestation")]
+            (None, None, Some(_)) => Some(AttestationStatementFormat::Apple),
+            _ => None,
+        }
+    }
+
+    /// Converts this statement into an [`AttestationStatement`][] of the given format.
+    ///
+    /// Returns `None` if a required field is missing or too long.
+    pub(crate) fn into_statement(
+        self,
+        format: AttestationStatementFormat,
+    ) -> Option<AttestationStatement> {
+        let statement = match format {
+            AttestationStatementFormat::None => {
+                AttestationStatement::None(NoneAttestationStatement {})
+            }
+            AttestationStatementFormat::Packed => {
+                AttestationStatement::Packed(PackedAttestationStatement {
+                    alg: self.alg?,
+                    sig: Bytes::from_slice(self.sig?).ok()?,
+                    x5c: self.x5c,
+                })
+            }
+            #[cfg(feature = "tpm-attestation")]
+            AttestationStatementFormat::Tpm => {
+                if self.ver? != TpmAttestationStatement::VERSION {
+                    return None;
+                }
+                AttestationStatement::Tpm(TpmAttestationStatement {
+                    alg: self.alg?,
+                    sig: Bytes::from_slice(self.sig?).ok()?,
+                    ver: TpmAttestationStatement::VERSION,
+                    x5c: self.x5c?,
+                    pub_area: Bytes::from_slice(self.pub_area?).ok()?,
+                    cert_info: Bytes::from_slice(self.cert_info?).ok()?,
+                })
+            }
+            #[cfg(feature = "android-key-attestation")]
+            AttestationStatementFormat::AndroidKey => {
+                AttestationStatement::AndroidKey(AndroidKeyAttestationStatement {
+                    alg: self.alg?,
+                    sig: Bytes::from_slice(self.sig?).ok()?,
+                    x5c: self.x5c?,
+                })
+            }
+            #[cfg(feature = "apple-attestation")]
+            AttestationStatementFormat::Apple => {
+                AttestationStatement::Apple(AppleAttestationStatement { x5c: self.x5c? })
+            }
+        };
+        Some(statement)
+    }
+}
+
+/// An entry in an [`AttestationFormatsPreference`][].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum PreferredAttestationFormat {
+    /// A format supported by this crate.
+    Known(AttestationStatementFormat),
+    /// One or more consecutive formats that are not supported by this crate.
+    Unknown,
+}
+
+/// The attestation statement formats preferred by the platform, most preferred first.
+///
+/// At most [`MAX_ATTESTATION_FORMATS_PREFERENCE_LENGTH`][] entries are stored.  Further entries
+/// are ignored.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AttestationFormatsPreference {
+    pub(crate) formats: Vec<PreferredAttestationFormat, MAX_ATTESTATION_FORMATS_PREFERENCE_LENGTH>,
+}
+
+impl AttestationFormatsPreference {
+    /// Returns all entries in the order of preference.
+    pub fn formats(&self) -> &[PreferredAttestationFormat] {
+        &self.formats
+    }
+
+    /// Returns the known formats in the order of preference.
+    pub fn known_formats(&self) -> impl Iterator<Item = AttestationStatementFormat> + '_ {
+        self.formats.iter().filter_map(|format| match format {
+            PreferredAttestationFormat::Known(format) => Some(*format),
+            PreferredAttestationFormat::Unknown => None,
+        })
+    }
+
+    pub fn includes_unknown_formats(&self) -> bool {
+        self.formats.contains(&PreferredAttestationFormat::Unknown)
+    }
+
+    /// Selects the most preferred format that is contained in `supported`.
+    ///
+    /// Returns `None` if none of the preferred formats is supported.  In this case, the
+    /// authenticator should use its default format.
+    pub fn select(
+        &self,
+        supported: &[AttestationStatementFormat],
+    ) -> Option<AttestationStatementFormat> {
+        self.known_formats()
+            .find(|format| supported.contains(format))
+    }
+
+    /// Picks the attestation statement format that the authenticator should produce.
+    ///
+    /// This returns the first preferred format that is contained in `supported`.  As the
+    /// authenticator can always strip the attestation, the `none` format is picked when it is
+    /// reached even if it is not contained in `supported`.  If no preferred format can be used,
+    /// `default` is returned.
+    pub fn pick(
+        &self,
+        supported: &[AttestationStatementFormat],
+        default: AttestationStatementFormat,
+    ) -> AttestationStatementFormat {
+        self.known_formats()
+            .find(|format| {
+                *format == AttestationStatementFormat::None || supported.contains(format)
+            })
+            .unwrap_or(default)
+    }
+
+    fn push(&mut self, format: PreferredAttestationFormat) {
+        if format == PreferredAttestationFormat::Unknown
+            && self.formats.last() == Some(&PreferredAttestationFormat::Unknown)
+        {
+            return;
+        }
+        self.formats.push(format).ok();
+    }
+}
+
+impl<'de> Deserialize<'de> for AttestationFormatsPreference {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ValueVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+            type Value = AttestationFormatsPreference;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut preference = AttestationFormatsPreference::default();
+                while let Some(value) = seq.next_element::<&str>()? {
+                    let format = AttestationStatementFormat::try_from(value)
+                        .map(PreferredAttestationFormat::Known)
+                        .unwrap_or(PreferredAttestationFormat::Unknown);
+                    preference.push(format);
+                }
+                Ok(preference)
+            }
+        }
+
+        deserializer.deserialize_seq(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_test::{assert_ser_tokens, Token};
+
+    #[test]
+    fn test_serde_attestation_statement_format() {
+        let formats = [
+            (AttestationStatementFormat::None, "none"),
+            (AttestationStatementFormat::Packed, "packed"),
+            #[cfg(feature = "tpm-attestation")]
+            (AttestationStatementFormat::Tpm, "tpm"),
+            #[cfg(feature = "android-key-attestation")]
+            (AttestationStatementFormat::AndroidKey, "android-key"),
+            #[cfg(feature = "apple-attestation")]
+            (AttestationStatementFormat::Apple, "apple"),
+        ];
+        for (format, s) in formats {
+            assert_ser_tokens(&format, &[Token::BorrowedStr(s)]);
+        }
+    }
+
+    #[cfg(feature = "apple-attestation")]
+    #[test]
+    fn test_ser_apple_attestation_statement() {
+        let mut x5c = Vec::new();
+        x5c.push(Bytes::from_slice(&[0x30, 0x82]).unwrap()).unwrap();
+        let statement = AttestationStatement::Apple(AppleAttestationStatement { x5c });
+        assert_ser_tokens(
+            &statement,
+            &[
+                Token::Struct {
+                    name: "AppleAttestationStatement",
+                    len: 1,
+                },
+                Token::Str("x5c"),
+                Token::Seq { len: Some(1) },
+                Token::Bytes(&[0x30, 0x82]),
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_de_attestation_formats_preference() {
+        let preference: AttestationFormatsPreference =
+            cbor_smol::cbor_deserialize(b"\x84gunknownfpackedcfoodnone").unwrap();
+        assert_eq!(
+            preference.formats(),
+            &[
+                PreferredAttestationFormat::Unknown,
+                PreferredAttestationFormat::Known(AttestationStatementFormat::Packed),
+                PreferredAttestationFormat::Unknown,
+                PreferredAttestationFormat::Known(AttestationStatementFormat::None),
+            ]
+        );
+        assert!(preference.includes_unknown_formats());
+        assert_eq!(
+            preference.select(&[
+                AttestationStatementFormat::None,
+                AttestationStatementFormat::Packed
+            ]),
+            Some(AttestationStatementFormat::Packed)
+        );
+        assert_eq!(
+            preference.select(&[AttestationStatementFormat::None]),
+            Some(AttestationStatementFormat::None)
+        );
+        assert_eq!(preference.select(&[]), None);
+    }
+
+    #[test]
+    fn test_pick_attestation_format() {
+        let preference: AttestationFormatsPreference =
+            cbor_smol::cbor_deserialize(b"\x83gunknownfpackeddnone").unwrap();
+        assert_eq!(
+            preference.pick(
+                &[AttestationStatementFormat::Packed],
+                AttestationStatementFormat::Packed
+            ),
+            AttestationStatementFormat::Packed
+        );
+        // none is always possible
+        assert_eq!(
+            preference.pick(&[], AttestationStatementFormat::Packed),
+            AttestationStatementFormat::None
+        );
+
+        let preference: AttestationFormatsPreference =
+            cbor_smol::cbor_deserialize(b"\x82gunknownfpacked").unwrap();
+        assert_eq!(
+            preference.pick(&[], AttestationStatementFormat::Packed),
+            AttestationStatementFormat::Packed
+        );
+        assert_eq!(
+            AttestationFormatsPreference::default().pick(&[], AttestationStatementFormat::None),
+            AttestationStatementFormat::None
+        );
+    }
+}
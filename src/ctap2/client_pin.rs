@@ -1,10 +1,15 @@
-use crate::Bytes;
+use crate::{
+    cose::{EcdhEsHkdf256PublicKey, EcdhEsHkdf256PublicKeyRef},
+    Bytes,
+};
 use bitflags::bitflags;
-use cosey::EcdhEsHkdf256PublicKey;
+use serde::{Deserialize, Serialize};
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+use super::{Error, Result};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[repr(u8)]
@@ -19,6 +24,34 @@ pub enum PinV1Subcommand {
     GetPinUvAuthTokenUsingPinWithPermissions = 0x09,
 }
 
+/// A PIN/UV auth protocol version.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum PinProtocolVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl TryFrom<u8> for PinProtocolVersion {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+impl From<PinProtocolVersion> for u8 {
+    fn from(version: PinProtocolVersion) -> u8 {
+        version as u8
+    }
+}
+
 bitflags! {
     #[derive(Default)]
     pub struct Permissions: u8 {
@@ -31,6 +64,101 @@ bitflags! {
     }
 }
 
+impl Permissions {
+    /// Converts from the underlying bit representation, preserving bits that do not correspond
+    /// to known permissions.
+    pub const fn from_bits_retain(bits: u8) -> Self {
+        // SAFETY: all operations on Permissions are well-defined for unknown bits
+        unsafe { Self::from_bits_unchecked(bits) }
+    }
+
+    /// Returns true if these permissions require an RP ID.
+    ///
+    /// This is the case for the `mc` and `ga` permissions.
+    pub fn requires_rp_id(&self) -> bool {
+        self.intersects(Self::MAKE_CREDENTIAL | Self::GET_ASSERTION)
+    }
+
+    /// Returns true if these permissions contain bits that do not correspond to known permissions.
+    pub fn has_unknown_bits(&self) -> bool {
+        Self::from_bits(self.bits()).is_none()
+    }
+}
+
+impl serde::Serialize for Permissions {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.bits())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Permissions {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        <u8 as serde::Deserialize>::deserialize(deserializer).map(Self::from_bits_retain)
+    }
+}
+
+/// A PIN/UV auth token encrypted with the shared secret.
+///
+/// The PIN/UV auth token is 32 bytes long.  For PIN protocol 1, it is encrypted without an IV,
+/// so the ciphertext is 32 bytes long.  For PIN protocol 2, a 16 byte IV is prepended to the
+/// ciphertext, so it is 48 bytes long.  As the protocol is not part of the response, it has to
+/// be checked with [`EncryptedPinToken::validate`][] after deserialization.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct EncryptedPinToken(Bytes<48>);
+
+impl EncryptedPinToken {
+    /// Creates an encrypted PIN/UV auth token for the given protocol.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the length of `data` is not valid for the protocol.
+    pub fn new(protocol: PinProtocolVersion, data: &[u8]) -> Result<Self> {
+        let pin_token = Bytes::from_slice(data)
+            .map(Self)
+            .map_err(|_| Error::InvalidLength)?;
+        pin_token.validate(protocol)?;
+        Ok(pin_token)
+    }
+
+    /// Checks that the length of the encrypted token is valid for the given protocol.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the length is not valid.
+    pub fn validate(&self, protocol: PinProtocolVersion) -> Result<()> {
+        let len = match protocol {
+            PinProtocolVersion::V1 => 32,
+            PinProtocolVersion::V2 => 48,
+        };
+        if self.0.len() == len {
+            Ok(())
+        } else {
+            Err(Error::InvalidLength)
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for EncryptedPinToken {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        let data = Bytes::<48>::deserialize(deserializer)?;
+        if !matches!(data.len(), 32 | 48) {
+            return Err(serde::de::Error::invalid_length(
+                data.len(),
+                &"32 or 48 bytes",
+            ));
+        }
+        Ok(Self(data))
+    }
+}
+
 // minimum PIN length: 4 unicode
 // maximum PIN length: UTF-8 represented by <= 63 bytes
 // maximum consecutive incorrect PIN attempts: 8
@@ -52,7 +180,7 @@ pub struct Request<'a> {
     // Public key of platformKeyAgreementKey.
     // Must contain "alg" parameter, must not contain any other optional parameters
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub key_agreement: Option<EcdhEsHkdf256PublicKey>,
+    pub key_agreement: Option<EcdhEsHkdf256PublicKeyRef<'a>>,
 
     // 0x04
     // First 16 bytes of HMAC-SHA-256 of encrypted contents
@@ -82,7 +210,7 @@ pub struct Request<'a> {
     // 0x09
     // Bitfield of permissions
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub permissions: Option<u8>,
+    pub permissions: Option<Permissions>,
 
     // 0x0A
     // The RP ID to assign as the permissions RP ID
@@ -90,6 +218,103 @@ pub struct Request<'a> {
     pub rp_id: Option<&'a str>,
 }
 
+impl Request<'_> {
+    /// Checks that the parameters required by the subcommand are present and that no other
+    /// parameters are set.
+    ///
+    /// Returns [`Error::MissingParameter`][] if a required parameter is missing and
+    /// [`Error::InvalidParameter`][] if an unexpected parameter is present or if the permissions
+    /// are empty.  The lengths of `new_pin_enc` and `pin_hash_enc` are checked against the PIN
+    /// protocol, see [`Request::validate_lengths`][].
+    pub fn validate(&self) -> Result<()> {
+        const KEY_AGREEMENT: u8 = 0x01;
+        const PIN_AUTH: u8 = 0x02;
+        const NEW_PIN_ENC: u8 = 0x04;
+        const PIN_HASH_ENC: u8 = 0x08;
+        const PERMISSIONS: u8 = 0x10;
+        const RP_ID: u8 = 0x20;
+
+        let required = match self.sub_command {
+            PinV1Subcommand::GetRetries
+            | PinV1Subcommand::GetKeyAgreement
+            | PinV1Subcommand::GetUVRetries => 0,
+            PinV1Subcommand::SetPin => KEY_AGREEMENT | PIN_AUTH | NEW_PIN_ENC,
+            PinV1Subcommand::ChangePin => KEY_AGREEMENT | PIN_AUTH | NEW_PIN_ENC | PIN_HASH_ENC,
+            PinV1Subcommand::GetPinToken => KEY_AGREEMENT | PIN_HASH_ENC,
+            PinV1Subcommand::GetPinUvAuthTokenUsingUvWithPermissions => KEY_AGREEMENT | PERMISSIONS,
+            PinV1Subcommand::GetPinUvAuthTokenUsingPinWithPermissions => {
+                KEY_AGREEMENT | PIN_HASH_ENC | PERMISSIONS
+            }
+        };
+        // the RP ID is optional for the subcommands with permissions
+        let allowed = if required & PERMISSIONS != 0 {
+            required | RP_ID
+        } else {
+            required
+        };
+
+        let parameters = [
+            (self.key_agreement.is_some(), KEY_AGREEMENT),
+            (self.pin_auth.is_some(), PIN_AUTH),
+            (self.new_pin_enc.is_some(), NEW_PIN_ENC),
+            (self.pin_hash_enc.is_some(), PIN_HASH_ENC),
+            (self.permissions.is_some(), PERMISSIONS),
+            (self.rp_id.is_some(), RP_ID),
+        ];
+        let present = parameters
+            .into_iter()
+            .filter(|(is_present, _)| *is_present)
+            .fold(0, |present, (_, parameter)| present | parameter);
+
+        if present & required != required {
+            return Err(Error::MissingParameter);
+        }
+        if present & !allowed != 0 {
+            return Err(Error::InvalidParameter);
+        }
+
+        if let Some(permissions) = self.permissions {
+            if permissions.is_empty() {
+                return Err(Error::InvalidParameter);
+            }
+            if permissions.requires_rp_id() && self.rp_id.is_none() {
+                return Err(Error::MissingParameter);
+            }
+        }
+
+        self.validate_lengths()
+    }
+
+    /// Checks the lengths of the encrypted PIN parameters for the PIN protocol of this request.
+    ///
+    /// `new_pin_enc` must be 64 bytes for protocol 1 and 80 bytes for protocol 2, otherwise
+    /// [`Error::PinPolicyViolation`][] is returned.  `pin_hash_enc` must be 16 bytes for protocol 1
+    /// and 32 bytes for protocol 2, otherwise [`Error::InvalidParameter`][] is returned.  If one of
+    /// these parameters is present, an unsupported PIN protocol is rejected with
+    /// [`Error::InvalidParameter`][].
+    pub fn validate_lengths(&self) -> Result<()> {
+        if self.new_pin_enc.is_none() && self.pin_hash_enc.is_none() {
+            return Ok(());
+        }
+        let (new_pin_enc_len, pin_hash_enc_len) =
+            match PinProtocolVersion::try_from(self.pin_protocol)? {
+                PinProtocolVersion::V1 => (64, 16),
+                PinProtocolVersion::V2 => (80, 32),
+            };
+        if let Some(new_pin_enc) = self.new_pin_enc {
+            if new_pin_enc.len() != new_pin_enc_len {
+                return Err(Error::PinPolicyViolation);
+            }
+        }
+        if let Some(pin_hash_enc) = self.pin_hash_enc {
+            if pin_hash_enc.len() != pin_hash_enc_len {
+                return Err(Error::InvalidParameter);
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
@@ -100,7 +325,7 @@ pub struct Response {
 
     // 0x02, encrypted `pinToken` using `sharedSecret`
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub pin_token: Option<Bytes<48>>,
+    pub pin_token: Option<EncryptedPinToken>,
 
     // 0x03, number of PIN attempts remaining before lockout
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -115,11 +340,125 @@ pub struct Response {
     pub uv_retries: Option<u8>,
 }
 
+impl Response {
+    /// Creates a response for the `getKeyAgreement` subcommand.
+    pub fn with_key_agreement(key_agreement: EcdhEsHkdf256PublicKey) -> Self {
+        Self {
+            key_agreement: Some(key_agreement),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a response with the encrypted PIN/UV auth token for the `getPinToken`,
+    /// `getPinUvAuthTokenUsingUvWithPermissions` and `getPinUvAuthTokenUsingPinWithPermissions`
+    /// subcommands.
+    pub fn with_pin_token(pin_token: EncryptedPinToken) -> Self {
+        Self {
+            pin_token: Some(pin_token),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a response for the `getPINRetries` subcommand.
+    ///
+    /// The optional `power_cycle_state` can be set afterwards.
+    pub fn with_retries(retries: u8) -> Self {
+        Self {
+            retries: Some(retries),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a response for the `getUVRetries` subcommand.
+    pub fn with_uv_retries(uv_retries: u8) -> Self {
+        Self {
+            uv_retries: Some(uv_retries),
+            ..Default::default()
+        }
+    }
+
+    /// Checks that the encrypted PIN/UV auth token, if present, is valid for the PIN protocol of
+    /// the given request.
+    pub fn validate(&self, request: &Request<'_>) -> Result<()> {
+        if let Some(pin_token) = &self.pin_token {
+            pin_token.validate(PinProtocolVersion::try_from(request.pin_protocol)?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+/// A response that only contains the fields that are valid for a single subcommand.
+///
+/// This can be converted into the [`Response`][] that is sent to the platform.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TypedResponse {
+    /// The response to the `getPINRetries` subcommand.
+    Retries {
+        retries: u8,
+        power_cycle_state: Option<bool>,
+    },
+    /// The response to the `getKeyAgreement` subcommand.
+    KeyAgreement(EcdhEsHkdf256PublicKey),
+    /// The response to the `getPinToken`, `getPinUvAuthTokenUsingUvWithPermissions` and
+    /// `getPinUvAuthTokenUsingPinWithPermissions` subcommands.
+    PinToken(EncryptedPinToken),
+    /// The response to the `getUVRetries` subcommand.
+    UvRetries(u8),
+    /// The empty response to the `setPIN` and `changePIN` subcommands.
+    Empty,
+}
+
+impl TypedResponse {
+    /// Checks whether this response is valid for the given subcommand.
+    pub fn is_valid_for(&self, sub_command: PinV1Subcommand) -> bool {
+        use PinV1Subcommand::*;
+        matches!(
+            (self, sub_command),
+            (Self::Retries { .. }, GetRetries)
+                | (Self::KeyAgreement(_), GetKeyAgreement)
+                | (
+                    Self::PinToken(_),
+                    GetPinToken
+                        | GetPinUvAuthTokenUsingUvWithPermissions
+                        | GetPinUvAuthTokenUsingPinWithPermissions
+                )
+                | (Self::UvRetries(_), GetUVRetries)
+                | (Self::Empty, SetPin | ChangePin)
+        )
+    }
+}
+
+impl From<TypedResponse> for Response {
+    fn from(response: TypedResponse) -> Self {
+        match response {
+            TypedResponse::Retries {
+                retries,
+                power_cycle_state,
+            } => Self {
+                power_cycle_state,
+                ..Self::with_retries(retries)
+            },
+            TypedResponse::KeyAgreement(key_agreement) => Self::with_key_agreement(key_agreement),
+            TypedResponse::PinToken(pin_token) => Self::with_pin_token(pin_token),
+            TypedResponse::UvRetries(uv_retries) => Self::with_uv_retries(uv_retries),
+            TypedResponse::Empty => Self::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use hex_literal::hex;
-    use serde_test::{assert_de_tokens, assert_ser_tokens, assert_tokens, Token};
+    use serde_test::{
+        assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, assert_tokens, Token,
+    };
 
     const KEY_AGREEMENT: &[u8] = &hex!("b174bc49c7ca254b70d2e5c207cee9cf174820ebd77ea3c65508c26da51b657c1cc6b952f8621697936482da0a6d3d3826a59095daf6cd7c03e2e60385d2f6d9");
     const NEW_PIN_ENC: &[u8] = &[0xde; 64];
@@ -187,9 +526,9 @@ mod tests {
 
     #[test]
     fn test_de_request_set_pin() {
-        let key_agreement = EcdhEsHkdf256PublicKey {
-            x: Bytes::from_slice(&KEY_AGREEMENT[..32]).unwrap(),
-            y: Bytes::from_slice(&KEY_AGREEMENT[32..]).unwrap(),
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
         };
         let request = Request {
             pin_protocol: 1,
@@ -245,9 +584,9 @@ mod tests {
 
     #[test]
     fn test_de_request_change_pin() {
-        let key_agreement = EcdhEsHkdf256PublicKey {
-            x: Bytes::from_slice(&KEY_AGREEMENT[..32]).unwrap(),
-            y: Bytes::from_slice(&KEY_AGREEMENT[32..]).unwrap(),
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
         };
         let request = Request {
             pin_protocol: 1,
@@ -306,9 +645,9 @@ mod tests {
 
     #[test]
     fn test_de_get_pin_token() {
-        let key_agreement = EcdhEsHkdf256PublicKey {
-            x: Bytes::from_slice(&KEY_AGREEMENT[..32]).unwrap(),
-            y: Bytes::from_slice(&KEY_AGREEMENT[32..]).unwrap(),
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
         };
         let request = Request {
             pin_protocol: 1,
@@ -361,9 +700,9 @@ mod tests {
 
     #[test]
     fn test_de_get_pin_token_with_permissions() {
-        let key_agreement = EcdhEsHkdf256PublicKey {
-            x: Bytes::from_slice(&KEY_AGREEMENT[..32]).unwrap(),
-            y: Bytes::from_slice(&KEY_AGREEMENT[32..]).unwrap(),
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
         };
         let request = Request {
             pin_protocol: 1,
@@ -374,7 +713,7 @@ mod tests {
             pin_hash_enc: Some(serde_bytes::Bytes::new(PIN_HASH_ENC)),
             _placeholder07: None,
             _placeholder08: None,
-            permissions: Some(0x04),
+            permissions: Some(Permissions::CREDENTIAL_MANAGEMENT),
             rp_id: Some("example.com"),
         };
         assert_de_tokens(
@@ -420,6 +759,93 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_request() {
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
+        };
+        let mut request = Request {
+            pin_protocol: 2,
+            sub_command: PinV1Subcommand::GetRetries,
+            key_agreement: None,
+            pin_auth: None,
+            new_pin_enc: None,
+            pin_hash_enc: None,
+            _placeholder07: None,
+            _placeholder08: None,
+            permissions: None,
+            rp_id: None,
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        request.pin_hash_enc = Some(serde_bytes::Bytes::new(&[0xda; 32]));
+        assert_eq!(request.validate(), Err(Error::InvalidParameter));
+
+        request.sub_command = PinV1Subcommand::GetPinToken;
+        assert_eq!(request.validate(), Err(Error::MissingParameter));
+        request.key_agreement = Some(key_agreement);
+        assert_eq!(request.validate(), Ok(()));
+        request.rp_id = Some("example.com");
+        assert_eq!(request.validate(), Err(Error::InvalidParameter));
+
+        request.sub_command = PinV1Subcommand::GetPinUvAuthTokenUsingPinWithPermissions;
+        assert_eq!(request.validate(), Err(Error::MissingParameter));
+        request.permissions = Some(Permissions::empty());
+        assert_eq!(request.validate(), Err(Error::InvalidParameter));
+        request.permissions = Some(Permissions::GET_ASSERTION);
+        assert_eq!(request.validate(), Ok(()));
+        request.rp_id = None;
+        assert_eq!(request.validate(), Err(Error::MissingParameter));
+        request.permissions = Some(Permissions::CREDENTIAL_MANAGEMENT);
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_lengths() {
+        let key_agreement = EcdhEsHkdf256PublicKeyRef {
+            x: serde_bytes::Bytes::new(&KEY_AGREEMENT[..32]),
+            y: serde_bytes::Bytes::new(&KEY_AGREEMENT[32..]),
+        };
+        let mut request = Request {
+            pin_protocol: 1,
+            sub_command: PinV1Subcommand::ChangePin,
+            key_agreement: Some(key_agreement),
+            pin_auth: Some(serde_bytes::Bytes::new(PIN_AUTH)),
+            new_pin_enc: Some(serde_bytes::Bytes::new(NEW_PIN_ENC)),
+            pin_hash_enc: Some(serde_bytes::Bytes::new(PIN_HASH_ENC)),
+            _placeholder07: None,
+            _placeholder08: None,
+            permissions: None,
+            rp_id: None,
+        };
+        assert_eq!(request.validate(), Ok(()));
+
+        request.pin_protocol = 2;
+        assert_eq!(request.validate(), Err(Error::PinPolicyViolation));
+        request.new_pin_enc = Some(serde_bytes::Bytes::new(&[0xde; 80]));
+        assert_eq!(request.validate(), Err(Error::InvalidParameter));
+        request.pin_hash_enc = Some(serde_bytes::Bytes::new(&[0xda; 32]));
+        assert_eq!(request.validate(), Ok(()));
+
+        request.pin_protocol = 3;
+        assert_eq!(request.validate_lengths(), Err(Error::InvalidParameter));
+    }
+
+    #[test]
+    fn test_serde_permissions() {
+        let permissions = Permissions::MAKE_CREDENTIAL | Permissions::LARGE_BLOB_WRITE;
+        assert_tokens(&permissions, &[Token::U8(0x11)]);
+        assert!(permissions.requires_rp_id());
+        assert!(!permissions.has_unknown_bits());
+
+        let permissions = Permissions::from_bits_retain(0x84);
+        assert_tokens(&permissions, &[Token::U8(0x84)]);
+        assert!(!permissions.requires_rp_id());
+        assert!(permissions.has_unknown_bits());
+        assert!(permissions.contains(Permissions::CREDENTIAL_MANAGEMENT));
+    }
+
     #[test]
     fn test_ser_response_get_retries() {
         let response = Response {
@@ -439,6 +865,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_response_constructors() {
+        assert_eq!(
+            Response::with_retries(3),
+            Response {
+                retries: Some(3),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            Response::with_uv_retries(5),
+            Response {
+                uv_retries: Some(5),
+                ..Default::default()
+            }
+        );
+
+        let key_agreement = EcdhEsHkdf256PublicKey {
+            x: Bytes::from_slice(&KEY_AGREEMENT[..32]).unwrap(),
+            y: Bytes::from_slice(&KEY_AGREEMENT[32..]).unwrap(),
+        };
+        assert_eq!(
+            Response::with_key_agreement(key_agreement.clone()),
+            Response {
+                key_agreement: Some(key_agreement),
+                ..Default::default()
+            }
+        );
+
+        let pin_token = EncryptedPinToken::new(PinProtocolVersion::V1, PIN_TOKEN).unwrap();
+        assert_eq!(
+            Response::with_pin_token(pin_token.clone()),
+            Response {
+                pin_token: Some(pin_token),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_typed_response() {
+        let response = TypedResponse::Retries {
+            retries: 3,
+            power_cycle_state: Some(true),
+        };
+        assert!(response.is_valid_for(PinV1Subcommand::GetRetries));
+        assert!(!response.is_valid_for(PinV1Subcommand::GetUVRetries));
+        let mut expected = Response::with_retries(3);
+        expected.power_cycle_state = Some(true);
+        assert_eq!(Response::from(response), expected);
+
+        let pin_token = EncryptedPinToken::new(PinProtocolVersion::V1, &[0; 32]).unwrap();
+        let response = TypedResponse::PinToken(pin_token.clone());
+        assert!(response.is_valid_for(PinV1Subcommand::GetPinUvAuthTokenUsingPinWithPermissions));
+        assert!(!response.is_valid_for(PinV1Subcommand::SetPin));
+        assert_eq!(
+            Response::from(response),
+            Response::with_pin_token(pin_token)
+        );
+
+        assert!(TypedResponse::Empty.is_valid_for(PinV1Subcommand::ChangePin));
+        assert_eq!(Response::from(TypedResponse::Empty), Response::default());
+    }
+
     #[test]
     fn test_ser_response_get_key_agreement() {
         let key_agreement = EcdhEsHkdf256PublicKey {
@@ -481,7 +971,7 @@ mod tests {
     #[test]
     fn test_ser_response_get_pin_token() {
         let response = Response {
-            pin_token: Some(Bytes::from_slice(PIN_TOKEN).unwrap()),
+            pin_token: Some(EncryptedPinToken::new(PinProtocolVersion::V1, PIN_TOKEN).unwrap()),
             ..Default::default()
         };
         assert_ser_tokens(
@@ -497,6 +987,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_encrypted_pin_token() {
+        assert!(EncryptedPinToken::new(PinProtocolVersion::V1, &[0; 32]).is_ok());
+        assert!(EncryptedPinToken::new(PinProtocolVersion::V2, &[0; 48]).is_ok());
+        assert_eq!(
+            EncryptedPinToken::new(PinProtocolVersion::V1, &[0; 16]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            EncryptedPinToken::new(PinProtocolVersion::V1, &[0; 48]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            EncryptedPinToken::new(PinProtocolVersion::V2, &[0; 32]),
+            Err(Error::InvalidLength)
+        );
+
+        let pin_token = EncryptedPinToken::new(PinProtocolVersion::V2, &[0xed; 48]).unwrap();
+        assert_tokens(&pin_token, &[Token::BorrowedBytes(&[0xed; 48])]);
+        assert_eq!(pin_token.validate(PinProtocolVersion::V2), Ok(()));
+        assert_eq!(
+            pin_token.validate(PinProtocolVersion::V1),
+            Err(Error::InvalidLength)
+        );
+        assert_de_tokens_error::<EncryptedPinToken>(
+            &[Token::BorrowedBytes(&[0xed; 16])],
+            "invalid length 16, expected 32 or 48 bytes",
+        );
+
+        assert_eq!(PinProtocolVersion::try_from(2), Ok(PinProtocolVersion::V2));
+        assert_eq!(
+            PinProtocolVersion::try_from(3),
+            Err(Error::InvalidParameter)
+        );
+    }
+
     #[test]
     fn pin_v1_subcommand() {
         // NB: This does *not* work without serde_repr, as the
@@ -1,14 +1,15 @@
-use cosey::PublicKey;
-use serde_bytes::ByteArray;
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use super::{large_blobs::LargeBlobKey, Authenticator, Error};
+use crate::cose::PublicKey;
 use crate::webauthn::{
     PublicKeyCredentialDescriptor, PublicKeyCredentialDescriptorRef, PublicKeyCredentialRpEntity,
-    PublicKeyCredentialUserEntity,
+    PublicKeyCredentialUserEntity, PublicKeyCredentialUserEntityRef, RpIdHash,
 };
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize_repr, Deserialize_repr)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(u8)]
 pub enum CredentialProtectionPolicy {
     #[default]
@@ -37,30 +38,122 @@ pub enum Subcommand {
 pub struct SubcommandParameters<'a> {
     // 0x01
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub rp_id_hash: Option<&'a ByteArray<32>>,
+    pub rp_id_hash: Option<&'a RpIdHash>,
     // 0x02
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credential_id: Option<PublicKeyCredentialDescriptorRef<'a>>,
     // 0x03
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub user: Option<PublicKeyCredentialUserEntity>,
+    pub user: Option<PublicKeyCredentialUserEntityRef<'a>>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 #[non_exhaustive]
-#[serde_indexed(offset = 1)]
 pub struct Request<'a> {
     // 0x01
     pub sub_command: Subcommand,
     // 0x02
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub sub_command_params: Option<SubcommandParameters<'a>>,
     // 0x03
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub pin_protocol: Option<u8>,
     // 0x04
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub pin_auth: Option<&'a serde_bytes::Bytes>,
+    /// The raw CBOR encoding of the subcommand parameters, captured when the request is parsed
+    /// as a [`ctap2::Request`][super::Request].
+    raw_sub_command_params: Option<&'a [u8]>,
+}
+
+/// The serialized fields of [`Request`][].
+#[derive(SerializeIndexed, DeserializeIndexed)]
+#[serde_indexed(offset = 1)]
+struct RawRequest<'a> {
+    sub_command: Subcommand,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sub_command_params: Option<SubcommandParameters<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pin_protocol: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pin_auth: Option<&'a serde_bytes::Bytes>,
+}
+
+impl<'de: 'a, 'a> serde::Deserialize<'de> for Request<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let fields: RawRequest<'a> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self::new(
+            fields.sub_command,
+            fields.sub_command_params,
+            fields.pin_protocol,
+            fields.pin_auth,
+        ))
+    }
+}
+
+impl serde::Serialize for Request<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let fields = RawRequest {
+            sub_command: self.sub_command,
+            sub_command_params: self.sub_command_params.clone(),
+            pin_protocol: self.pin_protocol,
+            pin_auth: self.pin_auth,
+        };
+        serde::Serialize::serialize(&fields, serializer)
+    }
+}
+
+impl<'a> Request<'a> {
+    pub fn new(
+        sub_command: Subcommand,
+        sub_command_params: Option<SubcommandParameters<'a>>,
+        pin_protocol: Option<u8>,
+        pin_auth: Option<&'a serde_bytes::Bytes>,
+    ) -> Self {
+        Self {
+            sub_command,
+            sub_command_params,
+            pin_protocol,
+            pin_auth,
+            raw_sub_command_params: None,
+        }
+    }
+
+    /// Stores the raw subcommand parameters from the given CBOR-encoded parameters, i.e. the
+    /// request without the command byte.
+    pub(crate) fn capture_sub_command_params(&mut self, parameters: &'a [u8]) {
+        if self.sub_command_params.is_some() {
+            self.raw_sub_command_params = crate::cbor::map_value(parameters, 0x02).flatten();
+        }
+    }
+
+    /// Writes the message that is authenticated by `pin_auth` to `buffer` and returns it.
+    ///
+    /// The message consists of the subcommand followed by the CBOR encoding of the subcommand
+    /// parameters, if present.  If the request has been parsed as a
+    /// [`ctap2::Request`][super::Request], the parameters are copied as sent by the platform.
+    /// Otherwise they are serialized, which does not preserve unknown transports of a credential
+    /// descriptor.  Returns [`Error::InvalidLength`][] if the buffer is too small.
+    pub fn pin_auth_message<'b>(&self, buffer: &'b mut [u8]) -> crate::Result<&'b [u8]> {
+        let (sub_command, data) = buffer.split_first_mut().ok_or(Error::InvalidLength)?;
+        *sub_command = self.sub_command as u8;
+        let len = match (self.raw_sub_command_params, &self.sub_command_params) {
+            (Some(raw), Some(_)) => {
+                data.get_mut(..raw.len())
+                    .ok_or(Error::InvalidLength)?
+                    .copy_from_slice(raw);
+                raw.len()
+            }
+            (_, Some(params)) => crate::serde::cbor_serialize(params, data)
+                .map_err(|_| Error::InvalidLength)?
+                .len(),
+            (_, None) => 0,
+        };
+        Ok(&buffer[..1 + len])
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, SerializeIndexed)]
@@ -83,7 +176,7 @@ pub struct Response {
     pub rp: Option<PublicKeyCredentialRpEntity>,
     // 0x04
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub rp_id_hash: Option<ByteArray<32>>,
+    pub rp_id_hash: Option<RpIdHash>,
     // 0x05
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_rps: Option<u32>,
@@ -107,9 +200,756 @@ pub struct Response {
     pub cred_protect: Option<CredentialProtectionPolicy>,
     // 0x0B
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub large_blob_key: Option<ByteArray<32>>,
+    pub large_blob_key: Option<LargeBlobKey>,
     // 0x0C
     #[cfg(feature = "third-party-payment")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub third_party_payment: Option<bool>,
 }
+
+impl Response {
+    /// Creates a response for the `getCredsMetadata` subcommand.
+    pub fn metadata(
+        existing_resident_credentials_count: u32,
+        max_possible_remaining_residential_credentials_count: u32,
+    ) -> Self {
+        Self {
+            existing_resident_credentials_count: Some(existing_resident_credentials_count),
+            max_possible_remaining_residential_credentials_count: Some(
+                max_possible_remaining_residential_credentials_count,
+            ),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a response for the `enumerateRPsBegin` and `enumerateRPsGetNextRP` subcommands.
+    ///
+    /// `total_rps` must only be set for `enumerateRPsBegin`.
+    pub fn rp(
+        rp: PublicKeyCredentialRpEntity,
+        rp_id_hash: RpIdHash,
+        total_rps: Option<u32>,
+    ) -> Self {
+        Self {
+            rp: Some(rp),
+            rp_id_hash: Some(rp_id_hash),
+            total_rps,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a response for the `enumerateCredentialsBegin` and
+    /// `enumerateCredentialsGetNextCredential` subcommands.
+    ///
+    /// `total_credentials` must only be set for `enumerateCredentialsBegin`.  The optional
+    /// `cred_protect` and `large_blob_key` fields can be set afterwards.
+    pub fn credential(
+        user: PublicKeyCredentialUserEntity,
+        credential_id: PublicKeyCredentialDescriptor,
+        public_key: PublicKey,
+        total_credentials: Option<u32>,
+    ) -> Self {
+        Self {
+            user: Some(user),
+            credential_id: Some(credential_id),
+            public_key: Some(public_key),
+            total_credentials,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> crate::Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+/// Deserializes a response returned by an authenticator.
+///
+/// The public key is deserialized as a [`PublicKey`][], see its documentation for the supported
+/// key types.
+#[cfg(feature = "platform")]
+impl<'de> serde::Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(DeserializeIndexed)]
+        #[serde_indexed(offset = 1)]
+        struct RawResponse {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            existing_resident_credentials_count: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            max_possible_remaining_residential_credentials_count: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            rp: Option<PublicKeyCredentialRpEntity>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            rp_id_hash: Option<RpIdHash>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            total_rps: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user: Option<PublicKeyCredentialUserEntity>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            credential_id: Option<PublicKeyCredentialDescriptor>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            public_key: Option<PublicKey>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            total_credentials: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cred_protect: Option<CredentialProtectionPolicy>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            large_blob_key: Option<LargeBlobKey>,
+            #[cfg(feature = "third-party-payment")]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            third_party_payment: Option<bool>,
+        }
+
+        let response: RawResponse = serde::Deserialize::deserialize(deserializer)?;
+        Ok(Self {
+            existing_resident_credentials_count: response.existing_resident_credentials_count,
+            max_possible_remaining_residential_credentials_count: response
+                .max_possible_remaining_residential_credentials_count,
+            rp: response.rp,
+            rp_id_hash: response.rp_id_hash,
+            total_rps: response.total_rps,
+            user: response.user,
+            credential_id: response.credential_id,
+            public_key: response.public_key,
+            total_credentials: response.total_credentials,
+            cred_protect: response.cred_protect,
+            large_blob_key: response.large_blob_key,
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment: response.third_party_payment,
+        })
+    }
+}
+
+/// A response that only contains the fields that are valid for a single subcommand.
+///
+/// This can be converted into the [`Response`][] that is sent to the platform.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TypedResponse {
+    /// The response to the `getCredsMetadata` subcommand (keys 0x01 and 0x02).
+    Metadata {
+        existing_resident_credentials_count: u32,
+        max_possible_remaining_residential_credentials_count: u32,
+    },
+    /// The response to the `enumerateRPsBegin` and `enumerateRPsGetNextRP` subcommands (keys
+    /// 0x03 to 0x05).
+    ///
+    /// `total_rps` must only be set for `enumerateRPsBegin`.
+    Rp {
+        rp: PublicKeyCredentialRpEntity,
+        rp_id_hash: RpIdHash,
+        total_rps: Option<u32>,
+    },
+    /// The response to the `enumerateCredentialsBegin` and
+    /// `enumerateCredentialsGetNextCredential` subcommands (keys 0x06 to 0x0C).
+    ///
+    /// `total_credentials` must only be set for `enumerateCredentialsBegin`.
+    Credential {
+        user: PublicKeyCredentialUserEntity,
+        credential_id: PublicKeyCredentialDescriptor,
+        public_key: PublicKey,
+        total_credentials: Option<u32>,
+        cred_protect: Option<CredentialProtectionPolicy>,
+        large_blob_key: Option<LargeBlobKey>,
+        #[cfg(feature = "third-party-payment")]
+        third_party_payment: Option<bool>,
+    },
+    /// The empty response to the `deleteCredential` and `updateUserInformation` subcommands.
+    Empty,
+}
+
+impl TypedResponse {
+    /// Checks whether this response is valid for the given subcommand.
+    pub fn is_valid_for(&self, sub_command: Subcommand) -> bool {
+        match self {
+            Self::Metadata { .. } => sub_command == Subcommand::GetCredsMetadata,
+            Self::Rp { total_rps, .. } => {
+                if total_rps.is_some() {
+                    sub_command == Subcommand::EnumerateRpsBegin
+                } else {
+                    sub_command == Subcommand::EnumerateRpsGetNextRp
+                }
+            }
+            Self::Credential {
+                total_credentials, ..
+            } => {
+                if total_credentials.is_some() {
+                    sub_command == Subcommand::EnumerateCredentialsBegin
+                } else {
+                    sub_command == Subcommand::EnumerateCredentialsGetNextCredential
+                }
+            }
+            Self::Empty => matches!(
+                sub_command,
+                Subcommand::DeleteCredential | Subcommand::UpdateUserInformation
+            ),
+        }
+    }
+}
+
+impl From<TypedResponse> for Response {
+    fn from(response: TypedResponse) -> Self {
+        match response {
+            TypedResponse::Metadata {
+                existing_resident_credentials_count,
+                max_possible_remaining_residential_credentials_count,
+            } => Self::metadata(
+                existing_resident_credentials_count,
+                max_possible_remaining_residential_credentials_count,
+            ),
+            TypedResponse::Rp {
+                rp,
+                rp_id_hash,
+                total_rps,
+            } => Self::rp(rp, rp_id_hash, total_rps),
+            TypedResponse::Credential {
+                user,
+                credential_id,
+                public_key,
+                total_credentials,
+                cred_protect,
+                large_blob_key,
+                #[cfg(feature = "third-party-payment")]
+                third_party_payment,
+            } => Self {
+                cred_protect,
+                large_blob_key,
+                #[cfg(feature = "third-party-payment")]
+                third_party_payment,
+                ..Self::credential(user, credential_id, public_key, total_credentials)
+            },
+            TypedResponse::Empty => Self::default(),
+        }
+    }
+}
+
+/// An RP returned by the `enumerateRPsBegin` and `enumerateRPsGetNextRP` subcommands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct EnumeratedRp {
+    pub rp: PublicKeyCredentialRpEntity,
+    pub rp_id_hash: RpIdHash,
+}
+
+impl TryFrom<Response> for EnumeratedRp {
+    type Error = Error;
+
+    fn try_from(response: Response) -> crate::Result<Self> {
+        Ok(Self {
+            rp: response.rp.ok_or(Error::MissingParameter)?,
+            rp_id_hash: response.rp_id_hash.ok_or(Error::MissingParameter)?,
+        })
+    }
+}
+
+/// A credential returned by the `enumerateCredentialsBegin` and
+/// `enumerateCredentialsGetNextCredential` subcommands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct EnumeratedCredential {
+    pub user: PublicKeyCredentialUserEntity,
+    pub credential_id: PublicKeyCredentialDescriptor,
+    pub public_key: PublicKey,
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+    pub large_blob_key: Option<LargeBlobKey>,
+    #[cfg(feature = "third-party-payment")]
+    pub third_party_payment: Option<bool>,
+}
+
+impl TryFrom<Response> for EnumeratedCredential {
+    type Error = Error;
+
+    fn try_from(response: Response) -> crate::Result<Self> {
+        Ok(Self {
+            user: response.user.ok_or(Error::MissingParameter)?,
+            credential_id: response.credential_id.ok_or(Error::MissingParameter)?,
+            public_key: response.public_key.ok_or(Error::MissingParameter)?,
+            cred_protect: response.cred_protect,
+            large_blob_key: response.large_blob_key,
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment: response.third_party_payment,
+        })
+    }
+}
+
+/// An iterator over the RPs with discoverable credentials on an authenticator.
+///
+/// The first call to `next` sends an `enumerateRPsBegin` request with the given PIN/UV auth
+/// parameters, the following calls send `enumerateRPsGetNextRP` requests until all RPs have been
+/// returned.  If the authenticator does not have any discoverable credentials, the iterator is
+/// empty.  After an error, the iterator is exhausted.
+pub struct RpIterator<'a, A> {
+    authenticator: &'a mut A,
+    pin_protocol: u8,
+    pin_auth: &'a [u8],
+    remaining: Option<u32>,
+}
+
+impl<'a, A: Authenticator> RpIterator<'a, A> {
+    pub fn new(authenticator: &'a mut A, pin_protocol: u8, pin_auth: &'a [u8]) -> Self {
+        Self {
+            authenticator,
+            pin_protocol,
+            pin_auth,
+            remaining: None,
+        }
+    }
+}
+
+impl<A: Authenticator> Iterator for RpIterator<'_, A> {
+    type Item = crate::Result<EnumeratedRp>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let begin = Request::new(
+            Subcommand::EnumerateRpsBegin,
+            None,
+            Some(self.pin_protocol),
+            Some(serde_bytes::Bytes::new(self.pin_auth)),
+        );
+        let response = next_response(
+            self.authenticator,
+            &mut self.remaining,
+            &begin,
+            Subcommand::EnumerateRpsGetNextRp,
+            |response| response.total_rps,
+        )?;
+        Some(response.and_then(TryFrom::try_from))
+    }
+}
+
+/// An iterator over the discoverable credentials for an RP on an authenticator.
+///
+/// The first call to `next` sends an `enumerateCredentialsBegin` request with the given RP ID
+/// hash and PIN/UV auth parameters, the following calls send
+/// `enumerateCredentialsGetNextCredential` requests until all credentials have been returned.  If
+/// the authenticator does not have any discoverable credentials for the RP, the iterator is
+/// empty.  After an error, the iterator is exhausted.
+pub struct CredentialIterator<'a, A> {
+    authenticator: &'a mut A,
+    rp_id_hash: &'a RpIdHash,
+    pin_protocol: u8,
+    pin_auth: &'a [u8],
+    remaining: Option<u32>,
+}
+
+impl<'a, A: Authenticator> CredentialIterator<'a, A> {
+    pub fn new(
+        authenticator: &'a mut A,
+        rp_id_hash: &'a RpIdHash,
+        pin_protocol: u8,
+        pin_auth: &'a [u8],
+    ) -> Self {
+        Self {
+            authenticator,
+            rp_id_hash,
+            pin_protocol,
+            pin_auth,
+            remaining: None,
+        }
+    }
+}
+
+impl<A: Authenticator> Iterator for CredentialIterator<'_, A> {
+    type Item = crate::Result<EnumeratedCredential>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let begin = Request::new(
+            Subcommand::EnumerateCredentialsBegin,
+            Some(SubcommandParameters {
+                rp_id_hash: Some(self.rp_id_hash),
+                credential_id: None,
+                user: None,
+            }),
+            Some(self.pin_protocol),
+            Some(serde_bytes::Bytes::new(self.pin_auth)),
+        );
+        let response = next_response(
+            self.authenticator,
+            &mut self.remaining,
+            &begin,
+            Subcommand::EnumerateCredentialsGetNextCredential,
+            |response| response.total_credentials,
+        )?;
+        Some(response.and_then(TryFrom::try_from))
+    }
+}
+
+/// Drives an enumeration: sends `begin` if it has not been started yet, otherwise `next` if there
+/// are items left.
+fn next_response<A: Authenticator>(
+    authenticator: &mut A,
+    remaining: &mut Option<u32>,
+    begin: &Request<'_>,
+    next: Subcommand,
+    total: fn(&Response) -> Option<u32>,
+) -> Option<crate::Result<Response>> {
+    let response = match *remaining {
+        None => match authenticator.credential_management(begin) {
+            Err(Error::NoCredentials) => {
+                *remaining = Some(0);
+                return None;
+            }
+            response => response.and_then(|response| {
+                let total = total(&response).ok_or(Error::MissingParameter)?;
+                *remaining = Some(total.saturating_sub(1));
+                Ok(response)
+            }),
+        },
+        Some(0) => return None,
+        Some(n) => {
+            *remaining = Some(n - 1);
+            authenticator.credential_management(&Request::new(next, None, None, None))
+        }
+    };
+    if response.is_err() {
+        *remaining = Some(0);
+    }
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_response() {
+        let response = TypedResponse::Metadata {
+            existing_resident_credentials_count: 2,
+            max_possible_remaining_residential_credentials_count: 8,
+        };
+        assert!(response.is_valid_for(Subcommand::GetCredsMetadata));
+        assert!(!response.is_valid_for(Subcommand::EnumerateRpsBegin));
+        let response = Response::from(response);
+        assert_eq!(response.existing_resident_credentials_count, Some(2));
+        assert_eq!(
+            response.max_possible_remaining_residential_credentials_count,
+            Some(8)
+        );
+        assert_eq!(response.rp, None);
+
+        let rp = PublicKeyCredentialRpEntity {
+            id: "example.com".into(),
+            name: None,
+            icon: None,
+        };
+        let response = TypedResponse::Rp {
+            rp: rp.clone(),
+            rp_id_hash: RpIdHash::new([0xab; 32]),
+            total_rps: None,
+        };
+        assert!(response.is_valid_for(Subcommand::EnumerateRpsGetNextRp));
+        assert!(!response.is_valid_for(Subcommand::EnumerateRpsBegin));
+        let response = Response::from(response);
+        assert_eq!(response.rp, Some(rp));
+        assert_eq!(response.total_rps, None);
+
+        assert!(TypedResponse::Empty.is_valid_for(Subcommand::DeleteCredential));
+        assert_eq!(Response::from(TypedResponse::Empty), Response::default());
+    }
+
+    /// Authenticator that only implements the enumeration subcommands of credential management.
+    #[derive(Default)]
+    struct FakeAuthenticator {
+        rps: std::vec::Vec<&'static str>,
+        credentials: std::vec::Vec<u8>,
+        requests: std::vec::Vec<Subcommand>,
+    }
+
+    impl FakeAuthenticator {
+        fn rp(&self, index: usize, total_rps: Option<u32>) -> crate::Result<Response> {
+            let id = self.rps.get(index).ok_or(Error::NoCredentials)?;
+            let rp = PublicKeyCredentialRpEntity {
+                id: (*id).into(),
+                name: None,
+                icon: None,
+            };
+            Ok(Response::rp(
+                rp,
+                RpIdHash::new([index as u8; 32]),
+                total_rps,
+            ))
+        }
+
+        fn credential(
+            &self,
+            index: usize,
+            total_credentials: Option<u32>,
+        ) -> crate::Result<Response> {
+            use crate::cose::P256PublicKey;
+            use crate::webauthn::{CredentialId, UserHandle};
+            use crate::Bytes;
+
+            let id = self.credentials.get(index).ok_or(Error::NoCredentials)?;
+            let user = PublicKeyCredentialUserEntity {
+                id: UserHandle::from_slice(&[*id; 16]).unwrap(),
+                icon: None,
+                name: None,
+                display_name: None,
+            };
+            let credential_id = PublicKeyCredentialDescriptor {
+                id: CredentialId::from_slice(&[*id; 32]).unwrap(),
+                key_type: "public-key".into(),
+                transports: None,
+            };
+            let public_key = PublicKey::P256Key(P256PublicKey {
+                x: Bytes::from_slice(&[*id; 32]).unwrap(),
+                y: Bytes::from_slice(&[*id; 32]).unwrap(),
+            });
+            Ok(Response::credential(
+                user,
+                credential_id,
+                public_key,
+                total_credentials,
+            ))
+        }
+    }
+
+    impl Authenticator for FakeAuthenticator {
+        fn get_info(&mut self) -> crate::ctap2::get_info::Response {
+            Default::default()
+        }
+
+        fn make_credential(
+            &mut self,
+            _request: &crate::ctap2::make_credential::Request,
+        ) -> crate::Result<crate::ctap2::make_credential::Response> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn get_assertion(
+            &mut self,
+            _request: &crate::ctap2::get_assertion::Request,
+        ) -> crate::Result<crate::ctap2::get_assertion::Response> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn get_next_assertion(&mut self) -> crate::Result<crate::ctap2::get_assertion::Response> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn reset(&mut self) -> crate::Result<()> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn client_pin(
+            &mut self,
+            _request: &crate::ctap2::client_pin::Request,
+        ) -> crate::Result<crate::ctap2::client_pin::Response> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn credential_management(&mut self, request: &Request) -> crate::Result<Response> {
+            self.requests.push(request.sub_command);
+            let index = self.requests.len() - 1;
+            match request.sub_command {
+                Subcommand::EnumerateRpsBegin => {
+                    assert_eq!(request.pin_auth, Some(serde_bytes::Bytes::new(&[0xaa; 16])));
+                    self.rp(index, Some(self.rps.len() as u32))
+                }
+                Subcommand::EnumerateRpsGetNextRp => self.rp(index, None),
+                Subcommand::EnumerateCredentialsBegin => {
+                    assert_eq!(request.pin_auth, Some(serde_bytes::Bytes::new(&[0xaa; 16])));
+                    let rp_id_hash = request
+                        .sub_command_params
+                        .as_ref()
+                        .and_then(|params| params.rp_id_hash);
+                    assert_eq!(rp_id_hash, Some(&RpIdHash::new([0x42; 32])));
+                    self.credential(index, Some(self.credentials.len() as u32))
+                }
+                Subcommand::EnumerateCredentialsGetNextCredential => self.credential(index, None),
+                _ => Err(Error::InvalidSubcommand),
+            }
+        }
+
+        fn selection(&mut self) -> crate::Result<()> {
+            Err(Error::InvalidCommand)
+        }
+
+        fn vendor(&mut self, _op: crate::ctap2::VendorOperation) -> crate::Result<()> {
+            Err(Error::InvalidCommand)
+        }
+    }
+
+    #[test]
+    fn test_rp_iterator() {
+        let mut authenticator = FakeAuthenticator {
+            rps: std::vec!["example.com", "example.org"],
+            ..Default::default()
+        };
+        let rps: std::vec::Vec<_> = RpIterator::new(&mut authenticator, 2, &[0xaa; 16])
+            .map(|rp| rp.unwrap().rp.id)
+            .collect();
+        assert_eq!(rps, ["example.com", "example.org"]);
+        assert_eq!(
+            authenticator.requests,
+            [
+                Subcommand::EnumerateRpsBegin,
+                Subcommand::EnumerateRpsGetNextRp
+            ]
+        );
+
+        let mut authenticator = FakeAuthenticator::default();
+        assert_eq!(
+            RpIterator::new(&mut authenticator, 2, &[0xaa; 16]).next(),
+            None
+        );
+        assert_eq!(authenticator.requests, [Subcommand::EnumerateRpsBegin]);
+    }
+
+    #[test]
+    fn test_credential_iterator() {
+        let rp_id_hash = RpIdHash::new([0x42; 32]);
+        let mut authenticator = FakeAuthenticator {
+            credentials: std::vec![0x01, 0x02, 0x03],
+            ..Default::default()
+        };
+        let mut iter = CredentialIterator::new(&mut authenticator, &rp_id_hash, 2, &[0xaa; 16]);
+        for id in [0x01, 0x02, 0x03] {
+            let credential = iter.next().unwrap().unwrap();
+            assert_eq!(credential.credential_id.id.as_ref(), &[id; 32]);
+            assert_eq!(credential.user.id.as_bytes(), &[id; 16]);
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next(), None);
+        assert_eq!(
+            authenticator.requests,
+            [
+                Subcommand::EnumerateCredentialsBegin,
+                Subcommand::EnumerateCredentialsGetNextCredential,
+                Subcommand::EnumerateCredentialsGetNextCredential,
+            ]
+        );
+
+        let mut authenticator = FakeAuthenticator::default();
+        assert_eq!(
+            CredentialIterator::new(&mut authenticator, &rp_id_hash, 2, &[0xaa; 16]).next(),
+            None
+        );
+        assert_eq!(
+            authenticator.requests,
+            [Subcommand::EnumerateCredentialsBegin]
+        );
+    }
+
+    #[test]
+    fn test_pin_auth_message() {
+        let mut request = Request::new(
+            Subcommand::GetCredsMetadata,
+            None,
+            Some(2),
+            Some(serde_bytes::Bytes::new(&[0xaa; 32])),
+        );
+        let mut buffer = [0; 64];
+        assert_eq!(request.pin_auth_message(&mut buffer), Ok([0x01].as_slice()));
+
+        let rp_id_hash = RpIdHash::new([0xab; 32]);
+        request.sub_command = Subcommand::EnumerateCredentialsBegin;
+        request.sub_command_params = Some(SubcommandParameters {
+            rp_id_hash: Some(&rp_id_hash),
+            credential_id: None,
+            user: None,
+        });
+        let message = request.pin_auth_message(&mut buffer).unwrap();
+        assert_eq!(message[..5], [0x04, 0xa1, 0x01, 0x58, 0x20]);
+        assert_eq!(message[5..], [0xab; 32]);
+
+        assert_eq!(
+            request.pin_auth_message(&mut [0; 16]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(request.pin_auth_message(&mut []), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_pin_auth_message_unknown_transport() {
+        let mut params = std::vec::Vec::from(b"\xa1\x02\xa3bidP".as_slice());
+        params.extend_from_slice(&[0x02; 16]);
+        params.extend_from_slice(b"dtypejpublic-keyjtransports\x82cusbgunknown");
+        let mut command = std::vec::Vec::from(b"\x0a\xa2\x01\x06\x02".as_slice());
+        command.extend_from_slice(&params);
+
+        let Ok(crate::ctap2::Request::CredentialManagement(request)) =
+            crate::ctap2::Request::deserialize(&command)
+        else {
+            panic!("failed to parse credentialManagement request");
+        };
+        let credential_id = request
+            .sub_command_params
+            .as_ref()
+            .and_then(|params| params.credential_id.as_ref())
+            .unwrap();
+        assert_eq!(
+            credential_id.transports.as_deref(),
+            Some([crate::webauthn::AuthenticatorTransport::Usb].as_slice())
+        );
+
+        let mut buffer = [0; 128];
+        let message = request.pin_auth_message(&mut buffer).unwrap();
+        assert_eq!(message[0], 0x06);
+        assert_eq!(&message[1..], params.as_slice());
+        assert_eq!(
+            request.pin_auth_message(&mut [0; 16]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_response_constructors() {
+        let response = Response::metadata(2, 8);
+        let mut buffer = [0; 16];
+        assert_eq!(
+            crate::serde::cbor_serialize(&response, &mut buffer).unwrap(),
+            &[0xa2, 0x01, 0x02, 0x02, 0x08]
+        );
+
+        let rp = PublicKeyCredentialRpEntity {
+            id: "example.com".into(),
+            name: None,
+            icon: None,
+        };
+        let response = Response::rp(rp.clone(), RpIdHash::new([0xab; 32]), Some(3));
+        assert_eq!(response.rp, Some(rp));
+        assert_eq!(response.rp_id_hash, Some(RpIdHash::new([0xab; 32])));
+        assert_eq!(response.total_rps, Some(3));
+        assert_eq!(response.existing_resident_credentials_count, None);
+    }
+
+    #[cfg(feature = "platform")]
+    #[test]
+    fn test_deserialize_response() {
+        use crate::cose::P256PublicKey;
+        use crate::webauthn::{CredentialId, UserHandle};
+        use crate::Bytes;
+
+        let response = Response::from(TypedResponse::Credential {
+            user: PublicKeyCredentialUserEntity {
+                id: UserHandle::from_slice(&[0x01; 16]).unwrap(),
+                icon: None,
+                name: Some("john.doe".into()),
+                display_name: None,
+            },
+            credential_id: PublicKeyCredentialDescriptor {
+                id: CredentialId::from_slice(&[0x02; 32]).unwrap(),
+                key_type: "public-key".into(),
+                transports: None,
+            },
+            public_key: PublicKey::P256Key(P256PublicKey {
+                x: Bytes::from_slice(&[0x03; 32]).unwrap(),
+                y: Bytes::from_slice(&[0x04; 32]).unwrap(),
+            }),
+            total_credentials: Some(1),
+            cred_protect: Some(CredentialProtectionPolicy::Required),
+            large_blob_key: None,
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment: None,
+        });
+        let mut buffer = [0; 512];
+        let serialized = crate::serde::cbor_serialize(&response, &mut buffer).unwrap();
+        let deserialized: Response = crate::serde::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, response);
+    }
+}
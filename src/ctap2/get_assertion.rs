@@ -1,32 +1,313 @@
-use crate::{Bytes, Vec};
-use cosey::EcdhEsHkdf256PublicKey;
-use serde::{Deserialize, Serialize};
-use serde_bytes::ByteArray;
+use crate::cose::EcdhEsHkdf256PublicKeyRef;
+use crate::Bytes;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 
-use super::{AttestationFormatsPreference, AttestationStatement, AuthenticatorOptions, Result};
+use super::{
+    client_pin::PinProtocolVersion, large_blobs::LargeBlobKey, AttestationFormatsPreference,
+    AttestationStatement, AuthenticatorOptions, Error, Result,
+};
 use crate::sizes::*;
-use crate::webauthn::*;
+use crate::webauthn::{count_descriptors, DescriptorIter, *};
 
 #[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
-pub struct HmacSecretInput {
-    pub key_agreement: EcdhEsHkdf256PublicKey,
-    // *either* enc(salt1) *or* enc(salt1 || salt2)
-    pub salt_enc: Bytes<80>,
+pub struct HmacSecretInput<'a> {
+    pub key_agreement: EcdhEsHkdf256PublicKeyRef<'a>,
+    pub salt_enc: EncryptedSalts,
     pub salt_auth: Bytes<32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pin_protocol: Option<u32>,
 }
 
+impl HmacSecretInput<'_> {
+    /// Returns the PIN protocol used to encrypt the salts.
+    ///
+    /// If `pin_protocol` is not set, PIN protocol one is used.  Unsupported versions are rejected
+    /// with [`Error::InvalidParameter`][].
+    pub fn pin_protocol_version(&self) -> Result<PinProtocolVersion> {
+        match self.pin_protocol {
+            None => Ok(PinProtocolVersion::V1),
+            Some(pin_protocol) => u8::try_from(pin_protocol)
+                .map_err(|_| Error::InvalidParameter)?
+                .try_into(),
+        }
+    }
+
+    /// Checks the lengths of `salt_enc` and `salt_auth` against the declared PIN protocol.
+    ///
+    /// - PIN protocol one: `salt_enc` has 32 or 64 bytes, `salt_auth` has 16 bytes.
+    /// - PIN protocol two: `salt_enc` has 48 or 80 bytes, `salt_auth` has 32 bytes.
+    ///
+    /// A mismatching `salt_enc` is rejected with [`Error::InvalidLength`][], a mismatching
+    /// `salt_auth` with [`Error::PinAuthInvalid`][] as it cannot be verified.
+    pub fn validate(&self) -> Result<()> {
+        let version = self.pin_protocol_version()?;
+        if !self.salt_enc.is_valid_for(version) {
+            return Err(Error::InvalidLength);
+        }
+        let salt_auth_len = match version {
+            PinProtocolVersion::V1 => 16,
+            PinProtocolVersion::V2 => 32,
+        };
+        if self.salt_auth.len() != salt_auth_len {
+            return Err(Error::PinAuthInvalid);
+        }
+        Ok(())
+    }
+}
+
+/// The maximum length of the encrypted hmac-secret salts and outputs.
+///
+/// With PIN protocol two, the two 32 byte values are prefixed with a 16 byte IV.
+pub const HMAC_SECRET_MAX_LENGTH: usize = 80;
+
+/// Returns the valid lengths of a hmac-secret ciphertext with one or two values for the given PIN
+/// protocol.
+fn hmac_secret_lengths(version: PinProtocolVersion) -> [usize; 2] {
+    match version {
+        PinProtocolVersion::V1 => [32, 64],
+        PinProtocolVersion::V2 => [48, 80],
+    }
+}
+
+/// Returns the number of values encrypted in a hmac-secret ciphertext of the given length.
+///
+/// PIN protocol one encrypts without an IV, so one value results in 32 bytes and two values in 64
+/// bytes.  PIN protocol two prepends a 16 byte IV, so one value results in 48 bytes and two values
+/// in 80 bytes.
+fn hmac_secret_value_count(len: usize) -> Option<usize> {
+    match len {
+        32 | 48 => Some(1),
+        64 | 80 => Some(2),
+        _ => None,
+    }
+}
+
+/// The encrypted salts of the hmac-secret extension, *either* `enc(salt1)` *or*
+/// `enc(salt1 || salt2)`.
+///
+/// The length is validated on construction so that the number of salts can be determined.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(
+    into = "Bytes<HMAC_SECRET_MAX_LENGTH>",
+    try_from = "Bytes<HMAC_SECRET_MAX_LENGTH>"
+)]
+pub struct EncryptedSalts(Bytes<HMAC_SECRET_MAX_LENGTH>);
+
+impl EncryptedSalts {
+    pub fn from_slice(salts: &[u8]) -> Result<Self> {
+        Bytes::<HMAC_SECRET_MAX_LENGTH>::from_slice(salts)
+            .map_err(|_| Error::InvalidLength)?
+            .try_into()
+    }
+
+    /// Returns the number of encrypted salts, i.e. one or two.
+    pub fn salt_count(&self) -> usize {
+        // the length has been validated on construction
+        hmac_secret_value_count(self.0.len()).unwrap_or_default()
+    }
+
+    /// Returns true if the length of the ciphertext is valid for the given PIN protocol.
+    pub fn is_valid_for(&self, version: PinProtocolVersion) -> bool {
+        hmac_secret_lengths(version).contains(&self.0.len())
+    }
+
+    /// Returns the IV, which is only present for PIN protocol two.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the length is not valid for the PIN protocol.
+    pub fn iv(&self, version: PinProtocolVersion) -> Result<Option<&[u8; 16]>> {
+        self.split(version).map(|(iv, _)| iv)
+    }
+
+    /// Returns the ciphertext of the first salt, i.e. the first 32 bytes after the IV.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the length is not valid for the PIN protocol.
+    pub fn salt1(&self, version: PinProtocolVersion) -> Result<&[u8; 32]> {
+        let (_, ciphertext) = self.split(version)?;
+        Ok(ciphertext[..32].try_into().unwrap())
+    }
+
+    /// Returns the ciphertext of the second salt, if present.
+    ///
+    /// As the salts are encrypted with AES-256-CBC, the last block of [`EncryptedSalts::salt1`][]
+    /// is the IV for the second salt.  Returns [`Error::InvalidLength`][] if the length is not
+    /// valid for the PIN protocol.
+    pub fn salt2(&self, version: PinProtocolVersion) -> Result<Option<&[u8; 32]>> {
+        let (_, ciphertext) = self.split(version)?;
+        Ok(ciphertext.get(32..).map(|salt2| salt2.try_into().unwrap()))
+    }
+
+    /// Splits the decrypted salts, i.e. `salt1` or `salt1 || salt2`, into the individual salts.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the plaintext does not have 32 or 64 bytes.
+    pub fn split_decrypted(plaintext: &[u8]) -> Result<(&[u8; 32], Option<&[u8; 32]>)> {
+        match plaintext.len() {
+            32 => Ok((plaintext.try_into().unwrap(), None)),
+            64 => {
+                let (salt1, salt2) = plaintext.split_at(32);
+                Ok((salt1.try_into().unwrap(), Some(salt2.try_into().unwrap())))
+            }
+            _ => Err(Error::InvalidLength),
+        }
+    }
+
+    /// Splits the ciphertext into the optional IV and the encrypted salts.
+    fn split(&self, version: PinProtocolVersion) -> Result<(Option<&[u8; 16]>, &[u8])> {
+        if !self.is_valid_for(version) {
+            return Err(Error::InvalidLength);
+        }
+        match version {
+            PinProtocolVersion::V1 => Ok((None, &self.0)),
+            PinProtocolVersion::V2 => {
+                let (iv, ciphertext) = self.0.split_at(16);
+                Ok((Some(iv.try_into().unwrap()), ciphertext))
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for EncryptedSalts {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl TryFrom<Bytes<HMAC_SECRET_MAX_LENGTH>> for EncryptedSalts {
+    type Error = Error;
+
+    fn try_from(salts: Bytes<HMAC_SECRET_MAX_LENGTH>) -> Result<Self> {
+        hmac_secret_value_count(salts.len()).ok_or(Error::InvalidLength)?;
+        Ok(Self(salts))
+    }
+}
+
+impl From<EncryptedSalts> for Bytes<HMAC_SECRET_MAX_LENGTH> {
+    fn from(salts: EncryptedSalts) -> Self {
+        salts.0
+    }
+}
+
+/// The encrypted outputs of the hmac-secret extension, *either* `enc(output1)` *or*
+/// `enc(output1 || output2)`.
+///
+/// The length is validated on construction so that the number of outputs can be determined.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(
+    into = "Bytes<HMAC_SECRET_MAX_LENGTH>",
+    try_from = "Bytes<HMAC_SECRET_MAX_LENGTH>"
+)]
+pub struct EncryptedOutputs(Bytes<HMAC_SECRET_MAX_LENGTH>);
+
+impl EncryptedOutputs {
+    pub fn from_slice(outputs: &[u8]) -> Result<Self> {
+        Bytes::<HMAC_SECRET_MAX_LENGTH>::from_slice(outputs)
+            .map_err(|_| Error::InvalidLength)?
+            .try_into()
+    }
+
+    /// Returns the number of encrypted outputs, i.e. one or two.
+    pub fn output_count(&self) -> usize {
+        // the length has been validated on construction
+        hmac_secret_value_count(self.0.len()).unwrap_or_default()
+    }
+
+    /// Returns true if the length of the ciphertext is valid for the given PIN protocol.
+    pub fn is_valid_for(&self, version: PinProtocolVersion) -> bool {
+        hmac_secret_lengths(version).contains(&self.0.len())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for EncryptedOutputs {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl TryFrom<Bytes<HMAC_SECRET_MAX_LENGTH>> for EncryptedOutputs {
+    type Error = Error;
+
+    fn try_from(outputs: Bytes<HMAC_SECRET_MAX_LENGTH>) -> Result<Self> {
+        hmac_secret_value_count(outputs.len()).ok_or(Error::InvalidLength)?;
+        Ok(Self(outputs))
+    }
+}
+
+impl From<EncryptedOutputs> for Bytes<HMAC_SECRET_MAX_LENGTH> {
+    fn from(outputs: EncryptedOutputs) -> Self {
+        outputs.0
+    }
+}
+
+/// Computes the hmac-secret salt for an input of the WebAuthn `prf` extension.
+///
+/// The salt is `SHA-256("WebAuthn PRF" || 0x00 || input)`, see
+/// https://www.w3.org/TR/webauthn-3/#prf-extension
+#[cfg(feature = "sha2")]
+pub fn prf_salt(input: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"WebAuthn PRF\x00");
+    hasher.update(input);
+    hasher.finalize().into()
+}
+
+/// Computes the plaintext hmac-secret salts, *either* `salt1` *or* `salt1 || salt2`, for the
+/// `first` and `second` inputs of a `prf` evaluation.
+///
+/// The result has to be encrypted with the shared secret before it can be used as
+/// [`HmacSecretInput::salt_enc`][].
+#[cfg(feature = "sha2")]
+pub fn prf_salts(first: &[u8], second: Option<&[u8]>) -> Bytes<64> {
+    let mut salts = [0; 64];
+    salts[..32].copy_from_slice(&prf_salt(first));
+    let len = if let Some(second) = second {
+        salts[32..].copy_from_slice(&prf_salt(second));
+        64
+    } else {
+        32
+    };
+    Bytes::from_slice(&salts[..len]).unwrap()
+}
+
+/// Splits the decrypted hmac-secret outputs, *either* `output1` *or* `output1 || output2`, into
+/// the `first` and `second` results of a `prf` evaluation.
+///
+/// Returns [`Error::InvalidLength`][] if the outputs do not consist of one or two 32 byte values.
+pub fn prf_results(outputs: &[u8]) -> Result<(&[u8; 32], Option<&[u8; 32]>)> {
+    match outputs.len() {
+        32 => Ok((outputs.try_into().unwrap(), None)),
+        64 => {
+            let (first, second) = outputs.split_at(32);
+            Ok((first.try_into().unwrap(), Some(second.try_into().unwrap())))
+        }
+        _ => Err(Error::InvalidLength),
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
-pub struct ExtensionsInput {
+pub struct ExtensionsInput<'a> {
+    /// Whether the credBlob stored with the credential is requested.
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<bool>,
+
     #[serde(rename = "hmac-secret")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub hmac_secret: Option<HmacSecretInput>,
+    #[serde(borrow)]
+    pub hmac_secret: Option<HmacSecretInput<'a>>,
 
     /// Whether a large blob key is requested.
     #[serde(rename = "largeBlobKey")]
@@ -42,10 +323,14 @@ pub struct ExtensionsInput {
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ExtensionsOutput {
+    /// The credBlob stored with the credential, or an empty byte string if there is none.
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<Bytes<MAX_CRED_BLOB_LENGTH>>,
+
     #[serde(rename = "hmac-secret")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    // *either* enc(output1) *or* enc(output1 || output2)
-    pub hmac_secret: Option<Bytes<80>>,
+    pub hmac_secret: Option<EncryptedOutputs>,
 
     #[cfg(feature = "third-party-payment")]
     #[serde(rename = "thirdPartyPayment")]
@@ -57,11 +342,12 @@ impl ExtensionsOutput {
     #[inline]
     pub fn is_set(&self) -> bool {
         let Self {
+            cred_blob,
             hmac_secret,
             #[cfg(feature = "third-party-payment")]
             third_party_payment,
         } = self;
-        if hmac_secret.is_some() {
+        if cred_blob.is_some() || hmac_secret.is_some() {
             return true;
         }
         #[cfg(feature = "third-party-payment")]
@@ -75,15 +361,97 @@ impl ExtensionsOutput {
 pub struct NoAttestedCredentialData;
 
 impl super::SerializeAttestedCredentialData for NoAttestedCredentialData {
-    fn serialize(&self, _buffer: &mut super::SerializedAuthenticatorData) -> Result<()> {
+    fn serialize<const N: usize>(&self, _buffer: &mut Bytes<N>) -> Result<()> {
         Ok(())
     }
 }
 
+impl<'a> super::DeserializeAttestedCredentialData<'a> for NoAttestedCredentialData {
+    fn deserialize(_data: &'a [u8]) -> Result<(Self, &'a [u8])> {
+        // authenticator data for assertions must not contain attested credential data
+        Err(Error::InvalidParameter)
+    }
+}
+
 pub type AuthenticatorData<'a> =
     super::AuthenticatorData<'a, NoAttestedCredentialData, ExtensionsOutput>;
 
-pub type AllowList<'a> = Vec<PublicKeyCredentialDescriptorRef<'a>, MAX_CREDENTIAL_COUNT_IN_LIST>;
+/// The allow list of a getAssertion request.
+///
+/// To support allow lists of arbitrary length, the entries are validated during deserialization
+/// but not stored.  Instead, the allow list borrows the raw CBOR array from the request so that
+/// the entries can be deserialized on demand with [`AllowList::iter`][].
+///
+/// The raw array is captured when the request is parsed as a [`ctap2::Request`][super::Request].
+/// If the allow list is deserialized on its own, only the number of entries is known and
+/// [`AllowList::iter`][] returns an error.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AllowList<'a> {
+    data: &'a [u8],
+    len: usize,
+}
+
+impl<'a> AllowList<'a> {
+    /// Returns the number of entries in the allow list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the entries of the allow list.
+    ///
+    /// If the raw array has not been captured because the allow list was not parsed as part of
+    /// a [`ctap2::Request`][super::Request], [`Error::Other`][] is returned.  If the raw array is
+    /// malformed, [`Error::InvalidCbor`][] is returned.
+    pub fn iter(&self) -> Result<AllowListIter<'a>> {
+        DescriptorIter::from_array(self.data, self.len).map(AllowListIter)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for AllowList<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        count_descriptors(deserializer).map(|len| Self { data: &[], len })
+    }
+}
+
+/// A lazy iterator over the allow list of a getAssertion request.
+///
+/// The entries are deserialized on demand from the raw request so that the memory usage does not
+/// depend on the length of the allow list.
+#[derive(Clone, Debug)]
+pub struct AllowListIter<'a>(DescriptorIter<'a>);
+
+impl<'a> AllowListIter<'a> {
+    /// Creates an iterator over the allow list of the given CBOR-encoded getAssertion parameters,
+    /// i.e. the request without the command byte.
+    ///
+    /// If the request does not contain an allow list, the iterator is empty.  If the request is
+    /// malformed, [`Error::InvalidCbor`][] is returned.
+    pub fn new(parameters: &'a [u8]) -> Result<Self> {
+        DescriptorIter::new(parameters, 0x03).map(Self)
+    }
+}
+
+impl<'a> Iterator for AllowListIter<'a> {
+    type Item = Result<PublicKeyCredentialDescriptorRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for AllowListIter<'_> {}
 
 #[derive(Clone, Debug, Eq, PartialEq, DeserializeIndexed)]
 #[non_exhaustive]
@@ -94,7 +462,7 @@ pub struct Request<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_list: Option<AllowList<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub extensions: Option<ExtensionsInput>,
+    pub extensions: Option<ExtensionsInput<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<AuthenticatorOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -107,6 +475,63 @@ pub struct Request<'a> {
     pub attestation_formats_preference: Option<AttestationFormatsPreference>,
 }
 
+impl<'a> Request<'a> {
+    /// Stores the raw allow list from the given CBOR-encoded parameters, i.e. the request without
+    /// the command byte, in [`Request::allow_list`][].
+    pub(crate) fn capture_allow_list(&mut self, parameters: &'a [u8]) {
+        if let Some(allow_list) = &mut self.allow_list {
+            if let Some(Some(data)) = crate::cbor::map_value(parameters, 0x03) {
+                allow_list.data = data;
+            }
+        }
+    }
+
+    /// Performs the parameter checks of the specification that do not depend on the state or the
+    /// capabilities of the authenticator.
+    ///
+    /// - If `pin_auth` is present but empty, the platform asks the user to select this
+    ///   authenticator.  The authenticator has to collect user presence and then return
+    ///   [`Error::PinNotSet`][] or [`Error::PinInvalid`][], so no further checks are performed.
+    /// - If `pin_auth` is present, `pin_protocol` must be present too.  Whether the protocol is
+    ///   supported has to be checked by the authenticator.
+    /// - The `rk` option must not be present.
+    /// - The lengths of the hmac-secret input must match its PIN protocol, see
+    ///   [`HmacSecretInput::validate`][].
+    ///
+    /// If `pin_auth` is present, the `uv` option should be ignored by the authenticator.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(pin_auth) = self.pin_auth {
+            if pin_auth.is_empty() {
+                return Ok(());
+            }
+            if self.pin_protocol.is_none() {
+                return Err(Error::MissingParameter);
+            }
+        }
+        if let Some(options) = &self.options {
+            if options.rk.is_some() {
+                return Err(Error::UnsupportedOption);
+            }
+        }
+        if let Some(hmac_secret) = self
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.hmac_secret.as_ref())
+        {
+            hmac_secret.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the allow list if it is present and not empty.
+    ///
+    /// An empty allow list has to be treated like a missing allow list, i.e. the authenticator
+    /// should use discoverable credentials.
+    pub fn allowed_credentials(&self) -> Option<AllowList<'a>> {
+        self.allow_list.filter(|allow_list| !allow_list.is_empty())
+    }
+}
+
 // NB: attn object definition / order at end of
 // https://fidoalliance.org/specs/fido-v2.0-ps-20190130/fido-client-to-authenticator-protocol-v2.0-ps-20190130.html#authenticatorMakeCredential
 // does not coincide with what python-fido2 expects in AttestationObject.__init__ *at all* :'-)
@@ -116,7 +541,7 @@ pub struct Request<'a> {
 pub struct Response {
     pub credential: PublicKeyCredentialDescriptor,
     pub auth_data: Bytes<AUTHENTICATOR_DATA_LENGTH>,
-    pub signature: Bytes<ASN1_SIGNATURE_LENGTH>,
+    pub signature: Bytes<MAX_SIGNATURE_LENGTH>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<PublicKeyCredentialUserEntity>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -126,7 +551,7 @@ pub struct Response {
     /// A key that can be used to encrypt and decrypt large blob data.
     /// See https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#sctn-getAssert-authnr-alg
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub large_blob_key: Option<ByteArray<32>>,
+    pub large_blob_key: Option<LargeBlobKey>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -135,11 +560,78 @@ pub struct Response {
     pub att_stmt: Option<AttestationStatement>,
 }
 
+impl Response {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+/// Deserializes a response returned by an authenticator.
+///
+/// As the response does not contain the format of the attestation statement, it is inferred from
+/// the fields of the statement, see [`AttestationStatement`][].
+#[cfg(feature = "platform")]
+impl<'de> Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use super::attestation::RawAttestationStatement;
+        use serde::de::Error as _;
+
+        #[derive(DeserializeIndexed)]
+        #[serde_indexed(offset = 1)]
+        struct RawResponse<'a> {
+            credential: PublicKeyCredentialDescriptor,
+            auth_data: Bytes<AUTHENTICATOR_DATA_LENGTH>,
+            signature: Bytes<MAX_SIGNATURE_LENGTH>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user: Option<PublicKeyCredentialUserEntity>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            number_of_credentials: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user_selected: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            large_blob_key: Option<LargeBlobKey>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            ep_att: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            att_stmt: Option<RawAttestationStatement<'a>>,
+        }
+
+        let response = RawResponse::deserialize(deserializer)?;
+        let att_stmt = response
+            .att_stmt
+            .map(|att_stmt| {
+                att_stmt
+                    .infer_format()
+                    .and_then(|format| att_stmt.into_statement(format))
+                    .ok_or_else(|| D::Error::custom("invalid attestation statement"))
+            })
+            .transpose()?;
+        Ok(Self {
+            credential: response.credential,
+            auth_data: response.auth_data,
+            signature: response.signature,
+            user: response.user,
+            number_of_credentials: response.number_of_credentials,
+            user_selected: response.user_selected,
+            large_blob_key: response.large_blob_key,
+            unsigned_extension_outputs: response.unsigned_extension_outputs,
+            ep_att: response.ep_att,
+            att_stmt,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct ResponseBuilder {
     pub credential: PublicKeyCredentialDescriptor,
     pub auth_data: Bytes<AUTHENTICATOR_DATA_LENGTH>,
-    pub signature: Bytes<ASN1_SIGNATURE_LENGTH>,
+    pub signature: Bytes<MAX_SIGNATURE_LENGTH>,
 }
 
 impl ResponseBuilder {
@@ -160,6 +652,428 @@ impl ResponseBuilder {
     }
 }
 
+/// Same as [`Response`] but borrowing the larger fields, most notably the authenticator data and
+/// the signature.
+#[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed)]
+#[non_exhaustive]
+#[serde_indexed(offset = 1)]
+pub struct ResponseRef<'a> {
+    pub credential: PublicKeyCredentialDescriptorRef<'a>,
+    pub auth_data: &'a serde_bytes::Bytes,
+    pub signature: &'a serde_bytes::Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<&'a PublicKeyCredentialUserEntity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_credentials: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_selected: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob_key: Option<LargeBlobKey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ep_att: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub att_stmt: Option<&'a AttestationStatement>,
+}
+
+impl ResponseRef<'_> {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+impl<'a> From<&'a Response> for ResponseRef<'a> {
+    fn from(response: &'a Response) -> Self {
+        Self {
+            credential: PublicKeyCredentialDescriptorRef {
+                id: response.credential.id.as_id_ref(),
+                key_type: &response.credential.key_type,
+                transports: response.credential.transports.clone(),
+            },
+            auth_data: serde_bytes::Bytes::new(&response.auth_data),
+            signature: serde_bytes::Bytes::new(&response.signature),
+            user: response.user.as_ref(),
+            number_of_credentials: response.number_of_credentials,
+            user_selected: response.user_selected,
+            large_blob_key: response.large_blob_key,
+            unsigned_extension_outputs: response.unsigned_extension_outputs.clone(),
+            ep_att: response.ep_att,
+            att_stmt: response.att_stmt.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseRefBuilder<'a> {
+    pub credential: PublicKeyCredentialDescriptorRef<'a>,
+    pub auth_data: &'a [u8],
+    pub signature: &'a [u8],
+}
+
+impl<'a> ResponseRefBuilder<'a> {
+    #[inline(always)]
+    pub fn build(self) -> ResponseRef<'a> {
+        ResponseRef {
+            credential: self.credential,
+            auth_data: serde_bytes::Bytes::new(self.auth_data),
+            signature: serde_bytes::Bytes::new(self.signature),
+            user: None,
+            number_of_credentials: None,
+            user_selected: None,
+            large_blob_key: None,
+            unsigned_extension_outputs: None,
+            ep_att: None,
+            att_stmt: None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 #[non_exhaustive]
 pub struct UnsignedExtensionOutputs {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_request() {
+        let cbor = b"\xa3\x01kexample.com\x02X \xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\x03\x80";
+        let mut request: Request = cbor_smol::cbor_deserialize(cbor.as_slice()).unwrap();
+        assert_eq!(request.validate(), Ok(()));
+        assert_eq!(request.allow_list.map(|list| list.len()), Some(0));
+        assert_eq!(request.allowed_credentials(), None);
+
+        request.options = Some(AuthenticatorOptions {
+            rk: Some(false),
+            up: Some(false),
+            uv: None,
+        });
+        assert_eq!(request.validate(), Err(Error::UnsupportedOption));
+
+        request.options = None;
+        request.pin_auth = Some(serde_bytes::Bytes::new(&[0x50; 16]));
+        assert_eq!(request.validate(), Err(Error::MissingParameter));
+        request.pin_protocol = Some(1);
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let cbor = b"\xa2\x01kexample.com\x02X \xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd";
+        let request: Request = cbor_smol::cbor_deserialize(cbor.as_slice()).unwrap();
+        assert_eq!(request.allow_list, None);
+        assert_eq!(AllowListIter::new(cbor).unwrap().len(), 0);
+
+        // more entries than MAX_CREDENTIAL_COUNT_IN_LIST
+        let mut data = std::vec::Vec::from(cbor.as_slice());
+        data[0] = 0xa3;
+        data.extend_from_slice(b"\x03\x8c");
+        for i in 0..12 {
+            data.extend_from_slice(b"\xa2bidP");
+            data.extend_from_slice(&[i; 16]);
+            data.extend_from_slice(b"dtypejpublic-key");
+        }
+        let request: Request = cbor_smol::cbor_deserialize(&data).unwrap();
+        assert_eq!(
+            request.allowed_credentials().map(|list| list.len()),
+            Some(12)
+        );
+        // the raw allow list is only captured by ctap2::Request
+        assert_eq!(
+            request.allowed_credentials().unwrap().iter().unwrap_err(),
+            Error::Other
+        );
+
+        let iter = AllowListIter::new(&data).unwrap();
+        assert_eq!(iter.len(), 12);
+        for (i, descriptor) in iter.enumerate() {
+            let descriptor = descriptor.unwrap();
+            assert_eq!(descriptor.id.as_ref(), &[i as u8; 16]);
+            assert_eq!(descriptor.key_type, "public-key");
+        }
+        assert_eq!(
+            AllowListIter::new(&data[..data.len() - 1]).unwrap_err(),
+            Error::InvalidCbor
+        );
+
+        let mut command = std::vec![0x02];
+        command.extend_from_slice(&data);
+        let Ok(crate::ctap2::Request::GetAssertion(request)) =
+            crate::ctap2::Request::deserialize(&command)
+        else {
+            panic!("failed to parse getAssertion request");
+        };
+        let allow_list = request.allowed_credentials().unwrap();
+        assert_eq!(allow_list.len(), 12);
+        assert_eq!(allow_list.iter().unwrap().len(), 12);
+        for (i, descriptor) in allow_list.iter().unwrap().enumerate() {
+            assert_eq!(descriptor.unwrap().id.as_ref(), &[i as u8; 16]);
+        }
+    }
+
+    #[test]
+    fn test_encrypted_salts() {
+        assert_eq!(
+            EncryptedSalts::from_slice(&[0x5a; 32])
+                .unwrap()
+                .salt_count(),
+            1
+        );
+        assert_eq!(
+            EncryptedSalts::from_slice(&[0x5a; 48])
+                .unwrap()
+                .salt_count(),
+            1
+        );
+        assert_eq!(
+            EncryptedSalts::from_slice(&[0x5a; 64])
+                .unwrap()
+                .salt_count(),
+            2
+        );
+        assert_eq!(
+            EncryptedSalts::from_slice(&[0x5a; 80])
+                .unwrap()
+                .salt_count(),
+            2
+        );
+        for len in [0, 16, 33, 96] {
+            assert_eq!(
+                EncryptedSalts::from_slice(&[0x5a; 96][..len]),
+                Err(Error::InvalidLength)
+            );
+        }
+
+        let mut data = [0x5a; 80];
+        data[..16].fill(0x01);
+        data[48..].fill(0x02);
+        let salts = EncryptedSalts::from_slice(&data).unwrap();
+        assert_eq!(salts.iv(PinProtocolVersion::V2), Ok(Some(&[0x01; 16])));
+        assert_eq!(salts.salt1(PinProtocolVersion::V2), Ok(&[0x5a; 32]));
+        assert_eq!(salts.salt2(PinProtocolVersion::V2), Ok(Some(&[0x02; 32])));
+        assert_eq!(salts.iv(PinProtocolVersion::V1), Err(Error::InvalidLength));
+        assert_eq!(
+            salts.salt1(PinProtocolVersion::V1),
+            Err(Error::InvalidLength)
+        );
+
+        let salts = EncryptedSalts::from_slice(&data[16..48]).unwrap();
+        assert_eq!(salts.iv(PinProtocolVersion::V1), Ok(None));
+        assert_eq!(salts.salt1(PinProtocolVersion::V1), Ok(&[0x5a; 32]));
+        assert_eq!(salts.salt2(PinProtocolVersion::V1), Ok(None));
+
+        assert_eq!(
+            EncryptedSalts::split_decrypted(&data[16..]),
+            Ok((&[0x5a; 32], Some(&[0x02; 32])))
+        );
+        assert_eq!(
+            EncryptedSalts::split_decrypted(&data[48..]),
+            Ok((&[0x02; 32], None))
+        );
+        assert_eq!(
+            EncryptedSalts::split_decrypted(&data[..48]),
+            Err(Error::InvalidLength)
+        );
+
+        let outputs = EncryptedOutputs::from_slice(&[0x0e; 48]).unwrap();
+        assert_eq!(outputs.output_count(), 1);
+        assert_eq!(outputs.as_bytes(), &[0x0e; 48]);
+
+        let mut buffer = [0; 128];
+        let serialized = cbor_smol::cbor_serialize(&outputs, &mut buffer).unwrap();
+        assert_eq!(&serialized[..2], &[0x58, 48]);
+        let deserialized: EncryptedOutputs = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, outputs);
+        assert!(cbor_smol::cbor_deserialize::<EncryptedSalts>(&[0x50; 17][..]).is_err());
+    }
+
+    #[test]
+    fn test_validate_hmac_secret_input() {
+        let x = [0x01; 32];
+        let y = [0x02; 32];
+        let mut input = HmacSecretInput {
+            key_agreement: EcdhEsHkdf256PublicKeyRef {
+                x: serde_bytes::Bytes::new(&x),
+                y: serde_bytes::Bytes::new(&y),
+            },
+            salt_enc: EncryptedSalts::from_slice(&[0x5a; 64]).unwrap(),
+            salt_auth: Bytes::from_slice(&[0xa5; 16]).unwrap(),
+            pin_protocol: None,
+        };
+        assert_eq!(input.pin_protocol_version(), Ok(PinProtocolVersion::V1));
+        assert_eq!(input.validate(), Ok(()));
+
+        input.pin_protocol = Some(2);
+        assert_eq!(input.validate(), Err(Error::InvalidLength));
+        input.salt_enc = EncryptedSalts::from_slice(&[0x5a; 80]).unwrap();
+        assert_eq!(input.validate(), Err(Error::PinAuthInvalid));
+        input.salt_auth = Bytes::from_slice(&[0xa5; 32]).unwrap();
+        assert_eq!(input.validate(), Ok(()));
+        assert!(input.salt_enc.is_valid_for(PinProtocolVersion::V2));
+        assert!(!input.salt_enc.is_valid_for(PinProtocolVersion::V1));
+
+        input.pin_protocol = Some(3);
+        assert_eq!(input.validate(), Err(Error::InvalidParameter));
+        input.pin_protocol = Some(0x101);
+        assert_eq!(input.validate(), Err(Error::InvalidParameter));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_prf_salts() {
+        use hex_literal::hex;
+
+        let first = hex!("e9dfe497cb7e3b350ab7e7e0103b46142e37aefda2bf9cf576d6b94a4762aa35");
+        let second = hex!("339638f02d3ac0fcbaf33a905bbd568d1748d77fe78911400f1997040506adb8");
+        assert_eq!(prf_salt(b"first"), first);
+        assert_eq!(prf_salts(b"first", None).as_slice(), first.as_slice());
+        let salts = prf_salts(b"first", Some(b"second"));
+        assert_eq!(&salts[..32], first.as_slice());
+        assert_eq!(&salts[32..], second.as_slice());
+    }
+
+    #[test]
+    fn test_prf_results() {
+        let mut outputs = [0x01; 64];
+        outputs[32..].fill(0x02);
+        assert_eq!(prf_results(&outputs[..32]), Ok((&[0x01; 32], None)));
+        assert_eq!(prf_results(&outputs), Ok((&[0x01; 32], Some(&[0x02; 32]))));
+        assert_eq!(prf_results(&outputs[..48]), Err(Error::InvalidLength));
+        assert_eq!(prf_results(&[]), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_ser_extensions_output() {
+        let mut extensions = ExtensionsOutput::default();
+        assert!(!extensions.is_set());
+        extensions.cred_blob = Some(Bytes::new());
+        assert!(extensions.is_set());
+
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+        assert_eq!(serialized, b"\xa1\x68credBlob\x40");
+
+        extensions.cred_blob = Some(Bytes::from_slice(&[0xcb; 32]).unwrap());
+        let serialized = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+        let deserialized: ExtensionsOutput = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, extensions);
+
+        let input: ExtensionsInput<'_> =
+            cbor_smol::cbor_deserialize(b"\xa1\x68credBlob\xf5").unwrap();
+        assert_eq!(input.cred_blob, Some(true));
+    }
+
+    #[test]
+    fn test_ser_extensions_input_canonical() {
+        let x = [0x01; 32];
+        let y = [0x02; 32];
+        let extensions = ExtensionsInput {
+            cred_blob: Some(true),
+            hmac_secret: Some(HmacSecretInput {
+                key_agreement: EcdhEsHkdf256PublicKeyRef {
+                    x: serde_bytes::Bytes::new(&x),
+                    y: serde_bytes::Bytes::new(&y),
+                },
+                salt_enc: EncryptedSalts::from_slice(&[0x5a; 32]).unwrap(),
+                salt_auth: Bytes::from_slice(&[0xa5; 16]).unwrap(),
+                pin_protocol: None,
+            }),
+            large_blob_key: Some(true),
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment: Some(true),
+        };
+        let mut buffer = [0; 256];
+        let serialized = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+
+        let mut command = std::vec::Vec::from(b"\x02\xa3\x01kexample.com\x02X ".as_slice());
+        command.extend_from_slice(&[0xcd; 32]);
+        command.push(0x04);
+        command.extend_from_slice(serialized);
+        let Ok(crate::ctap2::Request::GetAssertion(request)) =
+            crate::ctap2::Request::deserialize_strict(&command)
+        else {
+            panic!("failed to parse getAssertion request");
+        };
+        assert_eq!(request.extensions, Some(extensions));
+    }
+
+    #[cfg(feature = "platform")]
+    #[test]
+    fn test_deserialize_response() {
+        use crate::ctap2::{NoneAttestationStatement, PackedAttestationStatement};
+
+        let mut response = ResponseBuilder {
+            credential: PublicKeyCredentialDescriptor {
+                id: CredentialId::from_slice(&[0xc1; 64]).unwrap(),
+                key_type: "public-key".into(),
+                transports: None,
+            },
+            auth_data: Bytes::from_slice(&[0xad; 37]).unwrap(),
+            signature: Bytes::from_slice(&[0x5e; 70]).unwrap(),
+        }
+        .build();
+        response.number_of_credentials = Some(2);
+        response.user_selected = Some(true);
+        let mut buffer = [0; 512];
+        let serialized = cbor_smol::cbor_serialize(&response, &mut buffer).unwrap();
+        let deserialized: Response = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, response);
+
+        let statements = [
+            AttestationStatement::None(NoneAttestationStatement {}),
+            AttestationStatement::Packed(PackedAttestationStatement {
+                alg: -7,
+                sig: Bytes::from_slice(&[0x5e; 70]).unwrap(),
+                x5c: None,
+            }),
+        ];
+        for statement in statements {
+            response.ep_att = Some(true);
+            response.att_stmt = Some(statement);
+            let serialized = cbor_smol::cbor_serialize(&response, &mut buffer).unwrap();
+            let deserialized: Response = cbor_smol::cbor_deserialize(serialized).unwrap();
+            assert_eq!(deserialized, response);
+        }
+    }
+
+    #[test]
+    fn test_ser_response_ref() {
+        let credential_id = [0xc1; 64];
+        let auth_data = [0xad; 37];
+        let signature = [0x5e; 72];
+        let mut response = ResponseBuilder {
+            credential: PublicKeyCredentialDescriptor {
+                id: CredentialId::from_slice(&credential_id).unwrap(),
+                key_type: "public-key".into(),
+                transports: None,
+            },
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
+            signature: Bytes::from_slice(&signature).unwrap(),
+        }
+        .build();
+        response.number_of_credentials = Some(2);
+
+        let mut response_ref = ResponseRefBuilder {
+            credential: PublicKeyCredentialDescriptorRef {
+                id: CredentialIdRef::new(&credential_id),
+                key_type: "public-key",
+                transports: None,
+            },
+            auth_data: &auth_data,
+            signature: &signature,
+        }
+        .build();
+        response_ref.number_of_credentials = Some(2);
+        assert_eq!(ResponseRef::from(&response), response_ref);
+
+        let mut owned = [0; 256];
+        let mut borrowed = [0; 256];
+        assert_eq!(
+            cbor_smol::cbor_serialize(&response, &mut owned).unwrap(),
+            cbor_smol::cbor_serialize(&response_ref, &mut borrowed).unwrap(),
+        );
+    }
+}
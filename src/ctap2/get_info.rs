@@ -14,7 +14,7 @@ pub struct Response {
 
     // 0x02
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub extensions: Option<Vec<Extension, 4>>,
+    pub extensions: Option<Vec<Extension, 8>>,
 
     // 0x03
     pub aaguid: Bytes<16>,
@@ -135,6 +135,13 @@ pub struct Response {
     pub long_touch_for_reset: Option<bool>,
 }
 
+impl Response {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> crate::Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
 impl Default for Response {
     fn default() -> Self {
         let mut zero_aaguid = Vec::<u8, 16>::new();
@@ -248,15 +255,19 @@ impl TryFrom<&str> for Version {
 #[non_exhaustive]
 #[serde(into = "&str", try_from = "&str")]
 pub enum Extension {
+    CredBlob,
     CredProtect,
     HmacSecret,
+    HmacSecretMc,
     LargeBlobKey,
     ThirdPartyPayment,
 }
 
 impl Extension {
+    const CRED_BLOB: &'static str = "credBlob";
     const CRED_PROTECT: &'static str = "credProtect";
     const HMAC_SECRET: &'static str = "hmac-secret";
+    const HMAC_SECRET_MC: &'static str = "hmac-secret-mc";
     const LARGE_BLOB_KEY: &'static str = "largeBlobKey";
     const THIRD_PARTY_PAYMENT: &'static str = "thirdPartyPayment";
 }
@@ -264,8 +275,10 @@ impl Extension {
 impl From<Extension> for &str {
     fn from(extension: Extension) -> Self {
         match extension {
+            Extension::CredBlob => Extension::CRED_BLOB,
             Extension::CredProtect => Extension::CRED_PROTECT,
             Extension::HmacSecret => Extension::HMAC_SECRET,
+            Extension::HmacSecretMc => Extension::HMAC_SECRET_MC,
             Extension::LargeBlobKey => Extension::LARGE_BLOB_KEY,
             Extension::ThirdPartyPayment => Extension::THIRD_PARTY_PAYMENT,
         }
@@ -277,8 +290,10 @@ impl TryFrom<&str> for Extension {
 
     fn try_from(s: &str) -> Result<Self, Self::Error> {
         match s {
+            Self::CRED_BLOB => Ok(Self::CredBlob),
             Self::CRED_PROTECT => Ok(Self::CredProtect),
             Self::HMAC_SECRET => Ok(Self::HmacSecret),
+            Self::HMAC_SECRET_MC => Ok(Self::HmacSecretMc),
             Self::LARGE_BLOB_KEY => Ok(Self::LargeBlobKey),
             Self::THIRD_PARTY_PAYMENT => Ok(Self::ThirdPartyPayment),
             _ => Err(TryFromStrError),
@@ -463,8 +478,10 @@ mod tests {
     #[test]
     fn test_serde_extension() {
         let extensions = [
+            (Extension::CredBlob, "credBlob"),
             (Extension::CredProtect, "credProtect"),
             (Extension::HmacSecret, "hmac-secret"),
+            (Extension::HmacSecretMc, "hmac-secret-mc"),
             (Extension::LargeBlobKey, "largeBlobKey"),
         ];
         for (extension, s) in extensions {
@@ -1,7 +1,11 @@
 use crate::sizes::LARGE_BLOB_MAX_FRAGMENT_LENGTH;
 use crate::Bytes;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteArray;
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 
+use super::{Error, Result};
+
 // See: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#largeBlobsRW
 #[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
 #[non_exhaustive]
@@ -26,13 +30,458 @@ pub struct Request<'a> {
     pub pin_uv_auth_protocol: Option<u32>,
 }
 
+impl Request<'_> {
+    /// Checks the parameters of the request according to the rules of the specification that do
+    /// not depend on the stored data.
+    ///
+    /// `max_fragment_length` is the maximum length of a fragment and `max_blob_size` is the
+    /// maximum size of the serialized large-blob array.  The offset of `set` requests has to be
+    /// checked separately, see [`WriteState`][].
+    ///
+    /// - [`Error::InvalidParameter`][] is returned if not exactly one of `get` and `set` is
+    ///   present, if `length` is present for a `get` request or for a `set` request with a
+    ///   non-zero offset, if it is missing for a `set` request with offset zero or too small for
+    ///   the hash.  PIN/UV auth parameters of a `get` request are ignored.
+    /// - [`Error::InvalidLength`][] is returned if the requested or provided fragment is longer
+    ///   than `max_fragment_length`.
+    /// - [`Error::LargeBlobStorageFull`][] is returned if `length` exceeds `max_blob_size`.
+    /// - [`Error::PinRequired`][] is returned if `pin_uv_auth_param` is missing for a `set`
+    ///   request and [`Error::MissingParameter`][] if `pin_uv_auth_protocol` is missing.
+    pub fn validate(&self, max_fragment_length: usize, max_blob_size: usize) -> Result<()> {
+        match (self.get, self.set) {
+            (Some(get), None) => {
+                if self.length.is_some() {
+                    return Err(Error::InvalidParameter);
+                }
+                if get as usize > max_fragment_length {
+                    return Err(Error::InvalidLength);
+                }
+            }
+            (None, Some(set)) => {
+                if set.len() > max_fragment_length {
+                    return Err(Error::InvalidLength);
+                }
+                if self.offset == 0 {
+                    let length = self.length.ok_or(Error::InvalidParameter)?;
+                    if length as usize > max_blob_size {
+                        return Err(Error::LargeBlobStorageFull);
+                    }
+                    if (length as usize) < LARGE_BLOB_ARRAY_HASH_LENGTH + 1 {
+                        return Err(Error::InvalidParameter);
+                    }
+                } else if self.length.is_some() {
+                    return Err(Error::InvalidParameter);
+                }
+                if self.pin_uv_auth_param.is_none() {
+                    return Err(Error::PinRequired);
+                }
+                if self.pin_uv_auth_protocol.is_none() {
+                    return Err(Error::MissingParameter);
+                }
+            }
+            _ => return Err(Error::InvalidParameter),
+        }
+        Ok(())
+    }
+
+    /// Returns the message that is authenticated by `pin_uv_auth_param` for a write request.
+    ///
+    /// Returns `None` if `set` is not present.  See [`pin_auth_message`][].
+    #[cfg(feature = "sha2")]
+    pub fn pin_auth_message(&self) -> Option<[u8; 70]> {
+        self.set.map(|set| pin_auth_message(self.offset, set))
+    }
+}
+
+/// Returns the message that is authenticated by `pin_uv_auth_param` for writing `data` at
+/// `offset`.
+///
+/// The message is `0xff` repeated 32 times, followed by `0x0c 0x00`, the offset as a little
+/// endian 32-bit integer and the SHA-256 hash of the data.
+#[cfg(feature = "sha2")]
+pub fn pin_auth_message(offset: u32, data: &[u8]) -> [u8; 70] {
+    use sha2::{Digest, Sha256};
+
+    let mut message = [0xff; 70];
+    message[32..34].copy_from_slice(&[0x0c, 0x00]);
+    message[34..38].copy_from_slice(&offset.to_le_bytes());
+    message[38..].copy_from_slice(&Sha256::digest(data));
+    message
+}
+
+/// Keeps track of a write of the serialized large-blob array that spans multiple `set` requests.
+///
+/// The default value represents the state before the first write.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WriteState {
+    expected_length: u32,
+    expected_next_offset: u32,
+}
+
+impl WriteState {
+    /// Checks the offset of a validated `set` request and updates the state.
+    ///
+    /// A request with offset zero starts a new write.  Returns [`Error::InvalidSeq`][] if the
+    /// offset does not continue the current write and [`Error::InvalidParameter`][] if the
+    /// fragment exceeds the announced length.  On success, returns whether the write is complete,
+    /// i.e. whether the complete array has been received and its hash has to be verified.
+    pub fn update(&mut self, request: &Request<'_>) -> Result<bool> {
+        let set = request.set.ok_or(Error::InvalidParameter)?;
+        if request.offset == 0 {
+            *self = Self {
+                expected_length: request.length.ok_or(Error::InvalidParameter)?,
+                expected_next_offset: 0,
+            };
+        } else if request.offset != self.expected_next_offset {
+            return Err(Error::InvalidSeq);
+        }
+        let next_offset = u32::try_from(set.len())
+            .ok()
+            .and_then(|len| request.offset.checked_add(len))
+            .filter(|next_offset| *next_offset <= self.expected_length)
+            .ok_or(Error::InvalidParameter)?;
+        self.expected_next_offset = next_offset;
+        Ok(next_offset == self.expected_length)
+    }
+
+    /// Returns the offset that the next `set` request has to use to continue the current write.
+    pub fn expected_next_offset(&self) -> u32 {
+        self.expected_next_offset
+    }
+}
+
+/// The length of the truncated SHA-256 hash at the end of the serialized large-blob array.
+pub const LARGE_BLOB_ARRAY_HASH_LENGTH: usize = 16;
+
+/// The initial serialized large-blob array: an empty CBOR array followed by its hash.
+pub const EMPTY_LARGE_BLOB_ARRAY: [u8; 17] = [
+    0x80, 0x76, 0xbe, 0x8b, 0x52, 0x8d, 0x00, 0x75, 0xf7, 0xaa, 0xe9, 0x8d, 0x6f, 0xa5, 0x7a, 0x6d,
+    0x3c,
+];
+
+/// The per-credential key that encrypts the entries of the large-blob array.
+///
+/// It is returned by makeCredential, getAssertion and credential management if the `largeBlobKey`
+/// extension is used and serialized as a 32 byte string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LargeBlobKey(ByteArray<32>);
+
+impl LargeBlobKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(ByteArray::new(key))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn into_array(self) -> [u8; 32] {
+        self.0.into_array()
+    }
+}
+
+impl AsRef<[u8]> for LargeBlobKey {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl From<[u8; 32]> for LargeBlobKey {
+    fn from(key: [u8; 32]) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<ByteArray<32>> for LargeBlobKey {
+    fn from(key: ByteArray<32>) -> Self {
+        Self(key)
+    }
+}
+
+impl From<LargeBlobKey> for [u8; 32] {
+    fn from(key: LargeBlobKey) -> Self {
+        key.into_array()
+    }
+}
+
+impl From<LargeBlobKey> for ByteArray<32> {
+    fn from(key: LargeBlobKey) -> Self {
+        key.0
+    }
+}
+
+impl TryFrom<&[u8]> for LargeBlobKey {
+    type Error = Error;
+
+    fn try_from(key: &[u8]) -> Result<Self> {
+        key.try_into()
+            .map(Self::new)
+            .map_err(|_| Error::InvalidLength)
+    }
+}
+
+/// An entry of the large-blob array.
+///
+/// See: https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#large-blob
+#[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
+#[non_exhaustive]
+#[serde_indexed(offset = 1)]
+pub struct LargeBlobEntry<'a> {
+    // 0x01
+    pub ciphertext: &'a serde_bytes::Bytes,
+    // 0x02
+    pub nonce: &'a ByteArray<12>,
+    // 0x03
+    pub orig_size: u64,
+}
+
+impl<'a> LargeBlobEntry<'a> {
+    pub fn new(ciphertext: &'a [u8], nonce: &'a ByteArray<12>, orig_size: u64) -> Self {
+        Self {
+            ciphertext: serde_bytes::Bytes::new(ciphertext),
+            nonce,
+            orig_size,
+        }
+    }
+}
+
+/// A serialized large-blob array, i.e. a CBOR array of [`LargeBlobEntry`][]s followed by the
+/// left 16 bytes of the SHA-256 hash of the array.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LargeBlobArray<'a> {
+    array: &'a [u8],
+    hash: &'a [u8],
+}
+
+impl<'a> LargeBlobArray<'a> {
+    /// Splits a serialized large-blob array into the CBOR array and the hash.
+    ///
+    /// Returns [`Error::InvalidCbor`][] if the data does not start with a well-formed CBOR array
+    /// that is followed by exactly 16 bytes.  The hash is not verified, see
+    /// [`LargeBlobArray::verify`][].
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let (major, _, _) = crate::cbor::head(data).ok_or(Error::InvalidCbor)?;
+        let len = crate::cbor::item_len(data).ok_or(Error::InvalidCbor)?;
+        if major != 4 || data.len() - len != LARGE_BLOB_ARRAY_HASH_LENGTH {
+            return Err(Error::InvalidCbor);
+        }
+        let (array, hash) = data.split_at(len);
+        Ok(Self { array, hash })
+    }
+
+    /// Returns the serialized CBOR array without the hash.
+    pub fn array(&self) -> &'a [u8] {
+        self.array
+    }
+
+    /// Returns the truncated hash stored after the array.
+    pub fn hash(&self) -> &'a [u8] {
+        self.hash
+    }
+
+    /// Returns an iterator over the entries of the array.
+    ///
+    /// Entries that cannot be deserialized are returned as errors.
+    pub fn entries(&self) -> LargeBlobEntries<'a> {
+        // parse has checked that the array has a valid head
+        let (_, count, head_len) = crate::cbor::head(self.array).unwrap();
+        LargeBlobEntries {
+            data: &self.array[head_len..],
+            remaining: count,
+        }
+    }
+
+    /// Checks that the stored hash matches the array.
+    ///
+    /// Returns [`Error::IntegrityFailure`][] if the hash does not match.
+    #[cfg(feature = "sha2")]
+    pub fn verify(&self) -> Result<()> {
+        if array_hash(self.array) == self.hash {
+            Ok(())
+        } else {
+            Err(Error::IntegrityFailure)
+        }
+    }
+}
+
+/// An iterator over the entries of a [`LargeBlobArray`][].
+#[derive(Clone, Debug)]
+pub struct LargeBlobEntries<'a> {
+    data: &'a [u8],
+    remaining: u64,
+}
+
+impl<'a> Iterator for LargeBlobEntries<'a> {
+    type Item = Result<LargeBlobEntry<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        // parse has checked that the array is well-formed
+        let len = crate::cbor::item_len(self.data).unwrap();
+        let (entry, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(cbor_smol::cbor_deserialize(entry).map_err(|_| Error::InvalidCbor))
+    }
+}
+
+/// Serializes a large-blob array with the given entries into `buffer`, including the hash.
+///
+/// Returns [`Error::InvalidLength`][] if the buffer is too small.
+#[cfg(feature = "sha2")]
+pub fn serialize_large_blob_array<'b>(
+    entries: &[LargeBlobEntry<'_>],
+    buffer: &'b mut [u8],
+) -> Result<&'b [u8]> {
+    let len = cbor_smol::cbor_serialize(&entries, buffer)
+        .map_err(|_| Error::InvalidLength)?
+        .len();
+    let hash = array_hash(&buffer[..len]);
+    buffer
+        .get_mut(len..len + LARGE_BLOB_ARRAY_HASH_LENGTH)
+        .ok_or(Error::InvalidLength)?
+        .copy_from_slice(&hash);
+    Ok(&buffer[..len + LARGE_BLOB_ARRAY_HASH_LENGTH])
+}
+
+#[cfg(feature = "sha2")]
+fn array_hash(array: &[u8]) -> [u8; LARGE_BLOB_ARRAY_HASH_LENGTH] {
+    use sha2::{Digest, Sha256};
+
+    let hash = Sha256::digest(array);
+    let mut truncated = [0; LARGE_BLOB_ARRAY_HASH_LENGTH];
+    truncated.copy_from_slice(&hash[..LARGE_BLOB_ARRAY_HASH_LENGTH]);
+    truncated
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, SerializeIndexed, DeserializeIndexed)]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
-pub struct Response {
+pub struct Response<const N: usize = LARGE_BLOB_MAX_FRAGMENT_LENGTH> {
     // 0x01
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub config: Option<Bytes<LARGE_BLOB_MAX_FRAGMENT_LENGTH>>,
+    pub config: Option<Bytes<N>>,
+}
+
+impl<const N: usize> Response<N> {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> crate::Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+/// Produces [`Response`][]s with fragments of a serialized large-blob array that is read from
+/// storage on demand.
+///
+/// `read` is called with an offset and a buffer that it has to fill with the data starting at
+/// that offset.  This makes it possible to serve `get` requests without keeping the serialized
+/// array in RAM.  Used as an iterator, it yields the fragments of the array in order, each with
+/// at most `N` bytes.
+pub struct FragmentReader<F, const N: usize = LARGE_BLOB_MAX_FRAGMENT_LENGTH> {
+    len: usize,
+    offset: usize,
+    read: F,
+}
+
+impl<F, const N: usize> FragmentReader<F, N>
+where
+    F: FnMut(usize, &mut [u8]) -> Result<()>,
+{
+    /// Creates a reader for a serialized large-blob array of length `len`.
+    pub fn new(len: usize, read: F) -> Self {
+        Self {
+            len,
+            offset: 0,
+            read,
+        }
+    }
+
+    /// Returns the response to a `get` request for `length` bytes at `offset`.
+    ///
+    /// Returns [`Error::InvalidParameter`][] if the offset is larger than the length of the array
+    /// and [`Error::InvalidLength`][] if `length` is larger than `N`.  The returned fragment is
+    /// shorter than `length` if the end of the array is reached.
+    pub fn read(&mut self, offset: u32, length: u32) -> Result<Response<N>> {
+        let offset = offset as usize;
+        let length = length as usize;
+        if offset > self.len {
+            return Err(Error::InvalidParameter);
+        }
+        if length > N {
+            return Err(Error::InvalidLength);
+        }
+        let length = length.min(self.len - offset);
+        let mut config = Bytes::new();
+        // length <= N, so this cannot fail
+        config.resize_default(length).ok();
+        (self.read)(offset, &mut config)?;
+        self.offset = offset + length;
+        Ok(Response {
+            config: Some(config),
+        })
+    }
+}
+
+impl<F, const N: usize> Iterator for FragmentReader<F, N>
+where
+    F: FnMut(usize, &mut [u8]) -> Result<()>,
+{
+    type Item = Result<Response<N>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.len {
+            return None;
+        }
+        let offset = self.offset as u32;
+        let response = self.read(offset, N as u32);
+        if response.is_err() {
+            self.offset = self.len;
+        }
+        Some(response)
+    }
+}
+
+/// Same as [`Response`] but borrowing the fragment.
+///
+/// This makes it possible to serve the fragment directly from storage without copying it into a
+/// buffer of [`LARGE_BLOB_MAX_FRAGMENT_LENGTH`][] bytes.
+#[derive(Clone, Debug, Default, Eq, PartialEq, SerializeIndexed)]
+#[non_exhaustive]
+#[serde_indexed(offset = 1)]
+pub struct ResponseRef<'a> {
+    // 0x01
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<&'a serde_bytes::Bytes>,
+}
+
+impl<'a> ResponseRef<'a> {
+    /// Creates a response with the given fragment.
+    pub fn new(config: &'a [u8]) -> Self {
+        Self {
+            config: Some(serde_bytes::Bytes::new(config)),
+        }
+    }
+}
+
+impl ResponseRef<'_> {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> crate::Result<usize> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+impl<'a, const N: usize> From<&'a Response<N>> for ResponseRef<'a> {
+    fn from(response: &'a Response<N>) -> Self {
+        Self {
+            config: response
+                .config
+                .as_ref()
+                .map(|config| serde_bytes::Bytes::new(config)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -102,9 +551,237 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_request() {
+        let mut request = Request {
+            get: Some(255),
+            set: None,
+            offset: 0,
+            length: None,
+            pin_uv_auth_param: None,
+            pin_uv_auth_protocol: None,
+        };
+        assert_eq!(request.validate(1024, 4096), Ok(()));
+        assert_eq!(request.validate(128, 4096), Err(Error::InvalidLength));
+        // PIN/UV auth parameters are ignored for reads
+        request.pin_uv_auth_param = Some(serde_bytes::Bytes::new(PIN_AUTH));
+        request.pin_uv_auth_protocol = Some(2);
+        assert_eq!(request.validate(1024, 4096), Ok(()));
+        request.pin_uv_auth_param = None;
+        request.pin_uv_auth_protocol = None;
+        request.length = Some(255);
+        assert_eq!(request.validate(1024, 4096), Err(Error::InvalidParameter));
+
+        request.get = None;
+        assert_eq!(request.validate(1024, 4096), Err(Error::InvalidParameter));
+        request.set = Some(serde_bytes::Bytes::new(FRAGMENT));
+        assert_eq!(request.validate(1024, 4096), Err(Error::PinRequired));
+        request.pin_uv_auth_param = Some(serde_bytes::Bytes::new(PIN_AUTH));
+        assert_eq!(request.validate(1024, 4096), Err(Error::MissingParameter));
+        request.pin_uv_auth_protocol = Some(2);
+        assert_eq!(request.validate(1024, 4096), Ok(()));
+        assert_eq!(
+            request.validate(1024, 128),
+            Err(Error::LargeBlobStorageFull)
+        );
+        assert_eq!(request.validate(128, 4096), Err(Error::InvalidLength));
+        request.length = Some(16);
+        assert_eq!(request.validate(1024, 4096), Err(Error::InvalidParameter));
+        request.offset = 255;
+        assert_eq!(request.validate(1024, 4096), Err(Error::InvalidParameter));
+        request.length = None;
+        assert_eq!(request.validate(1024, 4096), Ok(()));
+
+        request.get = Some(255);
+        assert_eq!(request.validate(1024, 4096), Err(Error::InvalidParameter));
+    }
+
+    #[test]
+    fn test_write_state() {
+        let mut state = WriteState::default();
+        let mut request = Request {
+            get: None,
+            set: Some(serde_bytes::Bytes::new(FRAGMENT)),
+            offset: 255,
+            length: None,
+            pin_uv_auth_param: Some(serde_bytes::Bytes::new(PIN_AUTH)),
+            pin_uv_auth_protocol: Some(2),
+        };
+        assert_eq!(state.update(&request), Err(Error::InvalidSeq));
+
+        request.offset = 0;
+        request.length = Some(400);
+        assert_eq!(state.update(&request), Ok(false));
+        assert_eq!(state.expected_next_offset(), 255);
+
+        request.length = None;
+        request.offset = 255;
+        assert_eq!(state.update(&request), Err(Error::InvalidParameter));
+        request.set = Some(serde_bytes::Bytes::new(&FRAGMENT[..145]));
+        assert_eq!(state.update(&request), Ok(true));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_pin_auth_message() {
+        let request = Request {
+            get: None,
+            set: Some(serde_bytes::Bytes::new(FRAGMENT)),
+            offset: 0x0102,
+            length: None,
+            pin_uv_auth_param: Some(serde_bytes::Bytes::new(PIN_AUTH)),
+            pin_uv_auth_protocol: Some(2),
+        };
+        let message = request.pin_auth_message().unwrap();
+        assert_eq!(message[..32], [0xff; 32]);
+        assert_eq!(message[32..38], [0x0c, 0x00, 0x02, 0x01, 0x00, 0x00]);
+        assert_eq!(
+            message[38..],
+            hex_literal::hex!("4c8d39b9aef02ce99eba7fdfda190587aa9b71e539c30aec8aac3cdef34581db")
+        );
+    }
+
+    #[test]
+    fn test_large_blob_array() {
+        let array = LargeBlobArray::parse(&EMPTY_LARGE_BLOB_ARRAY).unwrap();
+        assert_eq!(array.array(), &[0x80]);
+        assert_eq!(array.entries().count(), 0);
+
+        let data = hex_literal::hex!(
+            "81a3014401020304024c000102030405060708090a0b0318200102030405060708090a0b0c0d0e0f10"
+        );
+        let array = LargeBlobArray::parse(&data).unwrap();
+        let entries: std::vec::Vec<_> = array.entries().map(|entry| entry.unwrap()).collect();
+        let nonce = ByteArray::new([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+        assert_eq!(entries, [LargeBlobEntry::new(&[1, 2, 3, 4], &nonce, 32)]);
+
+        assert_eq!(
+            LargeBlobArray::parse(&data[..data.len() - 1]),
+            Err(Error::InvalidCbor)
+        );
+        assert_eq!(LargeBlobArray::parse(&[0xa0; 17]), Err(Error::InvalidCbor));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_serialize_large_blob_array() {
+        let mut buffer = [0; 64];
+        let serialized = serialize_large_blob_array(&[], &mut buffer).unwrap();
+        assert_eq!(serialized, EMPTY_LARGE_BLOB_ARRAY);
+        assert_eq!(
+            LargeBlobArray::parse(&EMPTY_LARGE_BLOB_ARRAY)
+                .unwrap()
+                .verify(),
+            Ok(())
+        );
+
+        let nonce = ByteArray::new([0xab; 12]);
+        let entry = LargeBlobEntry::new(&[0xcd; 20], &nonce, 10);
+        let serialized = serialize_large_blob_array(&[entry.clone()], &mut buffer).unwrap();
+        let array = LargeBlobArray::parse(serialized).unwrap();
+        assert_eq!(array.verify(), Ok(()));
+        assert_eq!(array.entries().next(), Some(Ok(entry)));
+
+        let mut corrupted = serialized.to_vec();
+        *corrupted.last_mut().unwrap() ^= 0x01;
+        assert_eq!(
+            LargeBlobArray::parse(&corrupted).unwrap().verify(),
+            Err(Error::IntegrityFailure)
+        );
+        assert_eq!(
+            serialize_large_blob_array(&[], &mut [0; 16]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_fragment_reader() {
+        let data: std::vec::Vec<u8> = (0..=255).collect();
+        let read = |offset: usize, buffer: &mut [u8]| {
+            buffer.copy_from_slice(&data[offset..][..buffer.len()]);
+            Ok(())
+        };
+
+        let fragments: std::vec::Vec<_> = FragmentReader::<_, 100>::new(data.len(), read)
+            .map(|response| response.unwrap().config.unwrap())
+            .collect();
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(fragments[0].as_slice(), &data[..100]);
+        assert_eq!(fragments[2].as_slice(), &data[200..]);
+
+        let mut reader = FragmentReader::<_, 100>::new(data.len(), read);
+        let response = reader.read(250, 100).unwrap();
+        assert_eq!(response.config.unwrap().as_slice(), &data[250..]);
+        let response = reader.read(256, 100).unwrap();
+        assert_eq!(response.config.unwrap().as_slice(), &[]);
+        assert_eq!(reader.read(257, 100), Err(Error::InvalidParameter));
+        assert_eq!(reader.read(0, 101), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_large_blob_key() {
+        let key = LargeBlobKey::new([0xb1; 32]);
+        let mut expected = [0xb1; 34];
+        expected[..2].copy_from_slice(&hex_literal::hex!("5820"));
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&key, &mut buffer).unwrap();
+        assert_eq!(serialized, expected);
+        let deserialized: LargeBlobKey = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, key);
+
+        // only 32 byte strings are accepted
+        let mut short = [0xb1; 17];
+        short[0] = 0x50;
+        assert!(cbor_smol::cbor_deserialize::<LargeBlobKey>(&short).is_err());
+        assert_eq!(
+            LargeBlobKey::try_from(&[0xb1; 16][..]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(LargeBlobKey::try_from(&[0xb1; 32][..]), Ok(key));
+        assert_eq!(<[u8; 32]>::from(key), [0xb1; 32]);
+    }
+
+    #[test]
+    fn test_ser_response_ref() {
+        let response = ResponseRef::new(FRAGMENT);
+        assert_ser_tokens(
+            &response,
+            &[
+                Token::Map { len: Some(1) },
+                // 0x01: config
+                Token::U64(0x01),
+                Token::Some,
+                Token::BorrowedBytes(FRAGMENT),
+                Token::MapEnd,
+            ],
+        );
+        assert_eq!(
+            ResponseRef::from(&Response::<0>::default()),
+            ResponseRef::default()
+        );
+    }
+
+    #[test]
+    fn test_ser_response_fragment_length() {
+        let response = Response::<255> {
+            config: Some(Bytes::from_slice(FRAGMENT).unwrap()),
+        };
+        assert_ser_tokens(
+            &response,
+            &[
+                Token::Map { len: Some(1) },
+                // 0x01: config
+                Token::U64(0x01),
+                Token::Some,
+                Token::BorrowedBytes(FRAGMENT),
+                Token::MapEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_ser_response() {
-        let response = Response {
+        let response: Response = Response {
             config: Some(Bytes::from_slice(&[]).unwrap()),
             ..Default::default()
         };
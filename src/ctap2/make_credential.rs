@@ -1,15 +1,17 @@
-use crate::Vec;
-
-use serde::{Deserialize, Serialize};
-use serde_bytes::ByteArray;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_indexed::{DeserializeIndexed, SerializeIndexed};
 
 use super::{
     AttestationFormatsPreference, AttestationStatement, AttestationStatementFormat,
-    AuthenticatorOptions, Error,
+    AuthenticatorOptions, CertificateChain, Error, NoneAttestationStatement,
+    PackedAttestationStatement,
 };
 use crate::ctap2::credential_management::CredentialProtectionPolicy;
-use crate::webauthn::*;
+use crate::ctap2::get_assertion::{EncryptedOutputs, HmacSecretInput};
+use crate::ctap2::large_blobs::LargeBlobKey;
+use crate::sizes::MAX_SIGNATURE_LENGTH;
+use crate::webauthn::{count_descriptors, DescriptorIter, *};
+use crate::Bytes;
 
 impl TryFrom<u8> for CredentialProtectionPolicy {
     type Error = super::Error;
@@ -25,12 +27,18 @@ impl TryFrom<u8> for CredentialProtectionPolicy {
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
-#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
-pub struct Extensions {
+pub struct Extensions<'a> {
+    /// Opaque data that should be stored with the credential.
+    // See https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#sctn-credBlob-extension
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(borrow)]
+    pub cred_blob: Option<&'a serde_bytes::Bytes>,
+
     #[serde(rename = "credProtect")]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub cred_protect: Option<u8>,
+    pub cred_protect: Option<CredentialProtectionPolicy>,
 
     #[serde(rename = "hmac-secret")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -41,24 +49,173 @@ pub struct Extensions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub large_blob_key: Option<bool>,
 
+    /// The salts for the hmac-secret outputs that should be returned with the new credential.
+    // See https://fidoalliance.org/specs/fido-v2.2-rd-20230321/fido-client-to-authenticator-protocol-v2.2-rd-20230321.html#sctn-hmac-secret-make-cred-extension
+    #[serde(rename = "hmac-secret-mc")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(borrow)]
+    pub hmac_secret_mc: Option<HmacSecretInput<'a>>,
+
+    #[cfg(feature = "third-party-payment")]
+    #[serde(rename = "thirdPartyPayment")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub third_party_payment: Option<bool>,
+}
+
+impl<'a> Extensions<'a> {
+    /// Returns the credBlob if it is present and not longer than `max_cred_blob_length`.
+    ///
+    /// If the credBlob is too long, the authenticator must not store it and must return `false`
+    /// for the credBlob extension output.
+    pub fn storable_cred_blob(&self, max_cred_blob_length: usize) -> Option<&'a [u8]> {
+        self.cred_blob
+            .filter(|cred_blob| cred_blob.len() <= max_cred_blob_length)
+            .map(|cred_blob| &**cred_blob)
+    }
+}
+
+/// The extension outputs in the authenticator data of a makeCredential response.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ExtensionsOutput {
+    /// Whether the credBlob has been stored.
+    #[serde(rename = "credBlob")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_blob: Option<bool>,
+
+    #[serde(rename = "credProtect")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cred_protect: Option<CredentialProtectionPolicy>,
+
+    #[serde(rename = "hmac-secret")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac_secret: Option<bool>,
+
+    #[serde(rename = "hmac-secret-mc")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac_secret_mc: Option<EncryptedOutputs>,
+
     #[cfg(feature = "third-party-payment")]
     #[serde(rename = "thirdPartyPayment")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub third_party_payment: Option<bool>,
 }
 
+impl ExtensionsOutput {
+    #[inline]
+    pub fn is_set(&self) -> bool {
+        let Self {
+            cred_blob,
+            cred_protect,
+            hmac_secret,
+            hmac_secret_mc,
+            #[cfg(feature = "third-party-payment")]
+            third_party_payment,
+        } = self;
+        if cred_blob.is_some()
+            || cred_protect.is_some()
+            || hmac_secret.is_some()
+            || hmac_secret_mc.is_some()
+        {
+            return true;
+        }
+        #[cfg(feature = "third-party-payment")]
+        if third_party_payment.is_some() {
+            return true;
+        }
+        false
+    }
+}
+
+/// The exclude list of a makeCredential request.
+///
+/// To support exclude lists of arbitrary length, the entries are validated during deserialization
+/// but not stored.  Instead, the exclude list borrows the raw CBOR array from the request so that
+/// the entries can be deserialized on demand with [`ExcludeList::iter`][].
+///
+/// The raw array is captured when the request is parsed as a [`ctap2::Request`][super::Request].
+/// If the exclude list is deserialized on its own, only the number of entries is known and
+/// [`ExcludeList::iter`][] returns an error.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ExcludeList<'a> {
+    data: &'a [u8],
+    len: usize,
+}
+
+impl<'a> ExcludeList<'a> {
+    /// Returns the number of entries in the exclude list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the entries of the exclude list.
+    ///
+    /// If the raw array has not been captured because the exclude list was not parsed as part of
+    /// a [`ctap2::Request`][super::Request], [`Error::Other`][] is returned.  If the raw array is
+    /// malformed, [`Error::InvalidCbor`][] is returned.
+    pub fn iter(&self) -> Result<ExcludeListIter<'a>, Error> {
+        DescriptorIter::from_array(self.data, self.len).map(ExcludeListIter)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ExcludeList<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        count_descriptors(deserializer).map(|len| Self { data: &[], len })
+    }
+}
+
+/// A lazy iterator over the exclude list of a makeCredential request.
+///
+/// The entries are deserialized on demand from the raw request so that the memory usage does not
+/// depend on the length of the exclude list.
+#[derive(Clone, Debug)]
+pub struct ExcludeListIter<'a>(DescriptorIter<'a>);
+
+impl<'a> ExcludeListIter<'a> {
+    /// Creates an iterator over the exclude list of the given CBOR-encoded makeCredential
+    /// parameters, i.e. the request without the command byte.
+    ///
+    /// If the request does not contain an exclude list, the iterator is empty.  If the request is
+    /// malformed, [`Error::InvalidCbor`][] is returned.
+    pub fn new(parameters: &'a [u8]) -> Result<Self, Error> {
+        DescriptorIter::new(parameters, 0x05).map(Self)
+    }
+}
+
+impl<'a> Iterator for ExcludeListIter<'a> {
+    type Item = Result<PublicKeyCredentialDescriptorRef<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ExcludeListIter<'_> {}
+
 #[derive(Clone, Debug, Eq, PartialEq, DeserializeIndexed)]
 #[non_exhaustive]
 #[serde_indexed(offset = 1)]
 pub struct Request<'a> {
     pub client_data_hash: &'a serde_bytes::Bytes,
-    pub rp: PublicKeyCredentialRpEntity,
-    pub user: PublicKeyCredentialUserEntity,
+    pub rp: PublicKeyCredentialRpEntityRef<'a>,
+    pub user: PublicKeyCredentialUserEntityRef<'a>,
     pub pub_key_cred_params: FilteredPublicKeyCredentialParameters,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub exclude_list: Option<Vec<PublicKeyCredentialDescriptorRef<'a>, 16>>,
+    pub exclude_list: Option<ExcludeList<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub extensions: Option<Extensions>,
+    pub extensions: Option<Extensions<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub options: Option<AuthenticatorOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -71,10 +228,56 @@ pub struct Request<'a> {
     pub attestation_formats_preference: Option<AttestationFormatsPreference>,
 }
 
+impl<'a> Request<'a> {
+    /// Stores the raw exclude list from the given CBOR-encoded parameters, i.e. the request
+    /// without the command byte, in [`Request::exclude_list`][].
+    pub(crate) fn capture_exclude_list(&mut self, parameters: &'a [u8]) {
+        if let Some(exclude_list) = &mut self.exclude_list {
+            if let Some(Some(data)) = crate::cbor::map_value(parameters, 0x05) {
+                exclude_list.data = data;
+            }
+        }
+    }
+
+    /// Performs the parameter checks of the specification that do not depend on the state or the
+    /// capabilities of the authenticator.
+    ///
+    /// - If `pin_auth` is present but empty, the platform asks the user to select this
+    ///   authenticator.  The authenticator has to collect user presence and then return
+    ///   [`Error::PinNotSet`][] or [`Error::PinInvalid`][], so no further checks are performed.
+    /// - If `pin_auth` is present, `pin_protocol` must be present too.  Whether the protocol is
+    ///   supported has to be checked by the authenticator.
+    /// - The `up` option must not be `false`.
+    ///
+    /// If `pin_auth` is present, the `uv` option should be ignored by the authenticator.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(pin_auth) = self.pin_auth {
+            if pin_auth.is_empty() {
+                return Ok(());
+            }
+            if self.pin_protocol.is_none() {
+                return Err(Error::MissingParameter);
+            }
+        }
+        if let Some(options) = &self.options {
+            if options.up == Some(false) {
+                return Err(Error::InvalidOption);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the output of the `credProps` client extension for a credential created for this
+    /// request.
+    pub fn cred_props(&self) -> CredentialPropertiesOutput {
+        CredentialPropertiesOutput::from_options(self.options.as_ref())
+    }
+}
+
 pub type AttestationObject = Response;
 
 pub type AuthenticatorData<'a> =
-    super::AuthenticatorData<'a, AttestedCredentialData<'a>, Extensions>;
+    super::AuthenticatorData<'a, AttestedCredentialData<'a>, ExtensionsOutput>;
 
 // NOTE: This is not CBOR, it has a custom encoding...
 // https://www.w3.org/TR/webauthn/#sec-attested-credential-data
@@ -82,13 +285,51 @@ pub type AuthenticatorData<'a> =
 pub struct AttestedCredentialData<'a> {
     pub aaguid: &'a [u8],
     // this is where "unlimited non-resident keys" get stored
-    // TODO: Model as actual credential ID, with ser/de to bytes (format is up to authenticator)
-    pub credential_id: &'a [u8],
+    pub credential_id: CredentialIdRef<'a>,
     pub credential_public_key: &'a [u8],
 }
 
+impl<'a> AttestedCredentialData<'a> {
+    /// Parses the attested credential data at the start of `data` and returns the remaining
+    /// data.
+    ///
+    /// The length of the credential public key is determined from its CBOR encoding.
+    pub fn parse(data: &'a [u8]) -> Result<(Self, &'a [u8]), Error> {
+        // 16 bytes AAGUID, 2 bytes credential ID length
+        if data.len() < 18 {
+            return Err(Error::InvalidLength);
+        }
+        let (aaguid, data) = data.split_at(16);
+        let (credential_id_len, data) = data.split_at(2);
+        let credential_id_len = usize::from(u16::from_be_bytes([
+            credential_id_len[0],
+            credential_id_len[1],
+        ]));
+        if data.len() < credential_id_len {
+            return Err(Error::InvalidLength);
+        }
+        let (credential_id, data) = data.split_at(credential_id_len);
+        let credential_public_key_len = crate::cbor::item_len(data).ok_or(Error::InvalidCbor)?;
+        let (credential_public_key, data) = data.split_at(credential_public_key_len);
+        Ok((
+            Self {
+                aaguid,
+                credential_id: CredentialIdRef::new(credential_id),
+                credential_public_key,
+            },
+            data,
+        ))
+    }
+}
+
+impl<'a> super::DeserializeAttestedCredentialData<'a> for AttestedCredentialData<'a> {
+    fn deserialize(data: &'a [u8]) -> Result<(Self, &'a [u8]), Error> {
+        Self::parse(data)
+    }
+}
+
 impl<'a> super::SerializeAttestedCredentialData for AttestedCredentialData<'a> {
-    fn serialize(&self, buffer: &mut super::SerializedAuthenticatorData) -> Result<(), Error> {
+    fn serialize<const N: usize>(&self, buffer: &mut crate::Bytes<N>) -> Result<(), Error> {
         // TODO: validate lengths of credential ID and credential public key
         // 16 bytes, the aaguid
         buffer
@@ -102,7 +343,7 @@ impl<'a> super::SerializeAttestedCredentialData for AttestedCredentialData<'a> {
             .map_err(|_| Error::Other)?;
         // raw bytes of credential ID
         buffer
-            .extend_from_slice(self.credential_id)
+            .extend_from_slice(self.credential_id.as_bytes())
             .map_err(|_| Error::Other)?;
         buffer
             .extend_from_slice(self.credential_public_key)
@@ -122,11 +363,65 @@ pub struct Response {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ep_att: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub large_blob_key: Option<ByteArray<32>>,
+    pub large_blob_key: Option<LargeBlobKey>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
 }
 
+impl Response {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> Result<usize, Error> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+/// Deserializes a response returned by an authenticator.
+///
+/// The variant of the attestation statement is determined from the `fmt` field.
+#[cfg(feature = "platform")]
+impl<'de> Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use super::attestation::RawAttestationStatement;
+        use serde::de::Error as _;
+
+        #[derive(DeserializeIndexed)]
+        #[serde_indexed(offset = 1)]
+        struct RawResponse<'a> {
+            fmt: AttestationStatementFormat,
+            auth_data: super::SerializedAuthenticatorData,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            att_stmt: Option<RawAttestationStatement<'a>>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            ep_att: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            large_blob_key: Option<LargeBlobKey>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
+        }
+
+        let response = RawResponse::deserialize(deserializer)?;
+        let att_stmt = response
+            .att_stmt
+            .map(|att_stmt| {
+                att_stmt
+                    .into_statement(response.fmt)
+                    .ok_or_else(|| D::Error::custom("invalid attestation statement"))
+            })
+            .transpose()?;
+        Ok(Self {
+            fmt: response.fmt,
+            auth_data: response.auth_data,
+            att_stmt,
+            ep_att: response.ep_att,
+            large_blob_key: response.large_blob_key,
+            unsigned_extension_outputs: response.unsigned_extension_outputs,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct ResponseBuilder {
     pub fmt: AttestationStatementFormat,
@@ -145,16 +440,152 @@ impl ResponseBuilder {
             unsigned_extension_outputs: None,
         }
     }
+
+    /// Builds a response with the given attestation statement.
+    ///
+    /// `fmt` is set to the format of the attestation statement.
+    pub fn with_attestation(self, att_stmt: AttestationStatement) -> Response {
+        let mut response = Self {
+            fmt: att_stmt.format(),
+            ..self
+        }
+        .build();
+        response.att_stmt = Some(att_stmt);
+        response
+    }
+
+    /// Builds a response with a `packed` attestation statement.
+    ///
+    /// `sig` is the DER-encoded signature and `x5c` the attestation certificate chain.  For self
+    /// attestation, `x5c` must be `None`.
+    pub fn with_packed_attestation(
+        self,
+        alg: i32,
+        sig: Bytes<MAX_SIGNATURE_LENGTH>,
+        x5c: Option<CertificateChain>,
+    ) -> Response {
+        self.with_attestation(AttestationStatement::Packed(PackedAttestationStatement {
+            alg,
+            sig,
+            x5c,
+        }))
+    }
 }
 
+/// The attestation object of a [`Response`][] in the WebAuthn encoding.
+///
+/// In contrast to the CTAP response, the WebAuthn attestation object uses text keys and always
+/// contains an attestation statement.  See the [WebAuthn specification][] for the encoding.
+///
+/// [WebAuthn specification]: https://www.w3.org/TR/webauthn-3/#sctn-attestation
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct WebAuthnAttestationObject<'a> {
+    // the fields are ordered according to the CTAP2 canonical CBOR encoding
+    pub fmt: AttestationStatementFormat,
+    #[serde(rename = "attStmt")]
+    pub att_stmt: &'a AttestationStatement,
+    #[serde(rename = "authData")]
+    pub auth_data: &'a serde_bytes::Bytes,
+}
+
+static NONE_ATTESTATION_STATEMENT: AttestationStatement =
+    AttestationStatement::None(NoneAttestationStatement {});
+
+impl<'a> From<&'a Response> for WebAuthnAttestationObject<'a> {
+    /// Converts the response into a WebAuthn attestation object.
+    ///
+    /// If the response does not contain an attestation statement, an empty statement is used.
+    fn from(response: &'a Response) -> Self {
+        Self {
+            fmt: response.fmt,
+            att_stmt: response
+                .att_stmt
+                .as_ref()
+                .unwrap_or(&NONE_ATTESTATION_STATEMENT),
+            auth_data: serde_bytes::Bytes::new(&response.auth_data),
+        }
+    }
+}
+
+impl Response {
+    /// Returns the attestation object of this response in the WebAuthn encoding.
+    pub fn webauthn_attestation_object(&self) -> WebAuthnAttestationObject<'_> {
+        self.into()
+    }
+
+    /// Marks the attestation statement as an enterprise attestation by setting `ep_att`.
+    pub fn with_enterprise_attestation(mut self) -> Self {
+        self.ep_att = Some(true);
+        self
+    }
+}
+
+/// Same as [`Response`] but borrowing the authenticator data and the attestation statement.
+#[derive(Clone, Debug, Eq, PartialEq, SerializeIndexed)]
+#[non_exhaustive]
+#[serde_indexed(offset = 1)]
+pub struct ResponseRef<'a> {
+    pub fmt: AttestationStatementFormat,
+    pub auth_data: &'a serde_bytes::Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub att_stmt: Option<&'a AttestationStatement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ep_att: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_blob_key: Option<LargeBlobKey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unsigned_extension_outputs: Option<UnsignedExtensionOutputs>,
+}
+
+impl ResponseRef<'_> {
+    /// Returns the length of the CBOR serialization of this response.
+    pub fn serialized_len(&self) -> Result<usize, Error> {
+        crate::cbor::serialized_len(self)
+    }
+}
+
+impl<'a> From<&'a Response> for ResponseRef<'a> {
+    fn from(response: &'a Response) -> Self {
+        Self {
+            fmt: response.fmt,
+            auth_data: serde_bytes::Bytes::new(&response.auth_data),
+            att_stmt: response.att_stmt.as_ref(),
+            ep_att: response.ep_att,
+            large_blob_key: response.large_blob_key,
+            unsigned_extension_outputs: response.unsigned_extension_outputs.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseRefBuilder<'a> {
+    pub fmt: AttestationStatementFormat,
+    pub auth_data: &'a [u8],
+}
+
+impl<'a> ResponseRefBuilder<'a> {
+    #[inline(always)]
+    pub fn build(self) -> ResponseRef<'a> {
+        ResponseRef {
+            fmt: self.fmt,
+            auth_data: serde_bytes::Bytes::new(self.auth_data),
+            att_stmt: None,
+            ep_att: None,
+            large_blob_key: None,
+            unsigned_extension_outputs: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct UnsignedExtensionOutputs {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_test::{assert_ser_tokens, Token};
+    use crate::ctap2::{AuthenticatorDataFlags, NoneAttestationStatement};
+    use crate::Bytes;
 
     #[test]
     fn rp_entity_icon() {
@@ -168,13 +599,373 @@ mod tests {
     }
 
     #[test]
-    fn test_serde_attestation_statement_format() {
-        let formats = [
-            (AttestationStatementFormat::None, "none"),
-            (AttestationStatementFormat::Packed, "packed"),
-        ];
-        for (format, s) in formats {
-            assert_ser_tokens(&format, &[Token::BorrowedStr(s)]);
+    fn test_validate_request() {
+        let cbor = b"\xa4\x01X \xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\x02\xa1bidkexample.com\x03\xa2bidX \x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1ddnamedAdam\x04\x81\xa2calg&dtypejpublic-key";
+        let mut request: Request = cbor_smol::cbor_deserialize(cbor.as_slice()).unwrap();
+        assert_eq!(request.validate(), Ok(()));
+
+        request.options = Some(AuthenticatorOptions {
+            rk: Some(true),
+            up: Some(false),
+            uv: None,
+        });
+        assert_eq!(request.validate(), Err(Error::InvalidOption));
+
+        request.options = None;
+        request.pin_auth = Some(serde_bytes::Bytes::new(&[0x50; 16]));
+        assert_eq!(request.validate(), Err(Error::MissingParameter));
+        request.pin_protocol = Some(2);
+        assert_eq!(request.validate(), Ok(()));
+
+        request.pin_auth = Some(serde_bytes::Bytes::new(&[]));
+        request.pin_protocol = None;
+        assert_eq!(request.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_cred_props() {
+        let cbor = b"\xa5\x01X \xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\x02\xa1bidkexample.com\x03\xa2bidX \x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1ddnamedAdam\x04\x81\xa2calg&dtypejpublic-key\x07\xa1brk\xf5";
+        let mut request: Request = cbor_smol::cbor_deserialize(cbor.as_slice()).unwrap();
+        assert_eq!(request.cred_props(), CredentialPropertiesOutput::new(true));
+
+        request.options = None;
+        assert_eq!(request.cred_props(), CredentialPropertiesOutput::new(false));
+    }
+
+    #[test]
+    fn test_exclude_list() {
+        let cbor = b"\xa4\x01X \xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\xcd\x02\xa1bidkexample.com\x03\xa2bidX \x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1d\x1ddnamedAdam\x04\x81\xa2calg&dtypejpublic-key";
+        let request: Request = cbor_smol::cbor_deserialize(cbor.as_slice()).unwrap();
+        assert_eq!(request.exclude_list, None);
+        assert_eq!(ExcludeListIter::new(cbor).unwrap().count(), 0);
+
+        // more entries than the previous limit of 16
+        let mut data = std::vec::Vec::from(cbor.as_slice());
+        data[0] = 0xa5;
+        data.extend_from_slice(b"\x05\x94");
+        for i in 0..20 {
+            data.extend_from_slice(b"\xa2bidP");
+            data.extend_from_slice(&[i; 16]);
+            data.extend_from_slice(b"dtypejpublic-key");
+        }
+        let request: Request = cbor_smol::cbor_deserialize(&data).unwrap();
+        let exclude_list = request.exclude_list.unwrap();
+        assert_eq!(exclude_list.len(), 20);
+        assert!(!exclude_list.is_empty());
+        // the raw exclude list is only captured by ctap2::Request
+        assert_eq!(exclude_list.iter().unwrap_err(), Error::Other);
+
+        let iter = ExcludeListIter::new(&data).unwrap();
+        assert_eq!(iter.len(), 20);
+        for (i, descriptor) in iter.enumerate() {
+            let descriptor = descriptor.unwrap();
+            assert_eq!(descriptor.id.as_ref(), &[i as u8; 16]);
+            assert_eq!(descriptor.key_type, "public-key");
+        }
+
+        let mut command = std::vec![0x01];
+        command.extend_from_slice(&data);
+        let Ok(crate::ctap2::Request::MakeCredential(request)) =
+            crate::ctap2::Request::deserialize(&command)
+        else {
+            panic!("failed to parse makeCredential request");
+        };
+        let exclude_list = request.exclude_list.unwrap();
+        assert_eq!(exclude_list.len(), 20);
+        assert_eq!(exclude_list.iter().unwrap().len(), 20);
+        for (i, descriptor) in exclude_list.iter().unwrap().enumerate() {
+            assert_eq!(descriptor.unwrap().id.as_ref(), &[i as u8; 16]);
+        }
+
+        // an entry that is not a descriptor
+        let mut data = std::vec::Vec::from(cbor.as_slice());
+        data[0] = 0xa5;
+        data.extend_from_slice(b"\x05\x81\x01");
+        assert!(cbor_smol::cbor_deserialize::<Request>(&data).is_err());
+        let mut iter = ExcludeListIter::new(&data).unwrap();
+        assert_eq!(iter.next(), Some(Err(Error::InvalidCbor)));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(
+            ExcludeListIter::new(&data[..data.len() - 1]).unwrap_err(),
+            Error::InvalidCbor
+        );
+    }
+
+    #[test]
+    fn test_ser_response_ref() {
+        let auth_data = [0xad; 37];
+        let mut response = ResponseBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
+        }
+        .build();
+        response.att_stmt = Some(AttestationStatement::None(NoneAttestationStatement {}));
+        response.ep_att = Some(false);
+
+        let mut response_ref = ResponseRefBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: &auth_data,
+        }
+        .build();
+        response_ref.att_stmt = response.att_stmt.as_ref();
+        response_ref.ep_att = Some(false);
+        assert_eq!(ResponseRef::from(&response), response_ref);
+
+        let mut owned = [0; 128];
+        let mut borrowed = [0; 128];
+        assert_eq!(
+            cbor_smol::cbor_serialize(&response, &mut owned).unwrap(),
+            cbor_smol::cbor_serialize(&response_ref, &mut borrowed).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "platform")]
+    #[test]
+    fn test_deserialize_response() {
+        use crate::ctap2::PackedAttestationStatement;
+
+        let mut response = ResponseBuilder {
+            fmt: AttestationStatementFormat::Packed,
+            auth_data: Bytes::from_slice(&[0xad; 37]).unwrap(),
+        }
+        .build();
+        response.att_stmt = Some(AttestationStatement::Packed(PackedAttestationStatement {
+            alg: -7,
+            sig: Bytes::from_slice(&[0x5e; 70]).unwrap(),
+            x5c: None,
+        }));
+        response.large_blob_key = Some(LargeBlobKey::new([0xb1; 32]));
+        let mut buffer = [0; 256];
+        let serialized = cbor_smol::cbor_serialize(&response, &mut buffer).unwrap();
+        let deserialized: Response = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, response);
+
+        // the attestation statement is interpreted according to the format
+        let mut none = response.clone();
+        none.fmt = AttestationStatementFormat::None;
+        let serialized = cbor_smol::cbor_serialize(&none, &mut buffer).unwrap();
+        let deserialized: Response = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(
+            deserialized.att_stmt,
+            Some(AttestationStatement::None(NoneAttestationStatement {}))
+        );
+
+        let mut packed = response;
+        packed.att_stmt = Some(AttestationStatement::None(NoneAttestationStatement {}));
+        let serialized = cbor_smol::cbor_serialize(&packed, &mut buffer).unwrap();
+        assert!(cbor_smol::cbor_deserialize::<Response>(serialized).is_err());
+    }
+
+    #[test]
+    fn test_response_builder_attestation() {
+        let builder = || ResponseBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: Bytes::from_slice(&[0xad; 37]).unwrap(),
+        };
+        let sig = Bytes::from_slice(&[0x5e; 70]).unwrap();
+
+        let response = builder().with_packed_attestation(-7, sig.clone(), None);
+        assert_eq!(response.fmt, AttestationStatementFormat::Packed);
+        assert_eq!(
+            response.att_stmt,
+            Some(AttestationStatement::Packed(PackedAttestationStatement {
+                alg: -7,
+                sig,
+                x5c: None,
+            }))
+        );
+        assert_eq!(response.ep_att, None);
+        assert_eq!(response.with_enterprise_attestation().ep_att, Some(true));
+
+        let mut builder = builder();
+        builder.fmt = AttestationStatementFormat::Packed;
+        let response =
+            builder.with_attestation(AttestationStatement::None(NoneAttestationStatement {}));
+        assert_eq!(response.fmt, AttestationStatementFormat::None);
+    }
+
+    #[test]
+    fn test_webauthn_attestation_object() {
+        let auth_data = [0xad; 37];
+        let response = ResponseBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
         }
+        .build();
+        let mut buffer = [0; 128];
+        let serialized =
+            cbor_smol::cbor_serialize(&response.webauthn_attestation_object(), &mut buffer)
+                .unwrap();
+        let mut expected = std::vec::Vec::from(
+            b"\xa3\x63fmt\x64none\x67attStmt\xa0\x68authData\x58\x25".as_slice(),
+        );
+        expected.extend_from_slice(&auth_data);
+        assert_eq!(serialized, expected.as_slice());
+        assert!(crate::cbor::is_canonical(serialized));
+
+        let response = ResponseBuilder {
+            fmt: AttestationStatementFormat::None,
+            auth_data: Bytes::from_slice(&auth_data).unwrap(),
+        }
+        .with_packed_attestation(-7, Bytes::from_slice(&[0x5e; 70]).unwrap(), None);
+        let object = WebAuthnAttestationObject::from(&response);
+        assert_eq!(object.fmt, AttestationStatementFormat::Packed);
+        assert_eq!(Some(object.att_stmt), response.att_stmt.as_ref());
+        let serialized = cbor_smol::cbor_serialize(&object, &mut buffer).unwrap();
+        assert!(serialized.starts_with(b"\xa3\x63fmt\x66packed\x67attStmt\xa2\x63alg\x26"));
+    }
+
+    #[test]
+    fn test_serialize_authenticator_data_into() {
+        let rp_id_hash = RpIdHash::new([0xaa; 32]);
+        let authenticator_data = AuthenticatorData {
+            rp_id_hash: &rp_id_hash,
+            flags: AuthenticatorDataFlags::USER_PRESENCE
+                | AuthenticatorDataFlags::ATTESTED_CREDENTIAL_DATA,
+            sign_count: 42,
+            attested_credential_data: Some(AttestedCredentialData {
+                aaguid: &[0xbb; 16],
+                credential_id: CredentialIdRef::new(&[0xcc; 16]),
+                credential_public_key: &[0xa0],
+            }),
+            extensions: None,
+        };
+        let serialized = authenticator_data.serialize().unwrap();
+
+        let mut buffer = Bytes::<128>::from_slice(b"prefix").unwrap();
+        let n = authenticator_data.serialize_into(&mut buffer).unwrap();
+        assert_eq!(n, serialized.len());
+        assert_eq!(&buffer[..6], b"prefix");
+        assert_eq!(&buffer[6..], serialized.as_slice());
+
+        let mut buffer = Bytes::<64>::from_slice(b"prefix").unwrap();
+        assert_eq!(
+            authenticator_data.serialize_into(&mut buffer),
+            Err(Error::Other)
+        );
+        assert_eq!(buffer.as_slice(), b"prefix");
+    }
+
+    #[test]
+    fn test_cred_protect() {
+        let extensions: Extensions<'_> =
+            cbor_smol::cbor_deserialize(b"\xa1\x6bcredProtect\x02").unwrap();
+        assert_eq!(
+            extensions.cred_protect,
+            Some(CredentialProtectionPolicy::OptionalWithCredentialIdList)
+        );
+        for value in [0x00, 0x04] {
+            let mut data = *b"\xa1\x6bcredProtect\x00";
+            data[13] = value;
+            assert!(cbor_smol::cbor_deserialize::<Extensions<'_>>(&data).is_err());
+        }
+
+        let mut output = ExtensionsOutput::default();
+        output.cred_protect = Some(CredentialProtectionPolicy::Required);
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&output, &mut buffer).unwrap();
+        assert_eq!(serialized, b"\xa1\x6bcredProtect\x03");
+    }
+
+    #[test]
+    fn test_cred_blob() {
+        let cred_blob = [0xcb; 33];
+        let mut extensions = Extensions::default();
+        assert_eq!(extensions.storable_cred_blob(32), None);
+        extensions.cred_blob = Some(serde_bytes::Bytes::new(&cred_blob[..32]));
+        assert_eq!(extensions.storable_cred_blob(32), Some(&cred_blob[..32]));
+        extensions.cred_blob = Some(serde_bytes::Bytes::new(&cred_blob));
+        assert_eq!(extensions.storable_cred_blob(32), None);
+        assert_eq!(
+            extensions.storable_cred_blob(64),
+            Some(cred_blob.as_slice())
+        );
+
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+        let deserialized: Extensions<'_> = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, extensions);
+
+        let mut output = ExtensionsOutput::default();
+        output.cred_blob = Some(false);
+        assert!(output.is_set());
+        let serialized = cbor_smol::cbor_serialize(&output, &mut buffer).unwrap();
+        assert_eq!(serialized, b"\xa1\x68credBlob\xf4");
+    }
+
+    #[test]
+    fn test_hmac_secret_mc() {
+        use crate::cose::EcdhEsHkdf256PublicKeyRef;
+        use crate::ctap2::get_assertion::EncryptedSalts;
+
+        let x = [0x01; 32];
+        let y = [0x02; 32];
+        let mut extensions = Extensions::default();
+        extensions.hmac_secret = Some(true);
+        extensions.hmac_secret_mc = Some(HmacSecretInput {
+            key_agreement: EcdhEsHkdf256PublicKeyRef {
+                x: serde_bytes::Bytes::new(&x),
+                y: serde_bytes::Bytes::new(&y),
+            },
+            salt_enc: EncryptedSalts::from_slice(&[0x5a; 48]).unwrap(),
+            salt_auth: Bytes::from_slice(&[0xa5; 32]).unwrap(),
+            pin_protocol: Some(2),
+        });
+        let mut buffer = [0; 256];
+        let serialized = cbor_smol::cbor_serialize(&extensions, &mut buffer).unwrap();
+        let deserialized: Extensions<'_> = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, extensions);
+
+        let mut output = ExtensionsOutput::default();
+        assert!(!output.is_set());
+        output.hmac_secret_mc = Some(EncryptedOutputs::from_slice(&[0x0e; 48]).unwrap());
+        assert!(output.is_set());
+        let mut buffer = [0; 128];
+        let serialized = cbor_smol::cbor_serialize(&output, &mut buffer).unwrap();
+        assert_eq!(&serialized[..16], b"\xa1\x6ehmac-secret-mc");
+        let deserialized: ExtensionsOutput = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, output);
+    }
+
+    #[test]
+    fn test_parse_attested_credential_data() {
+        let mut data = Bytes::<128>::new();
+        data.extend_from_slice(&[0xbb; 16]).unwrap();
+        data.extend_from_slice(&[0x00, 0x04]).unwrap();
+        data.extend_from_slice(&[0xcc; 4]).unwrap();
+        // {1: 2, 3: -7}
+        data.extend_from_slice(&[0xa2, 0x01, 0x02, 0x03, 0x26])
+            .unwrap();
+        data.extend_from_slice(b"rest").unwrap();
+
+        let (attested_credential_data, rest) = AttestedCredentialData::parse(&data).unwrap();
+        assert_eq!(attested_credential_data.aaguid, &[0xbb; 16]);
+        assert_eq!(
+            attested_credential_data.credential_id.as_bytes(),
+            &[0xcc; 4]
+        );
+        assert_eq!(
+            attested_credential_data.credential_public_key,
+            &[0xa2, 0x01, 0x02, 0x03, 0x26]
+        );
+        assert_eq!(rest, b"rest");
+
+        let mut serialized = Bytes::<128>::new();
+        crate::ctap2::SerializeAttestedCredentialData::serialize(
+            &attested_credential_data,
+            &mut serialized,
+        )
+        .unwrap();
+        assert_eq!(serialized.as_slice(), &data[..data.len() - 4]);
+
+        assert_eq!(
+            AttestedCredentialData::parse(&data[..20]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            AttestedCredentialData::parse(&data[..24]),
+            Err(Error::InvalidCbor)
+        );
     }
 }
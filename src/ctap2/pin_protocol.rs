@@ -0,0 +1,402 @@
+//! Implementations of the PIN/UV auth protocols.
+//!
+//! See the [CTAP specification](https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#pinProto)
+//! for the definition of the protocols.  This module is only available if the `pin-protocol`
+//! feature is enabled.
+
+use aes::Aes256;
+use cbc::cipher::{generic_array::GenericArray, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use p256::{ecdh, elliptic_curve::sec1::ToEncodedPoint, PublicKey, SecretKey};
+use rand_core::CryptoRngCore;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use super::{client_pin::PinProtocolVersion, Error, Result};
+use crate::{
+    cose::{EcdhEsHkdf256PublicKey, EcdhEsHkdf256PublicKeyRef},
+    Bytes,
+};
+
+/// The operations of a PIN/UV auth protocol.
+pub trait PinUvAuthProtocol {
+    const VERSION: PinProtocolVersion;
+
+    /// The shared secret derived from the ECDH key agreement.
+    type SharedSecret: AsRef<[u8]>;
+
+    /// Derives the shared secret from the x coordinate of the ECDH result.
+    fn kdf(z: &[u8; 32]) -> Self::SharedSecret;
+
+    /// Encrypts `plaintext` with the shared secret.
+    ///
+    /// The length of `plaintext` must be a multiple of the AES block size.
+    fn encrypt<R: CryptoRngCore, const N: usize>(
+        rng: &mut R,
+        shared_secret: &Self::SharedSecret,
+        plaintext: &[u8],
+    ) -> Result<Bytes<N>>;
+
+    /// Decrypts `ciphertext` with the shared secret.
+    fn decrypt<const N: usize>(
+        shared_secret: &Self::SharedSecret,
+        ciphertext: &[u8],
+    ) -> Result<Bytes<N>>;
+
+    /// Computes the authentication tag of `message` with `key`.
+    ///
+    /// `key` is either the shared secret or the PIN/UV auth token.
+    fn authenticate(key: &[u8], message: &[u8]) -> Bytes<32>;
+
+    /// Verifies the authentication tag of `message` with `key`.
+    ///
+    /// Returns [`Error::PinAuthInvalid`][] if the verification fails.
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> Result<()>;
+
+    /// Computes the shared secret for the platform key agreement key `peer_public_key` using the
+    /// authenticator key agreement key `secret_key`.
+    fn decapsulate(
+        secret_key: &SecretKey,
+        peer_public_key: &EcdhEsHkdf256PublicKeyRef<'_>,
+    ) -> Result<Self::SharedSecret> {
+        let peer_public_key = to_public_key(peer_public_key)?;
+        let z = ecdh::diffie_hellman(secret_key.to_nonzero_scalar(), peer_public_key.as_affine());
+        Ok(Self::kdf(&(*z.raw_secret_bytes()).into()))
+    }
+
+    /// Generates an ephemeral key agreement key and computes the shared secret for the
+    /// authenticator key agreement key `peer_public_key`.
+    ///
+    /// Returns the public key that has to be sent to the authenticator and the shared secret.
+    fn encapsulate<R: CryptoRngCore>(
+        rng: &mut R,
+        peer_public_key: &EcdhEsHkdf256PublicKey,
+    ) -> Result<(EcdhEsHkdf256PublicKey, Self::SharedSecret)> {
+        let secret_key = SecretKey::random(rng);
+        let shared_secret = Self::decapsulate(&secret_key, &peer_public_key.into())?;
+        Ok((from_public_key(&secret_key.public_key()), shared_secret))
+    }
+}
+
+/// The shared secret of PIN/UV auth protocol one.
+///
+/// The same key is used for encryption and authentication.  The key is zeroized on drop.
+pub struct SharedSecretV1([u8; 32]);
+
+impl SharedSecretV1 {
+    /// Derives the shared secret from the x coordinate of the ECDH result using SHA-256.
+    pub fn derive(z: &[u8; 32]) -> Self {
+        Self(Sha256::digest(z).into())
+    }
+
+    /// Returns the key used for AES-256-CBC and HMAC-SHA-256.
+    pub fn key(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SharedSecretV1 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for SharedSecretV1 {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SharedSecretV1 {}
+
+/// The shared secret of PIN/UV auth protocol two.
+///
+/// It consists of the HMAC key followed by the AES key.  The keys are zeroized on drop.
+pub struct SharedSecretV2([u8; 64]);
+
+impl SharedSecretV2 {
+    /// Derives the shared secret from the x coordinate of the ECDH result using HKDF-SHA-256.
+    pub fn derive(z: &[u8; 32]) -> Self {
+        let hkdf = hkdf::Hkdf::<Sha256>::new(Some(&[0; 32]), z);
+        let mut shared_secret = [0; 64];
+        let (hmac_key, aes_key) = shared_secret.split_at_mut(32);
+        // the output length is valid for HKDF-SHA-256, so expand cannot fail
+        hkdf.expand(b"CTAP2 HMAC key", hmac_key).unwrap();
+        hkdf.expand(b"CTAP2 AES key", aes_key).unwrap();
+        Self(shared_secret)
+    }
+
+    /// Returns the key used for HMAC-SHA-256.
+    pub fn hmac_key(&self) -> &[u8; 32] {
+        self.0[..32].try_into().unwrap()
+    }
+
+    /// Returns the key used for AES-256-CBC.
+    pub fn aes_key(&self) -> &[u8; 32] {
+        self.0[32..].try_into().unwrap()
+    }
+}
+
+impl AsRef<[u8]> for SharedSecretV2 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for SharedSecretV2 {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SharedSecretV2 {}
+
+/// PIN/UV auth protocol one.
+pub struct PinProtocolV1;
+
+impl PinUvAuthProtocol for PinProtocolV1 {
+    const VERSION: PinProtocolVersion = PinProtocolVersion::V1;
+
+    type SharedSecret = SharedSecretV1;
+
+    fn kdf(z: &[u8; 32]) -> SharedSecretV1 {
+        SharedSecretV1::derive(z)
+    }
+
+    fn encrypt<R: CryptoRngCore, const N: usize>(
+        _rng: &mut R,
+        shared_secret: &SharedSecretV1,
+        plaintext: &[u8],
+    ) -> Result<Bytes<N>> {
+        let mut ciphertext = Bytes::from_slice(plaintext).map_err(|_| Error::InvalidLength)?;
+        aes_cbc_encrypt(shared_secret.key(), &[0; 16], &mut ciphertext)?;
+        Ok(ciphertext)
+    }
+
+    fn decrypt<const N: usize>(
+        shared_secret: &SharedSecretV1,
+        ciphertext: &[u8],
+    ) -> Result<Bytes<N>> {
+        let mut plaintext = Bytes::from_slice(ciphertext).map_err(|_| Error::InvalidLength)?;
+        aes_cbc_decrypt(shared_secret.key(), &[0; 16], &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    fn authenticate(key: &[u8], message: &[u8]) -> Bytes<32> {
+        let tag = hmac_sha256(key, message).finalize().into_bytes();
+        Bytes::from_slice(&tag[..16]).unwrap()
+    }
+
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+        if signature.len() != 16 {
+            return Err(Error::PinAuthInvalid);
+        }
+        hmac_sha256(key, message)
+            .verify_truncated_left(signature)
+            .map_err(|_| Error::PinAuthInvalid)
+    }
+}
+
+/// PIN/UV auth protocol two.
+pub struct PinProtocolV2;
+
+impl PinUvAuthProtocol for PinProtocolV2 {
+    const VERSION: PinProtocolVersion = PinProtocolVersion::V2;
+
+    type SharedSecret = SharedSecretV2;
+
+    fn kdf(z: &[u8; 32]) -> SharedSecretV2 {
+        SharedSecretV2::derive(z)
+    }
+
+    fn encrypt<R: CryptoRngCore, const N: usize>(
+        rng: &mut R,
+        shared_secret: &SharedSecretV2,
+        plaintext: &[u8],
+    ) -> Result<Bytes<N>> {
+        let mut iv = [0; 16];
+        rng.fill_bytes(&mut iv);
+        let mut ciphertext = Bytes::from_slice(&iv).map_err(|_| Error::InvalidLength)?;
+        ciphertext
+            .extend_from_slice(plaintext)
+            .map_err(|_| Error::InvalidLength)?;
+        aes_cbc_encrypt(shared_secret.aes_key(), &iv, &mut ciphertext[16..])?;
+        Ok(ciphertext)
+    }
+
+    fn decrypt<const N: usize>(
+        shared_secret: &SharedSecretV2,
+        ciphertext: &[u8],
+    ) -> Result<Bytes<N>> {
+        if ciphertext.len() < 16 {
+            return Err(Error::InvalidLength);
+        }
+        let (iv, ciphertext) = ciphertext.split_at(16);
+        let mut plaintext = Bytes::from_slice(ciphertext).map_err(|_| Error::InvalidLength)?;
+        aes_cbc_decrypt(shared_secret.aes_key(), iv, &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    fn authenticate(key: &[u8], message: &[u8]) -> Bytes<32> {
+        // if the key is the shared secret, only the HMAC key is used
+        let key = &key[..key.len().min(32)];
+        let tag = hmac_sha256(key, message).finalize().into_bytes();
+        Bytes::from_slice(&tag).unwrap()
+    }
+
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+        let key = &key[..key.len().min(32)];
+        hmac_sha256(key, message)
+            .verify_slice(signature)
+            .map_err(|_| Error::PinAuthInvalid)
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac
+}
+
+fn aes_cbc_encrypt(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<()> {
+    if data.len() % 16 != 0 {
+        return Err(Error::InvalidLength);
+    }
+    let mut encryptor =
+        cbc::Encryptor::<Aes256>::new(GenericArray::from_slice(key), GenericArray::from_slice(iv));
+    for block in data.chunks_exact_mut(16) {
+        encryptor.encrypt_block_mut(GenericArray::from_mut_slice(block));
+    }
+    Ok(())
+}
+
+fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &mut [u8]) -> Result<()> {
+    if data.len() % 16 != 0 {
+        return Err(Error::InvalidLength);
+    }
+    let mut decryptor =
+        cbc::Decryptor::<Aes256>::new(GenericArray::from_slice(key), GenericArray::from_slice(iv));
+    for block in data.chunks_exact_mut(16) {
+        decryptor.decrypt_block_mut(GenericArray::from_mut_slice(block));
+    }
+    Ok(())
+}
+
+fn to_public_key(key: &EcdhEsHkdf256PublicKeyRef<'_>) -> Result<PublicKey> {
+    if key.x.len() != 32 || key.y.len() != 32 {
+        return Err(Error::InvalidParameter);
+    }
+    let mut sec1 = [0; 65];
+    sec1[0] = 0x04;
+    sec1[1..33].copy_from_slice(&key.x);
+    sec1[33..].copy_from_slice(&key.y);
+    PublicKey::from_sec1_bytes(&sec1).map_err(|_| Error::InvalidParameter)
+}
+
+fn from_public_key(key: &PublicKey) -> EcdhEsHkdf256PublicKey {
+    let point = key.to_encoded_point(false);
+    // an uncompressed point always has both coordinates
+    EcdhEsHkdf256PublicKey {
+        x: Bytes::from_slice(point.x().unwrap()).unwrap(),
+        y: Bytes::from_slice(point.y().unwrap()).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::{CryptoRng, RngCore};
+
+    /// A deterministic RNG for the tests.
+    struct CounterRng(u8);
+
+    impl RngCore for CounterRng {
+        fn next_u32(&mut self) -> u32 {
+            rand_core::impls::next_u32_via_fill(self)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_fill(self)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                self.0 = self.0.wrapping_add(1);
+                *byte = self.0;
+            }
+        }
+
+        fn try_fill_bytes(
+            &mut self,
+            dest: &mut [u8],
+        ) -> core::result::Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for CounterRng {}
+
+    fn test_protocol<P: PinUvAuthProtocol>() {
+        let mut rng = CounterRng(0);
+        let secret_key = SecretKey::random(&mut rng);
+        let public_key = from_public_key(&secret_key.public_key());
+
+        let (platform_key, platform_secret) = P::encapsulate(&mut rng, &public_key).unwrap();
+        let authenticator_secret = P::decapsulate(&secret_key, &(&platform_key).into()).unwrap();
+        assert_eq!(platform_secret.as_ref(), authenticator_secret.as_ref());
+
+        let plaintext = [0x42; 32];
+        let ciphertext: Bytes<64> = P::encrypt(&mut rng, &platform_secret, &plaintext).unwrap();
+        assert_ne!(&ciphertext[ciphertext.len() - 32..], &plaintext);
+        let decrypted: Bytes<64> = P::decrypt(&authenticator_secret, &ciphertext).unwrap();
+        assert_eq!(decrypted.as_slice(), &plaintext);
+        assert_eq!(
+            P::encrypt::<_, 64>(&mut rng, &platform_secret, &[0x42; 20]),
+            Err(Error::InvalidLength)
+        );
+
+        let pin_token = [0x17; 32];
+        let signature = P::authenticate(&pin_token, b"message");
+        assert_eq!(P::verify(&pin_token, b"message", &signature), Ok(()));
+        assert_eq!(
+            P::verify(&pin_token, b"massage", &signature),
+            Err(Error::PinAuthInvalid)
+        );
+        assert_eq!(
+            P::verify(&pin_token, b"message", &signature[..8]),
+            Err(Error::PinAuthInvalid)
+        );
+    }
+
+    #[test]
+    fn test_pin_protocol_v1() {
+        test_protocol::<PinProtocolV1>();
+        assert_eq!(PinProtocolV1::authenticate(&[0; 32], b"").len(), 16);
+    }
+
+    #[test]
+    fn test_shared_secret_derivation() {
+        let z = [0x01; 32];
+        let v1 = SharedSecretV1::derive(&z);
+        assert_eq!(v1.key(), &<[u8; 32]>::from(Sha256::digest(z)));
+
+        let v2 = SharedSecretV2::derive(&z);
+        assert_ne!(v2.hmac_key(), v2.aes_key());
+        assert_eq!(&v2.as_ref()[..32], v2.hmac_key());
+        assert_eq!(&v2.as_ref()[32..], v2.aes_key());
+        // the HMAC key uses the same derivation as a single HKDF expansion
+        let mut hmac_key = [0; 32];
+        hkdf::Hkdf::<Sha256>::new(Some(&[0; 32]), &z)
+            .expand(b"CTAP2 HMAC key", &mut hmac_key)
+            .unwrap();
+        assert_eq!(v2.hmac_key(), &hmac_key);
+    }
+
+    #[test]
+    fn test_pin_protocol_v2() {
+        test_protocol::<PinProtocolV2>();
+        assert_eq!(PinProtocolV2::authenticate(&[0; 32], b"").len(), 32);
+    }
+}
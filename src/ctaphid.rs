@@ -0,0 +1,518 @@
+//! Helpers for the CTAPHID transport.
+//!
+//! See the [CTAP specification](https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#usb)
+//! for the message and packet structure.  This module defines the commands, error codes and
+//! packet formats and provides an iterator over the packet payloads of a response.  Complete
+//! messages can be assembled from packets with [`MessageAssembler`][] and split into packets with
+//! [`MessageDisassembler`][].
+
+use crate::{
+    ctap2::{Error, Result},
+    sizes::{PACKET_SIZE, THEORETICAL_MAX_MESSAGE_SIZE},
+};
+
+mod assembler;
+mod init;
+
+pub use assembler::{AssemblerError, MessageAssembler, MessageDisassembler};
+pub use init::{Capabilities, InitRequest, InitResponse, PROTOCOL_VERSION};
+
+/// The length of the payload of an initialization packet.
+pub const INITIALIZATION_PAYLOAD_LENGTH: usize = PACKET_SIZE - 7;
+/// The length of the payload of a continuation packet.
+pub const CONTINUATION_PAYLOAD_LENGTH: usize = PACKET_SIZE - 5;
+
+/// A CTAPHID command.
+///
+/// The values do not include the bit `0x80` that marks initialization packets.  Use
+/// [`Command::try_from`][] to parse a command and [`u8::from`][] to get its value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum Command {
+    Ping = 0x01,
+    Msg = 0x03,
+    Lock = 0x04,
+    Init = 0x06,
+    Wink = 0x08,
+    Cbor = 0x10,
+    Cancel = 0x11,
+    Keepalive = 0x3b,
+    Error = 0x3f,
+}
+
+impl TryFrom<u8> for Command {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0x01 => Ok(Self::Ping),
+            0x03 => Ok(Self::Msg),
+            0x04 => Ok(Self::Lock),
+            0x06 => Ok(Self::Init),
+            0x08 => Ok(Self::Wink),
+            0x10 => Ok(Self::Cbor),
+            0x11 => Ok(Self::Cancel),
+            0x3b => Ok(Self::Keepalive),
+            0x3f => Ok(Self::Error),
+            _ => Err(Error::InvalidCommand),
+        }
+    }
+}
+
+impl From<Command> for u8 {
+    fn from(command: Command) -> u8 {
+        command as u8
+    }
+}
+
+/// An error code sent with the [`Command::Error`][] command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum ErrorCode {
+    InvalidCommand = 0x01,
+    InvalidParameter = 0x02,
+    InvalidLength = 0x03,
+    InvalidSequence = 0x04,
+    Timeout = 0x05,
+    ChannelBusy = 0x06,
+    LockRequired = 0x0a,
+    InvalidChannel = 0x0b,
+    Other = 0x7f,
+}
+
+impl TryFrom<u8> for ErrorCode {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0x01 => Ok(Self::InvalidCommand),
+            0x02 => Ok(Self::InvalidParameter),
+            0x03 => Ok(Self::InvalidLength),
+            0x04 => Ok(Self::InvalidSequence),
+            0x05 => Ok(Self::Timeout),
+            0x06 => Ok(Self::ChannelBusy),
+            0x0a => Ok(Self::LockRequired),
+            0x0b => Ok(Self::InvalidChannel),
+            0x7f => Ok(Self::Other),
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+impl From<ErrorCode> for u8 {
+    fn from(code: ErrorCode) -> u8 {
+        code as u8
+    }
+}
+
+/// The status sent with the [`Command::Keepalive`][] command.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+#[repr(u8)]
+pub enum KeepaliveStatus {
+    /// The authenticator is still processing the request.
+    Processing = 1,
+    /// The authenticator is waiting for user presence.
+    UpNeeded = 2,
+}
+
+impl TryFrom<u8> for KeepaliveStatus {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(Self::Processing),
+            2 => Ok(Self::UpNeeded),
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+impl From<KeepaliveStatus> for u8 {
+    fn from(status: KeepaliveStatus) -> u8 {
+        status as u8
+    }
+}
+
+impl KeepaliveStatus {
+    /// Writes the keepalive packet with this status for the given channel.
+    pub fn write_packet(self, channel: ChannelId, packet: &mut [u8; PACKET_SIZE]) {
+        let chunk = Chunk::Initialization {
+            message_len: 1,
+            data: &[self as u8],
+        };
+        chunk.write_packet(channel, Command::Keepalive.into(), packet);
+    }
+}
+
+/// The payload of a [`Command::Wink`][] request, which is always empty.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WinkRequest;
+
+impl WinkRequest {
+    /// Parses the payload of a request.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the payload is not empty.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if !data.is_empty() {
+            return Err(Error::InvalidLength);
+        }
+        Ok(Self)
+    }
+}
+
+/// The payload of a [`Command::Lock`][] request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LockRequest {
+    /// The lock time in seconds.  Zero releases the lock.
+    pub seconds: u8,
+}
+
+impl LockRequest {
+    /// The maximum lock time in seconds.
+    pub const MAX_SECONDS: u8 = 10;
+
+    /// Parses the payload of a request.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the payload does not have one byte and
+    /// [`Error::InvalidParameter`][] if the lock time exceeds [`LockRequest::MAX_SECONDS`][].
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let [seconds] = data else {
+            return Err(Error::InvalidLength);
+        };
+        if *seconds > Self::MAX_SECONDS {
+            return Err(Error::InvalidParameter);
+        }
+        Ok(Self { seconds: *seconds })
+    }
+
+    /// Returns true if this request releases the lock.
+    pub fn is_release(&self) -> bool {
+        self.seconds == 0
+    }
+
+    /// Returns the lock time.
+    pub fn duration(&self) -> core::time::Duration {
+        core::time::Duration::from_secs(self.seconds.into())
+    }
+}
+
+/// A CTAPHID channel identifier.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ChannelId(pub u32);
+
+impl ChannelId {
+    /// The broadcast channel that is used for the [`Command::Init`][] command.
+    pub const BROADCAST: Self = Self(0xffff_ffff);
+
+    /// Returns true if this is the broadcast channel.
+    pub fn is_broadcast(&self) -> bool {
+        *self == Self::BROADCAST
+    }
+
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self(u32::from_be_bytes(bytes))
+    }
+
+    pub fn to_bytes(self) -> [u8; 4] {
+        self.0.to_be_bytes()
+    }
+}
+
+/// A CTAPHID packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Packet<'a> {
+    Initialization(InitializationPacket<'a>),
+    Continuation(ContinuationPacket<'a>),
+}
+
+impl<'a> Packet<'a> {
+    /// Parses a packet from a HID report.
+    pub fn parse(report: &'a [u8; PACKET_SIZE]) -> Self {
+        let channel = ChannelId::from_bytes(report[..4].try_into().unwrap());
+        if report[4] & 0x80 != 0 {
+            Self::Initialization(InitializationPacket {
+                channel,
+                command: report[4] & 0x7f,
+                message_len: u16::from_be_bytes([report[5], report[6]]),
+                data: report[7..].try_into().unwrap(),
+            })
+        } else {
+            Self::Continuation(ContinuationPacket {
+                channel,
+                sequence: report[4],
+                data: report[5..].try_into().unwrap(),
+            })
+        }
+    }
+
+    /// Returns the channel of this packet.
+    pub fn channel(&self) -> ChannelId {
+        match self {
+            Self::Initialization(packet) => packet.channel,
+            Self::Continuation(packet) => packet.channel,
+        }
+    }
+
+    /// Writes this packet into a HID report.
+    pub fn write(&self, report: &mut [u8; PACKET_SIZE]) {
+        match self {
+            Self::Initialization(packet) => packet.write(report),
+            Self::Continuation(packet) => packet.write(report),
+        }
+    }
+}
+
+/// The first packet of a CTAPHID message.
+///
+/// The payload always has the full packet size.  Only the first `message_len` bytes of the
+/// message are meaningful, the remaining bytes are padding.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitializationPacket<'a> {
+    pub channel: ChannelId,
+    /// The command without the bit `0x80`.  Use [`Command::try_from`][] to parse it.
+    pub command: u8,
+    pub message_len: u16,
+    pub data: &'a [u8; INITIALIZATION_PAYLOAD_LENGTH],
+}
+
+impl InitializationPacket<'_> {
+    /// Writes this packet into a HID report.
+    pub fn write(&self, report: &mut [u8; PACKET_SIZE]) {
+        report[..4].copy_from_slice(&self.channel.to_bytes());
+        report[4] = self.command | 0x80;
+        report[5..7].copy_from_slice(&self.message_len.to_be_bytes());
+        report[7..].copy_from_slice(self.data);
+    }
+}
+
+/// A subsequent packet of a CTAPHID message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ContinuationPacket<'a> {
+    pub channel: ChannelId,
+    /// The sequence number, starting at zero for the first continuation packet.
+    pub sequence: u8,
+    pub data: &'a [u8; CONTINUATION_PAYLOAD_LENGTH],
+}
+
+impl ContinuationPacket<'_> {
+    /// Writes this packet into a HID report.
+    pub fn write(&self, report: &mut [u8; PACKET_SIZE]) {
+        report[..4].copy_from_slice(&self.channel.to_bytes());
+        report[4] = self.sequence;
+        report[5..].copy_from_slice(self.data);
+    }
+}
+
+/// A part of a message that fits into a single CTAPHID packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Chunk<'a> {
+    /// The payload of the initialization packet together with the total message length.
+    Initialization { message_len: u16, data: &'a [u8] },
+    /// The payload of a continuation packet together with its sequence number.
+    Continuation { sequence: u8, data: &'a [u8] },
+}
+
+impl Chunk<'_> {
+    /// Returns the payload of this chunk.
+    pub fn data(&self) -> &[u8] {
+        match self {
+            Self::Initialization { data, .. } | Self::Continuation { data, .. } => data,
+        }
+    }
+
+    /// Writes the packet for this chunk for the given channel and command.
+    ///
+    /// The command is only used for initialization packets.  Unused bytes are set to zero.
+    pub fn write_packet(&self, channel: ChannelId, command: u8, packet: &mut [u8; PACKET_SIZE]) {
+        packet.fill(0);
+        packet[..4].copy_from_slice(&channel.to_bytes());
+        match self {
+            Self::Initialization { message_len, data } => {
+                packet[4] = command | 0x80;
+                packet[5..7].copy_from_slice(&message_len.to_be_bytes());
+                packet[7..][..data.len()].copy_from_slice(data);
+            }
+            Self::Continuation { sequence, data } => {
+                packet[4] = *sequence;
+                packet[5..][..data.len()].copy_from_slice(data);
+            }
+        }
+    }
+}
+
+/// An iterator over the [`Chunk`][]s of a serialized response.
+///
+/// This makes it possible to send a response packet by packet without copying it into a
+/// separate staging buffer.  The first item is always an initialization chunk, even for an empty
+/// response.
+#[derive(Clone, Debug)]
+pub struct ResponseChunks<'a> {
+    data: &'a [u8],
+    message_len: u16,
+    sequence: Option<u8>,
+}
+
+impl<'a> ResponseChunks<'a> {
+    /// Splits the given serialized response into chunks.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the response does not fit into a CTAPHID message.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() > THEORETICAL_MAX_MESSAGE_SIZE {
+            return Err(Error::InvalidLength);
+        }
+        let message_len = u16::try_from(data.len()).map_err(|_| Error::InvalidLength)?;
+        Ok(Self {
+            data,
+            message_len,
+            sequence: None,
+        })
+    }
+}
+
+impl<'a> Iterator for ResponseChunks<'a> {
+    type Item = Chunk<'a>;
+
+    fn next(&mut self) -> Option<Chunk<'a>> {
+        match self.sequence {
+            None => {
+                let (data, rest) = self
+                    .data
+                    .split_at(self.data.len().min(INITIALIZATION_PAYLOAD_LENGTH));
+                self.data = rest;
+                self.sequence = Some(0);
+                Some(Chunk::Initialization {
+                    message_len: self.message_len,
+                    data,
+                })
+            }
+            Some(sequence) if !self.data.is_empty() => {
+                let (data, rest) = self
+                    .data
+                    .split_at(self.data.len().min(CONTINUATION_PAYLOAD_LENGTH));
+                self.data = rest;
+                self.sequence = Some(sequence + 1);
+                Some(Chunk::Continuation { sequence, data })
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Command::try_from(0x10), Ok(Command::Cbor));
+        assert_eq!(Command::try_from(0x90), Err(Error::InvalidCommand));
+        assert_eq!(u8::from(Command::Keepalive), 0x3b);
+        assert_eq!(ErrorCode::try_from(0x0b), Ok(ErrorCode::InvalidChannel));
+        assert_eq!(ErrorCode::try_from(0x07), Err(Error::InvalidParameter));
+        assert_eq!(u8::from(ErrorCode::Other), 0x7f);
+        assert_eq!(KeepaliveStatus::try_from(2), Ok(KeepaliveStatus::UpNeeded));
+        assert_eq!(KeepaliveStatus::try_from(0), Err(Error::InvalidParameter));
+        assert_eq!(u8::from(KeepaliveStatus::Processing), 1);
+
+        let mut packet = [0xff; PACKET_SIZE];
+        KeepaliveStatus::UpNeeded.write_packet(ChannelId(0x01020304), &mut packet);
+        assert_eq!(
+            packet[..8],
+            [0x01, 0x02, 0x03, 0x04, 0xbb, 0x00, 0x01, 0x02]
+        );
+        assert_eq!(packet[8..], [0x00; PACKET_SIZE - 8]);
+        assert!(ChannelId::from_bytes([0xff; 4]).is_broadcast());
+        assert_eq!(ChannelId(0x01020304).to_bytes(), [0x01, 0x02, 0x03, 0x04]);
+    }
+
+    #[test]
+    fn test_wink_lock() {
+        assert_eq!(WinkRequest::parse(&[]), Ok(WinkRequest));
+        assert_eq!(WinkRequest::parse(&[0x00]), Err(Error::InvalidLength));
+
+        let request = LockRequest::parse(&[10]).unwrap();
+        assert!(!request.is_release());
+        assert_eq!(request.duration(), core::time::Duration::from_secs(10));
+        assert!(LockRequest::parse(&[0]).unwrap().is_release());
+        assert_eq!(LockRequest::parse(&[11]), Err(Error::InvalidParameter));
+        assert_eq!(LockRequest::parse(&[]), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_packets() {
+        let mut report = [0xab; PACKET_SIZE];
+        report[..7].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x86, 0x00, 0x08]);
+        let packet = Packet::parse(&report);
+        let Packet::Initialization(init) = packet else {
+            panic!("expected initialization packet, got: {:?}", packet);
+        };
+        assert!(init.channel.is_broadcast());
+        assert_eq!(Command::try_from(init.command), Ok(Command::Init));
+        assert_eq!(init.message_len, 8);
+        assert_eq!(init.data, &[0xab; INITIALIZATION_PAYLOAD_LENGTH]);
+        let mut output = [0; PACKET_SIZE];
+        packet.write(&mut output);
+        assert_eq!(output, report);
+
+        report[..5].copy_from_slice(&[0x01, 0x02, 0x03, 0x04, 0x05]);
+        let packet = Packet::parse(&report);
+        assert_eq!(
+            packet,
+            Packet::Continuation(ContinuationPacket {
+                channel: ChannelId(0x01020304),
+                sequence: 5,
+                data: &[0xab; CONTINUATION_PAYLOAD_LENGTH],
+            })
+        );
+        assert_eq!(packet.channel(), ChannelId(0x01020304));
+        packet.write(&mut output);
+        assert_eq!(output, report);
+    }
+
+    #[test]
+    fn test_response_chunks() {
+        let response = [0xab; 200];
+        let chunks: std::vec::Vec<_> = ResponseChunks::new(&response).unwrap().collect();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks[0],
+            Chunk::Initialization {
+                message_len: 200,
+                data: &response[..57]
+            }
+        );
+        assert_eq!(
+            chunks[3],
+            Chunk::Continuation {
+                sequence: 2,
+                data: &response[175..]
+            }
+        );
+        let len: usize = chunks.iter().map(|chunk| chunk.data().len()).sum();
+        assert_eq!(len, response.len());
+
+        let mut packet = [0xff; PACKET_SIZE];
+        chunks[3].write_packet(ChannelId(0x01020304), 0x10, &mut packet);
+        assert_eq!(packet[..5], [0x01, 0x02, 0x03, 0x04, 0x02]);
+        assert_eq!(packet[5..30], [0xab; 25]);
+        assert_eq!(packet[30..], [0x00; 34]);
+
+        chunks[0].write_packet(ChannelId(0x01020304), 0x10, &mut packet);
+        assert_eq!(packet[..7], [0x01, 0x02, 0x03, 0x04, 0x90, 0x00, 0xc8]);
+
+        let mut chunks = ResponseChunks::new(&[]).unwrap();
+        assert_eq!(
+            chunks.next(),
+            Some(Chunk::Initialization {
+                message_len: 0,
+                data: &[]
+            })
+        );
+        assert_eq!(chunks.next(), None);
+
+        assert!(ResponseChunks::new(&[0; THEORETICAL_MAX_MESSAGE_SIZE + 1]).is_err());
+    }
+}
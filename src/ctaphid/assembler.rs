@@ -0,0 +1,298 @@
+use super::{
+    ChannelId, Command, ErrorCode, Packet, ResponseChunks, CONTINUATION_PAYLOAD_LENGTH,
+    INITIALIZATION_PAYLOAD_LENGTH,
+};
+use crate::{
+    ctap2::Result,
+    sizes::{PACKET_SIZE, THEORETICAL_MAX_MESSAGE_SIZE},
+    Bytes,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Transaction {
+    channel: ChannelId,
+    command: Command,
+    message_len: usize,
+    sequence: u8,
+}
+
+/// An error returned by [`MessageAssembler::push`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AssemblerError {
+    /// A packet for the given channel was received while a transaction on another channel is
+    /// pending.  [`ErrorCode::ChannelBusy`][] should be sent to the given channel.  The pending
+    /// transaction is not affected.
+    ChannelBusy(ChannelId),
+    /// The packet is invalid.  The error code should be sent to the channel of the packet.
+    Invalid(ErrorCode),
+}
+
+impl AssemblerError {
+    /// Returns the error code that should be sent to the host.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::ChannelBusy(_) => ErrorCode::ChannelBusy,
+            Self::Invalid(code) => *code,
+        }
+    }
+}
+
+impl From<ErrorCode> for AssemblerError {
+    fn from(code: ErrorCode) -> Self {
+        Self::Invalid(code)
+    }
+}
+
+/// Assembles CTAPHID request messages from packets.
+///
+/// The assembler validates the channel and the sequence numbers of the packets.  Errors are
+/// returned as an [`AssemblerError`][] that contains the [`ErrorCode`][] that should be sent to
+/// the host.  Timeouts are not handled by
+/// the assembler and must be implemented by the transport using [`MessageAssembler::reset`][].
+#[derive(Clone, Debug)]
+pub struct MessageAssembler<const N: usize = THEORETICAL_MAX_MESSAGE_SIZE> {
+    buffer: Bytes<N>,
+    transaction: Option<Transaction>,
+}
+
+impl<const N: usize> Default for MessageAssembler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MessageAssembler<N> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Bytes::new(),
+            transaction: None,
+        }
+    }
+
+    /// Returns the channel of the pending transaction, if any.
+    pub fn channel(&self) -> Option<ChannelId> {
+        self.transaction.map(|transaction| transaction.channel)
+    }
+
+    /// Aborts the pending transaction, if any.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.transaction = None;
+    }
+
+    /// Processes the given packet and returns the command and the payload if the message is
+    /// complete.
+    ///
+    /// Continuation packets without a pending transaction are ignored.  Packets for other channels
+    /// while a transaction is pending are rejected with [`AssemblerError::ChannelBusy`][].  An
+    /// initialization packet for the [`Command::Init`][] command aborts a pending transaction on
+    /// the same channel.  Other errors abort the pending transaction.
+    pub fn push(
+        &mut self,
+        packet: &Packet<'_>,
+    ) -> core::result::Result<Option<(Command, &[u8])>, AssemblerError> {
+        match packet {
+            Packet::Initialization(packet) => {
+                let command = Command::try_from(packet.command);
+                if let Some(transaction) = self.transaction {
+                    if transaction.channel != packet.channel {
+                        return Err(AssemblerError::ChannelBusy(packet.channel));
+                    }
+                    if command != Ok(Command::Init) {
+                        self.reset();
+                        return Err(ErrorCode::InvalidSequence.into());
+                    }
+                }
+                self.reset();
+                let command = command.map_err(|_| ErrorCode::InvalidCommand)?;
+                if packet.channel == ChannelId(0)
+                    || (packet.channel.is_broadcast() && command != Command::Init)
+                {
+                    return Err(ErrorCode::InvalidChannel.into());
+                }
+                let message_len = usize::from(packet.message_len);
+                if message_len > N.min(THEORETICAL_MAX_MESSAGE_SIZE) {
+                    return Err(ErrorCode::InvalidLength.into());
+                }
+                let len = message_len.min(INITIALIZATION_PAYLOAD_LENGTH);
+                // cannot fail as we checked the message length
+                self.buffer.extend_from_slice(&packet.data[..len]).ok();
+                self.transaction = Some(Transaction {
+                    channel: packet.channel,
+                    command,
+                    message_len,
+                    sequence: 0,
+                });
+            }
+            Packet::Continuation(packet) => {
+                let Some(transaction) = &mut self.transaction else {
+                    return Ok(None);
+                };
+                if transaction.channel != packet.channel {
+                    return Err(AssemblerError::ChannelBusy(packet.channel));
+                }
+                if transaction.sequence != packet.sequence {
+                    self.reset();
+                    return Err(ErrorCode::InvalidSequence.into());
+                }
+                transaction.sequence += 1;
+                let len =
+                    (transaction.message_len - self.buffer.len()).min(CONTINUATION_PAYLOAD_LENGTH);
+                // cannot fail as we checked the message length
+                self.buffer.extend_from_slice(&packet.data[..len]).ok();
+            }
+        }
+        match self.transaction {
+            Some(transaction) if self.buffer.len() == transaction.message_len => {
+                self.transaction = None;
+                Ok(Some((transaction.command, &self.buffer)))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Splits a CTAPHID response message into packets.
+#[derive(Clone, Debug)]
+pub struct MessageDisassembler<'a> {
+    channel: ChannelId,
+    command: Command,
+    chunks: ResponseChunks<'a>,
+}
+
+impl<'a> MessageDisassembler<'a> {
+    /// Prepares the given response for the given channel and command.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::Error::InvalidLength] if the response does not
+    /// fit into a CTAPHID message.
+    pub fn new(channel: ChannelId, command: Command, data: &'a [u8]) -> Result<Self> {
+        Ok(Self {
+            channel,
+            command,
+            chunks: ResponseChunks::new(data)?,
+        })
+    }
+}
+
+impl Iterator for MessageDisassembler<'_> {
+    type Item = [u8; PACKET_SIZE];
+
+    fn next(&mut self) -> Option<[u8; PACKET_SIZE]> {
+        let chunk = self.chunks.next()?;
+        let mut report = [0; PACKET_SIZE];
+        chunk.write_packet(self.channel, self.command.into(), &mut report);
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let channel = ChannelId(0x01020304);
+        let message: std::vec::Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let mut assembler = MessageAssembler::<256>::new();
+        let reports: std::vec::Vec<_> = MessageDisassembler::new(channel, Command::Cbor, &message)
+            .unwrap()
+            .collect();
+        assert_eq!(reports.len(), 4);
+        for report in &reports[..3] {
+            assert_eq!(assembler.push(&Packet::parse(report)), Ok(None));
+            assert_eq!(assembler.channel(), Some(channel));
+        }
+        assert_eq!(
+            assembler.push(&Packet::parse(&reports[3])),
+            Ok(Some((Command::Cbor, message.as_slice())))
+        );
+        assert_eq!(assembler.channel(), None);
+
+        // spurious continuation packets are ignored
+        assert_eq!(assembler.push(&Packet::parse(&reports[1])), Ok(None));
+
+        // a message that fits into the initialization packet
+        let mut report = [0; PACKET_SIZE];
+        report[..7].copy_from_slice(&[0xff, 0xff, 0xff, 0xff, 0x86, 0x00, 0x08]);
+        report[7..15].fill(0xab);
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Ok(Some((Command::Init, &[0xab; 8][..])))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let message = [0xab; 100];
+        let reports: std::vec::Vec<_> =
+            MessageDisassembler::new(ChannelId(1), Command::Msg, &message)
+                .unwrap()
+                .collect();
+        let other: std::vec::Vec<_> =
+            MessageDisassembler::new(ChannelId(2), Command::Msg, &message)
+                .unwrap()
+                .collect();
+        let mut assembler = MessageAssembler::<256>::new();
+
+        // busy channel
+        assembler.push(&Packet::parse(&reports[0])).unwrap();
+        assert_eq!(
+            assembler.push(&Packet::parse(&other[0])),
+            Err(AssemblerError::ChannelBusy(ChannelId(2)))
+        );
+        assert_eq!(
+            assembler.push(&Packet::parse(&other[1])),
+            Err(AssemblerError::ChannelBusy(ChannelId(2)))
+        );
+        assert_eq!(
+            AssemblerError::ChannelBusy(ChannelId(2)).code(),
+            ErrorCode::ChannelBusy
+        );
+        assert_eq!(assembler.channel(), Some(ChannelId(1)));
+
+        // wrong sequence number
+        let mut report = reports[1];
+        report[4] = 1;
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidSequence))
+        );
+        assert_eq!(assembler.channel(), None);
+
+        // invalid channel, command and length
+        let mut report = reports[0];
+        report[..4].fill(0xff);
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidChannel))
+        );
+        report[..4].fill(0x00);
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidChannel))
+        );
+        let mut report = reports[0];
+        report[4] = 0x80 | 0x3c;
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidCommand))
+        );
+        let mut report = reports[0];
+        report[5..7].copy_from_slice(&257u16.to_be_bytes());
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidLength))
+        );
+
+        // messages that do not fit into the sequence numbers are rejected even if the buffer is
+        // large enough
+        let mut assembler = MessageAssembler::<8192>::new();
+        let len = u16::try_from(THEORETICAL_MAX_MESSAGE_SIZE + 1).unwrap();
+        report[5..7].copy_from_slice(&len.to_be_bytes());
+        assert_eq!(
+            assembler.push(&Packet::parse(&report)),
+            Err(AssemblerError::Invalid(ErrorCode::InvalidLength))
+        );
+        assert_eq!(assembler.channel(), None);
+    }
+}
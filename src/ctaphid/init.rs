@@ -0,0 +1,139 @@
+use bitflags::bitflags;
+
+use super::ChannelId;
+use crate::ctap2::{Error, Result};
+
+/// The CTAPHID protocol version implemented by this crate.
+pub const PROTOCOL_VERSION: u8 = 2;
+
+bitflags! {
+    /// The capabilities of a device as reported in the [`InitResponse`][].
+    #[derive(Default)]
+    pub struct Capabilities: u8 {
+        /// The device implements the WINK command.
+        const WINK = 0x01;
+        /// The device implements the CBOR command.
+        const CBOR = 0x04;
+        /// The device does not implement the MSG command.
+        const NMSG = 0x08;
+    }
+}
+
+/// The payload of a CTAPHID_INIT request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitRequest {
+    pub nonce: [u8; 8],
+}
+
+impl InitRequest {
+    /// Parses the payload of a request.
+    ///
+    /// Returns [`Error::InvalidLength`][] if the payload does not have eight bytes.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let nonce = data.try_into().map_err(|_| Error::InvalidLength)?;
+        Ok(Self { nonce })
+    }
+}
+
+/// The payload of a CTAPHID_INIT response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InitResponse {
+    /// The nonce of the request.
+    pub nonce: [u8; 8],
+    /// The channel assigned to the host.
+    pub channel: ChannelId,
+    pub protocol_version: u8,
+    pub major_device_version: u8,
+    pub minor_device_version: u8,
+    pub build_device_version: u8,
+    pub capabilities: Capabilities,
+}
+
+impl InitResponse {
+    /// The length of the serialized response.
+    pub const LENGTH: usize = 17;
+
+    /// Creates a response for the given request with the [`PROTOCOL_VERSION`][] of this crate.
+    pub fn new(
+        request: &InitRequest,
+        channel: ChannelId,
+        device_version: (u8, u8, u8),
+        capabilities: Capabilities,
+    ) -> Self {
+        Self {
+            nonce: request.nonce,
+            channel,
+            protocol_version: PROTOCOL_VERSION,
+            major_device_version: device_version.0,
+            minor_device_version: device_version.1,
+            build_device_version: device_version.2,
+            capabilities,
+        }
+    }
+
+    /// Parses the payload of a response.
+    ///
+    /// Unknown capability flags are ignored.  Returns [`Error::InvalidLength`][] if the payload
+    /// is shorter than [`InitResponse::LENGTH`][].  Additional data is ignored as permitted by the
+    /// specification.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let data: &[u8; Self::LENGTH] = data
+            .get(..Self::LENGTH)
+            .and_then(|data| data.try_into().ok())
+            .ok_or(Error::InvalidLength)?;
+        Ok(Self {
+            nonce: data[..8].try_into().unwrap(),
+            channel: ChannelId::from_bytes(data[8..12].try_into().unwrap()),
+            protocol_version: data[12],
+            major_device_version: data[13],
+            minor_device_version: data[14],
+            build_device_version: data[15],
+            capabilities: Capabilities::from_bits_truncate(data[16]),
+        })
+    }
+
+    /// Serializes the response.
+    pub fn serialize(&self) -> [u8; Self::LENGTH] {
+        let mut data = [0; Self::LENGTH];
+        data[..8].copy_from_slice(&self.nonce);
+        data[8..12].copy_from_slice(&self.channel.to_bytes());
+        data[12] = self.protocol_version;
+        data[13] = self.major_device_version;
+        data[14] = self.minor_device_version;
+        data[15] = self.build_device_version;
+        data[16] = self.capabilities.bits();
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init() {
+        let request =
+            InitRequest::parse(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]).unwrap();
+        assert_eq!(InitRequest::parse(&[0x01; 7]), Err(Error::InvalidLength));
+
+        let response = InitResponse::new(
+            &request,
+            ChannelId(0xaabbccdd),
+            (1, 2, 3),
+            Capabilities::WINK | Capabilities::CBOR,
+        );
+        let serialized = response.serialize();
+        assert_eq!(
+            serialized,
+            [
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0xaa, 0xbb, 0xcc, 0xdd, 0x02, 0x01,
+                0x02, 0x03, 0x05
+            ]
+        );
+        assert_eq!(InitResponse::parse(&serialized), Ok(response));
+        assert_eq!(
+            InitResponse::parse(&serialized[..16]),
+            Err(Error::InvalidLength)
+        );
+    }
+}
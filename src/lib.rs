@@ -28,8 +28,14 @@ pub use serde_bytes::ByteArray;
 #[cfg(feature = "arbitrary")]
 mod arbitrary;
 pub mod authenticator;
+#[cfg(feature = "base64url")]
+pub mod base64url;
+pub(crate) mod cbor;
+pub mod cose;
 pub mod ctap1;
 pub mod ctap2;
+pub mod ctaphid;
+pub mod nfc;
 pub(crate) mod operation;
 pub use cbor_smol as serde;
 pub mod sizes;
@@ -50,6 +56,8 @@ impl Display for TryFromStrError {
     }
 }
 
+impl core::error::Error for TryFromStrError {}
+
 #[cfg(test)]
 mod tests {}
 
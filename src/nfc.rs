@@ -0,0 +1,294 @@
+//! Helpers for the NFC transport.
+//!
+//! See the [CTAP specification](https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#nfc)
+//! for the framing of CTAP messages in ISO 7816-4 APDUs.  CTAP2 requests are sent with the
+//! `NFCCTAP_MSG` command and long responses are split using response chaining.
+
+use iso7816::{command::CommandView, Instruction};
+
+use crate::{ctap1, Bytes};
+
+/// The AID of the FIDO applet.
+pub const AID: [u8; 8] = [0xa0, 0x00, 0x00, 0x06, 0x47, 0x2f, 0x00, 0x01];
+
+/// The response to the SELECT command for authenticators that support CTAP2.
+pub const SELECT_RESPONSE_FIDO_2_0: &[u8] = b"FIDO_2_0";
+/// The response to the SELECT command for authenticators that only support CTAP1.
+pub const SELECT_RESPONSE_U2F_V2: &[u8] = b"U2F_V2";
+
+/// The class byte of the `NFCCTAP_MSG` and `NFCCTAP_GETRESPONSE` commands.
+pub const NFCCTAP_CLASS: u8 = 0x80;
+/// The instruction byte of the `NFCCTAP_MSG` command.
+pub const NFCCTAP_MSG: u8 = 0x10;
+/// The instruction byte of the `NFCCTAP_GETRESPONSE` command.
+pub const NFCCTAP_GETRESPONSE: u8 = 0x11;
+
+/// A command sent to the FIDO applet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Request<'a> {
+    /// SELECT of the FIDO applet.
+    Select,
+    /// A CTAP1 request.
+    Ctap1(ctap1::Request<'a>),
+    /// An `NFCCTAP_MSG` command with a serialized CTAP2 request.
+    Ctap2 {
+        /// The CTAP2 command byte followed by the CBOR parameters.
+        data: &'a [u8],
+        /// Whether the platform supports `NFCCTAP_GETRESPONSE`, i.e. whether the authenticator
+        /// may send status updates instead of blocking.
+        get_response_supported: bool,
+    },
+    /// An `NFCCTAP_GETRESPONSE` command that polls the result of a CTAP2 request.
+    Ctap2GetResponse,
+    /// A GET RESPONSE command that requests the next part of a chained response.
+    GetResponse,
+}
+
+impl<'a> TryFrom<CommandView<'a>> for Request<'a> {
+    type Error = ctap1::Error;
+
+    fn try_from(apdu: CommandView<'a>) -> ctap1::Result<Self> {
+        let cla = apdu.class().into_inner();
+        match (cla, apdu.instruction()) {
+            (0x00, Instruction::Select) => {
+                if apdu.p1 != 0x04 {
+                    return Err(ctap1::Error::IncorrectP1OrP2Parameter);
+                }
+                if apdu.data() != AID {
+                    return Err(ctap1::Error::NotFound);
+                }
+                Ok(Self::Select)
+            }
+            (0x00, Instruction::GetResponse) => Ok(Self::GetResponse),
+            (0x00, _) => ctap1::Request::try_from(apdu).map(Self::Ctap1),
+            (NFCCTAP_CLASS, Instruction::Unknown(NFCCTAP_MSG)) => Ok(Self::Ctap2 {
+                data: apdu.data(),
+                get_response_supported: apdu.p1 & 0x80 != 0,
+            }),
+            (NFCCTAP_CLASS, Instruction::Unknown(NFCCTAP_GETRESPONSE)) => {
+                Ok(Self::Ctap2GetResponse)
+            }
+            (NFCCTAP_CLASS, _) => Err(ctap1::Error::InstructionNotSupportedOrInvalid),
+            _ => Err(ctap1::Error::ClassNotSupported),
+        }
+    }
+}
+
+/// Writes an `NFCCTAP_MSG` command with the given serialized CTAP2 request into the buffer.
+///
+/// If `extended` is set, an extended length APDU is used, otherwise the request must not be
+/// longer than 255 bytes.  Returns [`Error::InvalidLength`][crate::Error::InvalidLength] if the
+/// request is empty or too long or if the command does not fit into the buffer.  If
+/// serialization fails, the buffer is restored to its previous length.
+pub fn write_msg_command<const N: usize>(
+    data: &[u8],
+    extended: bool,
+    buffer: &mut Bytes<N>,
+) -> crate::Result<()> {
+    let start = buffer.len();
+    let result = write_msg_command_parts(data, extended, buffer);
+    if result.is_err() {
+        buffer.truncate(start);
+    }
+    result
+}
+
+fn write_msg_command_parts<const N: usize>(
+    data: &[u8],
+    extended: bool,
+    buffer: &mut Bytes<N>,
+) -> crate::Result<()> {
+    // a request contains at least the command byte, and Lc must not be zero
+    if data.is_empty() {
+        return Err(crate::Error::InvalidLength);
+    }
+    let mut write = |data: &[u8]| {
+        buffer
+            .extend_from_slice(data)
+            .map_err(|_| crate::Error::InvalidLength)
+    };
+    write(&[NFCCTAP_CLASS, NFCCTAP_MSG, 0x00, 0x00])?;
+    if extended {
+        let len = u16::try_from(data.len()).map_err(|_| crate::Error::InvalidLength)?;
+        write(&[0x00])?;
+        write(&len.to_be_bytes())?;
+        write(data)?;
+        write(&[0x00, 0x00])
+    } else {
+        let len = u8::try_from(data.len()).map_err(|_| crate::Error::InvalidLength)?;
+        write(&[len])?;
+        write(data)?;
+        write(&[0x00])
+    }
+}
+
+/// An iterator over the parts of a response that is sent with response chaining.
+///
+/// Each item is a part of the response together with the status word that must be appended to
+/// it.  All parts except the last one use the status word `61XX`, where `XX` is the number of
+/// remaining bytes or `00` if 256 or more bytes are remaining.  The last part uses `9000`.  The
+/// first item is always returned, even for an empty response.
+#[derive(Clone, Debug)]
+pub struct ChainedResponse<'a> {
+    data: &'a [u8],
+    chunk_len: usize,
+    done: bool,
+}
+
+impl<'a> ChainedResponse<'a> {
+    /// Splits the given response into parts of at most `chunk_len` bytes.
+    ///
+    /// `chunk_len` is typically the expected length `Le` of the command, or 256 for short APDUs.
+    /// A zero `chunk_len` is treated as one.
+    pub fn new(data: &'a [u8], chunk_len: usize) -> Self {
+        Self {
+            data,
+            chunk_len: chunk_len.max(1),
+            done: false,
+        }
+    }
+
+    /// Returns the number of bytes that have not been returned yet.
+    pub fn remaining(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<'a> Iterator for ChainedResponse<'a> {
+    type Item = (&'a [u8], u16);
+
+    fn next(&mut self) -> Option<(&'a [u8], u16)> {
+        if self.done {
+            return None;
+        }
+        let (chunk, rest) = self.data.split_at(self.data.len().min(self.chunk_len));
+        self.data = rest;
+        let status = if rest.is_empty() {
+            self.done = true;
+            ctap1::NO_ERROR
+        } else {
+            0x6100 | u16::from(u8::try_from(rest.len()).unwrap_or(0))
+        };
+        Some((chunk, status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::Vec;
+    use iso7816::command::{class::Class, Command, CommandBuilder, ExpectedLen};
+
+    fn command(cla: u8, ins: u8, p1: u8, data: &[u8]) -> Command<1024> {
+        let builder = CommandBuilder::new(
+            Class::from_byte(cla).unwrap(),
+            Instruction::from(ins),
+            p1,
+            0x00,
+            data,
+            ExpectedLen::Max,
+        );
+        let mut apdu = Vec::<_, 1024>::new();
+        builder.serialize_into(&mut apdu).unwrap();
+        Command::try_from(&apdu).unwrap()
+    }
+
+    #[test]
+    fn test_request() {
+        let select = command(0x00, 0xa4, 0x04, &AID);
+        assert_eq!(Request::try_from(select.as_view()), Ok(Request::Select));
+        let select = command(0x00, 0xa4, 0x04, &AID[..7]);
+        assert_eq!(
+            Request::try_from(select.as_view()),
+            Err(ctap1::Error::NotFound)
+        );
+
+        let msg = command(0x80, 0x10, 0x80, &[0x04]);
+        assert_eq!(
+            Request::try_from(msg.as_view()),
+            Ok(Request::Ctap2 {
+                data: &[0x04],
+                get_response_supported: true,
+            })
+        );
+        let get_response = command(0x80, 0x11, 0x00, &[]);
+        assert_eq!(
+            Request::try_from(get_response.as_view()),
+            Ok(Request::Ctap2GetResponse)
+        );
+        let get_response = command(0x00, 0xc0, 0x00, &[]);
+        assert_eq!(
+            Request::try_from(get_response.as_view()),
+            Ok(Request::GetResponse)
+        );
+        let version = command(0x00, 0x03, 0x00, &[]);
+        assert_eq!(
+            Request::try_from(version.as_view()),
+            Ok(Request::Ctap1(ctap1::Request::Version))
+        );
+        let unknown = command(0x80, 0x12, 0x00, &[]);
+        assert_eq!(
+            Request::try_from(unknown.as_view()),
+            Err(ctap1::Error::InstructionNotSupportedOrInvalid)
+        );
+    }
+
+    #[test]
+    fn test_write_msg_command() {
+        let mut buffer = Bytes::<512>::new();
+        write_msg_command(&[0x04], false, &mut buffer).unwrap();
+        assert_eq!(
+            buffer.as_slice(),
+            [0x80, 0x10, 0x00, 0x00, 0x01, 0x04, 0x00]
+        );
+        let command = Command::<1024>::try_from(buffer.as_slice()).unwrap();
+        assert_eq!(
+            Request::try_from(command.as_view()),
+            Ok(Request::Ctap2 {
+                data: &[0x04],
+                get_response_supported: false,
+            })
+        );
+
+        buffer.clear();
+        let data = [0xab; 300];
+        assert_eq!(
+            write_msg_command(&data, false, &mut buffer),
+            Err(crate::Error::InvalidLength)
+        );
+        assert!(buffer.is_empty());
+        write_msg_command(&data, true, &mut buffer).unwrap();
+        assert_eq!(buffer[..7], [0x80, 0x10, 0x00, 0x00, 0x00, 0x01, 0x2c]);
+        assert_eq!(buffer[7..307], data);
+        assert_eq!(buffer[307..], [0x00, 0x00]);
+        let command = Command::<1024>::try_from(buffer.as_slice()).unwrap();
+        assert_eq!(&command.data()[..], &data[..]);
+
+        buffer.clear();
+        for extended in [false, true] {
+            assert_eq!(
+                write_msg_command(&[], extended, &mut buffer),
+                Err(crate::Error::InvalidLength)
+            );
+            assert!(buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_chained_response() {
+        let data = [0xab; 600];
+        let parts: std::vec::Vec<_> = ChainedResponse::new(&data, 256).collect();
+        assert_eq!(
+            parts,
+            [
+                (&data[..256], 0x6100),
+                (&data[256..512], 0x6158),
+                (&data[512..], 0x9000),
+            ]
+        );
+
+        let mut parts = ChainedResponse::new(&[], 256);
+        assert_eq!(parts.next(), Some((&[][..], 0x9000)));
+        assert_eq!(parts.next(), None);
+    }
+}
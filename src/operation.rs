@@ -1,3 +1,5 @@
+use serde::{de::Unexpected, Deserialize, Deserializer, Serialize, Serializer};
+
 /// the authenticator API, consisting of "operations"
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
@@ -48,6 +50,29 @@ impl Operation {
     }
 }
 
+impl core::fmt::Display for Operation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use Operation::*;
+        let name = match self {
+            MakeCredential => "authenticatorMakeCredential",
+            GetAssertion => "authenticatorGetAssertion",
+            GetNextAssertion => "authenticatorGetNextAssertion",
+            GetInfo => "authenticatorGetInfo",
+            ClientPin => "authenticatorClientPIN",
+            Reset => "authenticatorReset",
+            BioEnrollment => "authenticatorBioEnrollment",
+            CredentialManagement => "authenticatorCredentialManagement",
+            Selection => "authenticatorSelection",
+            LargeBlobs => "authenticatorLargeBlobs",
+            Config => "authenticatorConfig",
+            PreviewBioEnrollment => "authenticatorBioEnrollment (preview)",
+            PreviewCredentialManagement => "authenticatorCredentialManagement (preview)",
+            Vendor(operation) => return write!(f, "vendor operation {:#04x}", operation.0),
+        };
+        f.write_str(name)
+    }
+}
+
 /// Vendor CTAP2 operations, from 0x40 to 0x7f.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
@@ -101,3 +126,63 @@ impl TryFrom<u8> for Operation {
         })
     }
 }
+
+impl Serialize for Operation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.into_u8())
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u8::deserialize(deserializer)?;
+        Self::try_from(value).map_err(|_| {
+            serde::de::Error::invalid_value(
+                Unexpected::Unsigned(value.into()),
+                &"a CTAP2 operation",
+            )
+        })
+    }
+}
+
+impl Serialize for VendorOperation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for VendorOperation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u8::deserialize(deserializer)?;
+        Self::try_from(value).map_err(|_| {
+            serde::de::Error::invalid_value(
+                Unexpected::Unsigned(value.into()),
+                &"a vendor operation in the range 0x40..=0x7f",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    #[test]
+    fn test_serde_operation() {
+        assert_tokens(&Operation::MakeCredential, &[Token::U8(0x01)]);
+        assert_tokens(&Operation::PreviewBioEnrollment, &[Token::U8(0x40)]);
+        let vendor = VendorOperation::try_from(0x42).unwrap();
+        assert_tokens(&Operation::Vendor(vendor), &[Token::U8(0x42)]);
+        assert_tokens(&vendor, &[Token::U8(0x42)]);
+
+        assert_de_tokens_error::<Operation>(
+            &[Token::U8(0x03)],
+            "invalid value: integer `3`, expected a CTAP2 operation",
+        );
+        assert_de_tokens_error::<VendorOperation>(
+            &[Token::U8(0x01)],
+            "invalid value: integer `1`, expected a vendor operation in the range 0x40..=0x7f",
+        );
+    }
+}
@@ -4,6 +4,41 @@ pub const AUTHENTICATOR_DATA_LENGTH: usize = 676;
 pub const ASN1_SIGNATURE_LENGTH: usize = 77;
 // pub const ASN1_SIGNATURE_LENGTH_BYTES: usize = 72;
 
+/// Max length of signatures in attestation statements and assertions.
+///
+/// By default, this is [`ASN1_SIGNATURE_LENGTH`][], the size of a DER-encoded ECDSA P-256
+/// signature.  Larger signatures can be enabled with the `rsa-signatures` feature (RSA-4096,
+/// 512 bytes) and the `ml-dsa-signatures` feature (ML-DSA-87, 4627 bytes).
+#[cfg(not(any(feature = "rsa-signatures", feature = "ml-dsa-signatures")))]
+pub const MAX_SIGNATURE_LENGTH: usize = ASN1_SIGNATURE_LENGTH;
+#[cfg(all(feature = "rsa-signatures", not(feature = "ml-dsa-signatures")))]
+pub const MAX_SIGNATURE_LENGTH: usize = 512;
+#[cfg(feature = "ml-dsa-signatures")]
+pub const MAX_SIGNATURE_LENGTH: usize = 4627;
+
+/// Max length of a DER-encoded certificate in an attestation certificate chain (`x5c`).
+pub const MAX_ATTESTATION_CERTIFICATE_LENGTH: usize = 1024;
+
+/// Max number of certificates in an attestation certificate chain (`x5c`).
+///
+/// By default, only the attestation certificate itself can be included.  If the
+/// `attestation-certificate-chains` feature is enabled, up to three intermediate CA certificates
+/// can be added, for example for enterprise attestation.
+#[cfg(not(feature = "attestation-certificate-chains"))]
+pub const MAX_ATTESTATION_CERTIFICATE_CHAIN_LENGTH: usize = 1;
+#[cfg(feature = "attestation-certificate-chains")]
+pub const MAX_ATTESTATION_CERTIFICATE_CHAIN_LENGTH: usize = 4;
+
+/// Max number of entries stored for the attestation formats preference.
+pub const MAX_ATTESTATION_FORMATS_PREFERENCE_LENGTH: usize = 8;
+
+/// Max length of the signature in a `tpm` attestation statement (RSA-2048).
+pub const TPM_SIGNATURE_LENGTH: usize = 256;
+/// Max length of the `TPMS_ATTEST` structure in a `tpm` attestation statement.
+pub const TPM_CERT_INFO_LENGTH: usize = 256;
+/// Max length of the `TPMT_PUBLIC` structure in a `tpm` attestation statement.
+pub const TPM_PUB_AREA_LENGTH: usize = 512;
+
 pub const COSE_KEY_LENGTH: usize = 256;
 // pub const COSE_KEY_LENGTH_BYTES: usize = 256;
 
@@ -11,6 +46,17 @@ pub const MAX_CREDENTIAL_ID_LENGTH: usize = 255;
 pub const MAX_CREDENTIAL_ID_LENGTH_PLUS_256: usize = 767;
 pub const MAX_CREDENTIAL_COUNT_IN_LIST: usize = 10;
 
+/// Max number of entries stored for the public key credential parameters of a makeCredential
+/// request.
+pub const MAX_PUBLIC_KEY_CREDENTIAL_PARAMETERS: usize = 10;
+
+/// Max length of a credBlob, i.e. the minimum value of `maxCredBlobLength` required by the
+/// specification.
+pub const MAX_CRED_BLOB_LENGTH: usize = 32;
+
+/// Max length of the version string returned by a CTAP1 authenticator.
+pub const MAX_U2F_VERSION_LENGTH: usize = 32;
+
 pub const PACKET_SIZE: usize = 64;
 
 // 7609 bytes
@@ -21,11 +67,13 @@ pub const THEORETICAL_MAX_MESSAGE_SIZE: usize = PACKET_SIZE - 7 + 128 * (PACKET_
 /// Max length for a large blob fragment, according to
 /// https://fidoalliance.org/specs/fido-v2.1-ps-20210615/fido-client-to-authenticator-protocol-v2.1-ps-20210615.html#largeBlobsRW
 ///
-/// This constant determines the buffer size in [`ctap2::large_blobs::Response`][].  Ideally, this
-/// would be configurable.  Currently, this is not possible.  To keep the stack usage low if the
-/// extension is not used, this constant defaults to zero. For compatibility with the max message
-/// size in usbd-ctaphid (used by solo2 and nitrokey-3-firmware), it is set to 3072 - 64 =
-/// 3008 if the `large-blobs` feature is enabled.
+/// This constant determines the default buffer size in [`ctap2::large_blobs::Response`][], which
+/// is used in [`ctap2::Response`][].  Authenticators that need a different fragment length can
+/// set the const generic parameter of [`ctap2::large_blobs::Response`][] or use
+/// [`ctap2::large_blobs::ResponseRef`][] with [`ctap2::ResponseRef`][].  To keep the stack usage low if the extension is not
+/// used, this constant defaults to zero. For compatibility with the max message size in
+/// usbd-ctaphid (used by solo2 and nitrokey-3-firmware), it is set to 3072 - 64 = 3008 if the
+/// `large-blobs` feature is enabled.
 #[cfg(not(feature = "large-blobs"))]
 pub const LARGE_BLOB_MAX_FRAGMENT_LENGTH: usize = 0;
 #[cfg(feature = "large-blobs")]
@@ -1,18 +1,55 @@
 //! Subset of WebAuthn types that crept into CTAP.
 
 use crate::sizes::*;
-use crate::{Bytes, String};
-use serde::{de::Deserializer, Deserialize, Serialize};
+use crate::{Bytes, String, TryFromStrError, Vec};
+use core::fmt::{self, Formatter};
 
+use serde::{
+    de::{Deserializer, SeqAccess, Visitor},
+    Deserialize, Serialize,
+};
+
+#[cfg(feature = "client-data")]
+mod client_data;
+
+#[cfg(feature = "client-data")]
+pub use client_data::{ClientDataType, CollectedClientData};
+
+/// Authenticator data parsed from its serialized form, e.g. from the `authData` of an attestation
+/// object.
+///
+/// The extension outputs are kept as a raw CBOR map.  See the methods of
+/// [`AuthenticatorData`][crate::ctap2::AuthenticatorData] for typed access to the outputs of known
+/// extensions, for example [`AuthenticatorData::cred_protect`][crate::ctap2::AuthenticatorData::cred_protect].
+pub type ParsedAuthenticatorData<'a> = crate::ctap2::AuthenticatorData<
+    'a,
+    crate::ctap2::make_credential::AttestedCredentialData<'a>,
+    &'a [u8],
+>;
+
+/// The default maximum length of the `name` and `displayName` members of
+/// [`PublicKeyCredentialRpEntity`][] and [`PublicKeyCredentialUserEntity`][].
+///
+/// Longer values are truncated.  This is the minimum length that authenticators must support.
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 64;
+
+/// The default maximum length of the `icon` member of [`PublicKeyCredentialUserEntity`][].
+///
+/// Longer values are skipped.
+pub const DEFAULT_MAX_ICON_LENGTH: usize = 128;
+
+/// The RP entity of a request.
+///
+/// The name is truncated to `N` bytes, see [`DEFAULT_MAX_NAME_LENGTH`][].
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct PublicKeyCredentialRpEntity {
+pub struct PublicKeyCredentialRpEntity<const N: usize = DEFAULT_MAX_NAME_LENGTH> {
     pub id: String<256>,
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
         deserialize_with = "deserialize_from_str_and_truncate"
     )]
-    pub name: Option<String<64>>,
+    pub name: Option<String<N>>,
     /// This field has been removed in Webauthn 2 but CTAP 2.2 requires implementors to accept it.
     ///
     /// The content of this field must not be stored.  Therefore we use the [`Icon`][] helper type.
@@ -24,11 +61,117 @@ pub struct PublicKeyCredentialRpEntity {
     pub icon: Option<Icon>,
 }
 
+/// Same as [`PublicKeyCredentialRpEntity`][] but which deserializes using references.
+///
+/// The length of the ID is not restricted and the name is only truncated when converting into a
+/// [`PublicKeyCredentialRpEntity`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct PublicKeyCredentialRpEntityRef<'a> {
+    pub id: &'a str,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    /// See [`PublicKeyCredentialRpEntity::icon`][].
+    #[serde(skip_serializing, alias = "url")]
+    pub icon: Option<Icon>,
+}
+
+impl<'a, const N: usize> From<&'a PublicKeyCredentialRpEntity<N>>
+    for PublicKeyCredentialRpEntityRef<'a>
+{
+    fn from(rp: &'a PublicKeyCredentialRpEntity<N>) -> Self {
+        Self {
+            id: &rp.id,
+            name: rp.name.as_deref(),
+            icon: rp.icon,
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<PublicKeyCredentialRpEntityRef<'_>>
+    for PublicKeyCredentialRpEntity<N>
+{
+    type Error = crate::ctap2::Error;
+
+    /// Copies the RP entity, truncating the name, see [`PublicKeyCredentialRpEntity::from_ref`][].
+    fn try_from(rp: PublicKeyCredentialRpEntityRef<'_>) -> Result<Self, Self::Error> {
+        Self::from_ref(rp, StringLengthPolicy::Truncate)
+    }
+}
+
+impl<const N: usize> PublicKeyCredentialRpEntity<N> {
+    /// Copies the RP entity, handling a name that is longer than `N` bytes according to the given
+    /// policy.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength] if the ID is longer
+    /// than 256 bytes.
+    pub fn from_ref(
+        rp: PublicKeyCredentialRpEntityRef<'_>,
+        policy: StringLengthPolicy,
+    ) -> Result<Self, crate::ctap2::Error> {
+        let mut id = String::new();
+        id.push_str(rp.id)
+            .map_err(|_| crate::ctap2::Error::InvalidLength)?;
+        Ok(Self {
+            id,
+            name: policy.apply(rp.name)?,
+            icon: rp.icon,
+        })
+    }
+}
+
+/// Determines how strings that are longer than the maximum length of the target type are handled
+/// when copying a borrowed entity, e.g. with [`PublicKeyCredentialUserEntity::from_ref`][].
+///
+/// CTAP 2.1 allows authenticators to either truncate or reject such strings.  Requests are parsed
+/// into borrowed entities without any length restriction so that the authenticator can choose the
+/// policy when storing them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum StringLengthPolicy {
+    /// Truncate the string at a character boundary.
+    ///
+    /// As truncated icon URLs are not meaningful, icons are skipped instead.
+    #[default]
+    Truncate,
+    /// Skip the field.
+    Skip,
+    /// Reject the entity with [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength].
+    Reject,
+}
+
+impl StringLengthPolicy {
+    fn apply<const L: usize>(
+        self,
+        s: Option<&str>,
+    ) -> Result<Option<String<L>>, crate::ctap2::Error> {
+        match s {
+            Some(s) if s.len() > L => match self {
+                Self::Truncate => Ok(Some(truncate(s))),
+                Self::Skip => Ok(None),
+                Self::Reject => Err(crate::ctap2::Error::InvalidLength),
+            },
+            // the string fits, so truncate does not modify it
+            s => Ok(s.map(truncate)),
+        }
+    }
+
+    fn apply_to_icon<const L: usize>(
+        self,
+        icon: Option<&str>,
+    ) -> Result<Option<String<L>>, crate::ctap2::Error> {
+        match self {
+            Self::Truncate => Self::Skip.apply(icon),
+            policy => policy.apply(icon),
+        }
+    }
+}
+
 /// Helper type for the `icon` field of [`PublicKeyCredentialRpEntity`][].
 ///
 /// This field must be parsed but not used or stored.  Therefore this wrapper type can be
 /// deserialized from a string but does not store any data.
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Icon;
 
@@ -42,28 +185,106 @@ impl<'de> Deserialize<'de> for Icon {
     }
 }
 
+/// A user handle, i.e. the `id` of a [`PublicKeyCredentialUserEntity`][].
+///
+/// User handles have at least one and at most 64 bytes, which is validated on construction.  As
+/// the user handle identifies the user account, the [`Debug`][fmt::Debug] implementation only
+/// shows its length.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Bytes<64>", try_from = "Bytes<64>")]
+pub struct UserHandle(Bytes<64>);
+
+impl UserHandle {
+    /// Copies the given user handle.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength] if it is empty or
+    /// longer than 64 bytes.
+    pub fn from_slice(id: &[u8]) -> Result<Self, crate::ctap2::Error> {
+        Bytes::from_slice(id)
+            .map_err(|_| crate::ctap2::Error::InvalidLength)?
+            .try_into()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Decodes an unpadded base64url-encoded user handle.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength] if it is empty or
+    /// longer than 64 bytes.
+    #[cfg(feature = "base64url")]
+    pub fn from_base64url(s: &str) -> Result<Self, crate::ctap2::Error> {
+        crate::base64url::decode::<64>(s)?.try_into()
+    }
+
+    /// Encodes this user handle as unpadded base64url.
+    #[cfg(feature = "base64url")]
+    pub fn to_base64url<const M: usize>(&self) -> Result<String<M>, crate::ctap2::Error> {
+        crate::base64url::encode(&self.0)
+    }
+}
+
+impl AsRef<[u8]> for UserHandle {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for UserHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("UserHandle")
+            .field(&format_args!("<{} bytes>", self.0.len()))
+            .finish()
+    }
+}
+
+impl TryFrom<Bytes<64>> for UserHandle {
+    type Error = crate::ctap2::Error;
+
+    fn try_from(id: Bytes<64>) -> Result<Self, Self::Error> {
+        if id.is_empty() {
+            return Err(crate::ctap2::Error::InvalidLength);
+        }
+        Ok(Self(id))
+    }
+}
+
+impl From<UserHandle> for Bytes<64> {
+    fn from(id: UserHandle) -> Self {
+        id.0
+    }
+}
+
+/// The user entity of a request.
+///
+/// The name and the display name are truncated to `N` bytes, see [`DEFAULT_MAX_NAME_LENGTH`][].
+/// An icon that is longer than `I` bytes is skipped, see [`DEFAULT_MAX_ICON_LENGTH`][].
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct PublicKeyCredentialUserEntity {
-    pub id: Bytes<64>,
+pub struct PublicKeyCredentialUserEntity<
+    const N: usize = DEFAULT_MAX_NAME_LENGTH,
+    const I: usize = DEFAULT_MAX_ICON_LENGTH,
+> {
+    pub id: UserHandle,
     #[serde(
         default,
         deserialize_with = "deserialize_from_str_and_skip_if_too_long"
     )]
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub icon: Option<String<128>>,
+    pub icon: Option<String<I>>,
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
         deserialize_with = "deserialize_from_str_and_truncate"
     )]
-    pub name: Option<String<64>>,
+    pub name: Option<String<N>>,
     #[serde(
         default,
         skip_serializing_if = "Option::is_none",
         deserialize_with = "deserialize_from_str_and_truncate"
     )]
-    pub display_name: Option<String<64>>,
+    pub display_name: Option<String<N>>,
 }
 
 fn deserialize_from_str_and_skip_if_too_long<'de, D, const L: usize>(
@@ -125,7 +346,7 @@ const fn is_utf8_char_boundary(b: u8) -> bool {
 }
 
 impl PublicKeyCredentialUserEntity {
-    pub fn from(id: Bytes<64>) -> Self {
+    pub fn from(id: UserHandle) -> Self {
         Self {
             id,
             icon: None,
@@ -135,6 +356,90 @@ impl PublicKeyCredentialUserEntity {
     }
 }
 
+/// Same as [`PublicKeyCredentialUserEntity`][] but which deserializes using references.
+///
+/// The user handle is validated when parsing.  The name and the display name are only truncated
+/// and a too long icon is only skipped when converting into a [`PublicKeyCredentialUserEntity`][].
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKeyCredentialUserEntityRef<'a> {
+    #[serde(deserialize_with = "deserialize_user_handle")]
+    pub id: &'a serde_bytes::Bytes,
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<&'a str>,
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub name: Option<&'a str>,
+    #[serde(default, borrow, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<&'a str>,
+}
+
+impl fmt::Debug for PublicKeyCredentialUserEntityRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // the user handle is not shown, see UserHandle
+        f.debug_struct("PublicKeyCredentialUserEntityRef")
+            .field("id", &format_args!("<{} bytes>", self.id.len()))
+            .field("icon", &self.icon)
+            .field("name", &self.name)
+            .field("display_name", &self.display_name)
+            .finish()
+    }
+}
+
+impl<'a, const N: usize, const I: usize> From<&'a PublicKeyCredentialUserEntity<N, I>>
+    for PublicKeyCredentialUserEntityRef<'a>
+{
+    fn from(user: &'a PublicKeyCredentialUserEntity<N, I>) -> Self {
+        Self {
+            id: serde_bytes::Bytes::new(user.id.as_bytes()),
+            icon: user.icon.as_deref(),
+            name: user.name.as_deref(),
+            display_name: user.display_name.as_deref(),
+        }
+    }
+}
+
+impl<const N: usize, const I: usize> TryFrom<PublicKeyCredentialUserEntityRef<'_>>
+    for PublicKeyCredentialUserEntity<N, I>
+{
+    type Error = crate::ctap2::Error;
+
+    /// Copies the user entity, truncating the name and the display name and skipping the icon,
+    /// see [`PublicKeyCredentialUserEntity::from_ref`][].
+    fn try_from(user: PublicKeyCredentialUserEntityRef<'_>) -> Result<Self, Self::Error> {
+        Self::from_ref(user, StringLengthPolicy::Truncate)
+    }
+}
+
+impl<const N: usize, const I: usize> PublicKeyCredentialUserEntity<N, I> {
+    /// Copies the user entity, handling a name or display name that is longer than `N` bytes and
+    /// an icon that is longer than `I` bytes according to the given policy.
+    pub fn from_ref(
+        user: PublicKeyCredentialUserEntityRef<'_>,
+        policy: StringLengthPolicy,
+    ) -> Result<Self, crate::ctap2::Error> {
+        Ok(Self {
+            id: UserHandle::from_slice(user.id)?,
+            icon: policy.apply_to_icon(user.icon)?,
+            name: policy.apply(user.name)?,
+            display_name: policy.apply(user.display_name)?,
+        })
+    }
+}
+
+fn deserialize_user_handle<'de, D>(deserializer: D) -> Result<&'de serde_bytes::Bytes, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let id: &'de serde_bytes::Bytes = Deserialize::deserialize(deserializer)?;
+    if id.is_empty() || id.len() > 64 {
+        return Err(serde::de::Error::invalid_length(
+            id.len(),
+            &"a user handle with 1 to 64 bytes",
+        ));
+    }
+    Ok(id)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct KnownPublicKeyCredentialParameters {
     pub alg: i32,
@@ -158,6 +463,14 @@ pub enum UnknownPKCredentialParam {
 pub const ES256: i32 = -7;
 /// EdDSA
 pub const ED_DSA: i32 = -8;
+/// ECDSA w/ SHA-384
+pub const ES384: i32 = -35;
+/// RSASSA-PKCS1-v1_5 w/ SHA-256
+pub const RS256: i32 = -257;
+/// ML-DSA-44 (draft identifier, see the `ml-dsa-signatures` feature)
+pub const ML_DSA_44: i32 = -48;
+/// ML-DSA-65 (draft identifier, see the `ml-dsa-signatures` feature)
+pub const ML_DSA_65: i32 = -49;
 
 pub const COUNT_KNOWN_ALGS: usize = 2;
 pub const KNOWN_ALGS: [i32; COUNT_KNOWN_ALGS] = [ES256, ED_DSA];
@@ -176,11 +489,82 @@ impl TryFrom<PublicKeyCredentialParameters> for KnownPublicKeyCredentialParamete
     }
 }
 
-/// Struct of filtered PublicKeyCredentialParameters, that drops unknown algorithms while parsing
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct FilteredPublicKeyCredentialParameters(
-    pub heapless::Vec<KnownPublicKeyCredentialParameters, COUNT_KNOWN_ALGS>,
-);
+type ParameterList =
+    heapless::Vec<KnownPublicKeyCredentialParameters, MAX_PUBLIC_KEY_CREDENTIAL_PARAMETERS>;
+
+/// Struct of filtered PublicKeyCredentialParameters, that drops unknown types and duplicate
+/// algorithms while parsing.
+///
+/// The algorithms are not filtered while parsing as the supported algorithms depend on the
+/// authenticator.  Use [`filter`][Self::filter] or [`select`][Self::select] with the algorithms
+/// supported by the authenticator, for example [`KNOWN_ALGS`][].  At most
+/// [`MAX_PUBLIC_KEY_CREDENTIAL_PARAMETERS`][] entries are stored.  Further entries are dropped.
+///
+/// The parameters are stored in the order of preference of the RP.  Whether entries were dropped
+/// because of an unknown type can be checked with [`had_unknown`][Self::had_unknown], whether
+/// entries were dropped because there were too many with [`truncated`][Self::truncated].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FilteredPublicKeyCredentialParameters {
+    pub(crate) parameters: ParameterList,
+    pub(crate) had_unknown: bool,
+    pub(crate) truncated: bool,
+}
+
+impl FilteredPublicKeyCredentialParameters {
+    /// Returns the parameters in the order of preference of the RP.
+    pub fn parameters(&self) -> &[KnownPublicKeyCredentialParameters] {
+        &self.parameters
+    }
+
+    /// Returns the algorithms in the order of preference of the RP.
+    pub fn algorithms(&self) -> impl Iterator<Item = i32> + '_ {
+        self.parameters.iter().map(|parameters| parameters.alg)
+    }
+
+    /// Returns true if entries with an unknown type were dropped while parsing.
+    ///
+    /// Duplicate algorithms are not considered as unknown.
+    pub fn had_unknown(&self) -> bool {
+        self.had_unknown
+    }
+
+    /// Returns true if entries were dropped while parsing because there were more than
+    /// [`MAX_PUBLIC_KEY_CREDENTIAL_PARAMETERS`][] different algorithms.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns the parameters with an algorithm contained in `supported` in the order of
+    /// preference of the RP.
+    pub fn filter<'a>(
+        &'a self,
+        supported: &'a [i32],
+    ) -> impl Iterator<Item = &'a KnownPublicKeyCredentialParameters> + 'a {
+        self.parameters
+            .iter()
+            .filter(move |parameters| supported.contains(&parameters.alg))
+    }
+
+    /// Selects the algorithm most preferred by the RP that is contained in `supported`.
+    ///
+    /// Returns `None` if none of the algorithms is supported.  In this case, the authenticator
+    /// should return [`Error::UnsupportedAlgorithm`][crate::ctap2::Error::UnsupportedAlgorithm].
+    pub fn select(&self, supported: &[i32]) -> Option<i32> {
+        self.filter(supported)
+            .next()
+            .map(|parameters| parameters.alg)
+    }
+}
+
+impl From<ParameterList> for FilteredPublicKeyCredentialParameters {
+    fn from(parameters: ParameterList) -> Self {
+        Self {
+            parameters,
+            had_unknown: false,
+            truncated: false,
+        }
+    }
+}
 
 impl Serialize for FilteredPublicKeyCredentialParameters {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -188,8 +572,8 @@ impl Serialize for FilteredPublicKeyCredentialParameters {
         S: serde::Serializer,
     {
         use serde::ser::SerializeSeq;
-        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
-        for element in &self.0 {
+        let mut seq = serializer.serialize_seq(Some(self.parameters.len()))?;
+        for element in &self.parameters {
             let el: PublicKeyCredentialParameters = element.clone().into();
             seq.serialize_element(&el)?
         }
@@ -214,15 +598,22 @@ impl<'de> Deserialize<'de> for FilteredPublicKeyCredentialParameters {
             where
                 A: serde::de::SeqAccess<'de>,
             {
-                let mut values = FilteredPublicKeyCredentialParameters(Default::default());
+                let mut values = FilteredPublicKeyCredentialParameters::default();
                 while let Some(value) = seq.next_element::<PublicKeyCredentialParameters>()? {
-                    let Ok(el) = value.try_into() else {
-                        // Drop unknown algorithms
+                    if value.key_type != "public-key" {
+                        // Drop unknown types
+                        values.had_unknown = true;
+                        continue;
+                    }
+                    let el = KnownPublicKeyCredentialParameters { alg: value.alg };
+                    if values.parameters.contains(&el) {
                         continue;
-                    };
-                    // We drop too many elements. This shouldn't happen as we have enough space for all known algorithms.
-                    // This can only happen in case of duplicates.
-                    values.0.push(el).ok();
+                    }
+                    // We drop too many elements.  The algorithms most preferred by the RP are
+                    // kept.
+                    if values.parameters.push(el).is_err() {
+                        values.truncated = true;
+                    }
                 }
                 Ok(values)
             }
@@ -232,6 +623,123 @@ impl<'de> Deserialize<'de> for FilteredPublicKeyCredentialParameters {
     }
 }
 
+macro_rules! hash_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+        #[repr(transparent)]
+        pub struct $name([u8; 32]);
+
+        impl $name {
+            pub const fn new(hash: [u8; 32]) -> Self {
+                Self(hash)
+            }
+
+            /// Wraps a reference to a hash without copying it.
+            pub fn from_ref(hash: &[u8; 32]) -> &Self {
+                // SAFETY: this type is a transparent wrapper around [u8; 32]
+                unsafe { &*(hash as *const [u8; 32] as *const Self) }
+            }
+
+            pub const fn as_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+
+            pub const fn into_array(self) -> [u8; 32] {
+                self.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; 32]> for $name {
+            fn from(hash: [u8; 32]) -> Self {
+                Self(hash)
+            }
+        }
+
+        impl From<$name> for [u8; 32] {
+            fn from(hash: $name) -> Self {
+                hash.0
+            }
+        }
+
+        impl<'a> From<&'a [u8; 32]> for &'a $name {
+            fn from(hash: &'a [u8; 32]) -> Self {
+                $name::from_ref(hash)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = crate::ctap2::Error;
+
+            fn try_from(hash: &[u8]) -> Result<Self, Self::Error> {
+                hash.try_into()
+                    .map(Self)
+                    .map_err(|_| crate::ctap2::Error::InvalidLength)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                serde_bytes::ByteArray::<32>::deserialize(deserializer)
+                    .map(|hash| Self(hash.into_array()))
+            }
+        }
+
+        impl<'de: 'a, 'a> Deserialize<'de> for &'a $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                <&serde_bytes::ByteArray<32>>::deserialize(deserializer)
+                    .map(|hash| $name::from_ref(hash))
+            }
+        }
+    };
+}
+
+hash_type! {
+    /// The SHA-256 hash of an RP ID, or the application parameter of a CTAP1 request.
+    ///
+    /// It is serialized as a 32 byte string.
+    RpIdHash
+}
+
+hash_type! {
+    /// The SHA-256 hash of the client data, or the challenge parameter of a CTAP1 request.
+    ///
+    /// It is serialized as a 32 byte string.
+    ClientDataHash
+}
+
+impl ClientDataHash {
+    /// Computes the client data hash of the given client data JSON.
+    #[cfg(feature = "sha2")]
+    pub fn compute(client_data_json: &[u8]) -> Self {
+        use sha2::{Digest, Sha256};
+
+        Self::new(Sha256::digest(client_data_json).into())
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct PublicKeyCredentialParameters {
     pub alg: i32,
@@ -248,33 +756,984 @@ impl PublicKeyCredentialParameters {
     }
 }
 
+/// A credential ID with a maximum length of `N` bytes.
+///
+/// The format of the credential ID is up to the authenticator.  The length is validated on
+/// construction, see [`CredentialIdRef`][] for a borrowed variant.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Bytes<N>", try_from = "Bytes<N>")]
+pub struct CredentialId<const N: usize = MAX_CREDENTIAL_ID_LENGTH>(Bytes<N>);
+
+impl<const N: usize> CredentialId<N> {
+    /// Copies the given credential ID.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength] if it is longer
+    /// than `N` bytes.
+    pub fn from_slice(id: &[u8]) -> Result<Self, crate::ctap2::Error> {
+        Bytes::from_slice(id)
+            .map(Self)
+            .map_err(|_| crate::ctap2::Error::InvalidLength)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns a borrowed view of this credential ID.
+    pub fn as_id_ref(&self) -> CredentialIdRef<'_> {
+        CredentialIdRef(&self.0)
+    }
+
+    /// Decodes an unpadded base64url-encoded credential ID.
+    ///
+    /// Returns [`Error::InvalidLength`][crate::ctap2::Error::InvalidLength] if it is longer
+    /// than `N` bytes.
+    #[cfg(feature = "base64url")]
+    pub fn from_base64url(s: &str) -> Result<Self, crate::ctap2::Error> {
+        crate::base64url::decode(s).map(Self)
+    }
+
+    /// Encodes this credential ID as unpadded base64url.
+    #[cfg(feature = "base64url")]
+    pub fn to_base64url<const M: usize>(&self) -> Result<String<M>, crate::ctap2::Error> {
+        self.as_id_ref().to_base64url()
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for CredentialId<N> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const N: usize> From<Bytes<N>> for CredentialId<N> {
+    fn from(id: Bytes<N>) -> Self {
+        Self(id)
+    }
+}
+
+impl<const N: usize> From<CredentialId<N>> for Bytes<N> {
+    fn from(id: CredentialId<N>) -> Self {
+        id.0
+    }
+}
+
+impl<const N: usize> TryFrom<CredentialIdRef<'_>> for CredentialId<N> {
+    type Error = crate::ctap2::Error;
+
+    fn try_from(id: CredentialIdRef<'_>) -> Result<Self, Self::Error> {
+        Self::from_slice(id.0)
+    }
+}
+
+/// A borrowed credential ID, see [`CredentialId`][].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CredentialIdRef<'a>(&'a [u8]);
+
+impl<'a> CredentialIdRef<'a> {
+    pub const fn new(id: &'a [u8]) -> Self {
+        Self(id)
+    }
+
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Encodes this credential ID as unpadded base64url.
+    #[cfg(feature = "base64url")]
+    pub fn to_base64url<const M: usize>(&self) -> Result<String<M>, crate::ctap2::Error> {
+        crate::base64url::encode(self.0)
+    }
+}
+
+impl AsRef<[u8]> for CredentialIdRef<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl<'a> From<&'a [u8]> for CredentialIdRef<'a> {
+    fn from(id: &'a [u8]) -> Self {
+        Self(id)
+    }
+}
+
+impl<'a, const N: usize> From<&'a CredentialId<N>> for CredentialIdRef<'a> {
+    fn from(id: &'a CredentialId<N>) -> Self {
+        id.as_id_ref()
+    }
+}
+
+impl Serialize for CredentialIdRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for CredentialIdRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let id: &'de serde_bytes::Bytes = Deserialize::deserialize(deserializer)?;
+        Ok(Self(id))
+    }
+}
+
+/// A transport that can be used to communicate with an authenticator.
+///
+/// See: https://w3c.github.io/webauthn/#enumdef-authenticatortransport
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[serde(into = "&str", try_from = "&str")]
+pub enum AuthenticatorTransport {
+    Usb,
+    Nfc,
+    Ble,
+    SmartCard,
+    Hybrid,
+    Internal,
+}
+
+impl AuthenticatorTransport {
+    const USB: &'static str = "usb";
+    const NFC: &'static str = "nfc";
+    const BLE: &'static str = "ble";
+    const SMART_CARD: &'static str = "smart-card";
+    const HYBRID: &'static str = "hybrid";
+    const INTERNAL: &'static str = "internal";
+}
+
+impl From<AuthenticatorTransport> for &str {
+    fn from(transport: AuthenticatorTransport) -> Self {
+        match transport {
+            AuthenticatorTransport::Usb => AuthenticatorTransport::USB,
+            AuthenticatorTransport::Nfc => AuthenticatorTransport::NFC,
+            AuthenticatorTransport::Ble => AuthenticatorTransport::BLE,
+            AuthenticatorTransport::SmartCard => AuthenticatorTransport::SMART_CARD,
+            AuthenticatorTransport::Hybrid => AuthenticatorTransport::HYBRID,
+            AuthenticatorTransport::Internal => AuthenticatorTransport::INTERNAL,
+        }
+    }
+}
+
+impl TryFrom<&str> for AuthenticatorTransport {
+    type Error = TryFromStrError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            Self::USB => Ok(Self::Usb),
+            Self::NFC => Ok(Self::Nfc),
+            Self::BLE => Ok(Self::Ble),
+            Self::SMART_CARD => Ok(Self::SmartCard),
+            Self::HYBRID => Ok(Self::Hybrid),
+            Self::INTERNAL => Ok(Self::Internal),
+            _ => Err(TryFromStrError),
+        }
+    }
+}
+
+/// The maximum number of transports of a credential descriptor, i.e. the number of known
+/// [`AuthenticatorTransport`][]s.
+pub const MAX_TRANSPORTS: usize = 6;
+
+/// Deserializes the `transports` member of a credential descriptor.
+///
+/// Unknown transports are skipped as required by the WebAuthn specification, and so are
+/// duplicates.
+fn deserialize_transports<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<AuthenticatorTransport, MAX_TRANSPORTS>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct TransportsVisitor;
+
+    impl<'de> Visitor<'de> for TransportsVisitor {
+        type Value = Vec<AuthenticatorTransport, MAX_TRANSPORTS>;
+
+        fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a sequence of transports")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut transports = Vec::new();
+            while let Some(transport) = seq.next_element::<&'de str>()? {
+                if let Ok(transport) = AuthenticatorTransport::try_from(transport) {
+                    if !transports.contains(&transport) {
+                        // cannot fail as there are only MAX_TRANSPORTS different transports
+                        transports.push(transport).ok();
+                    }
+                }
+            }
+            Ok(transports)
+        }
+    }
+
+    deserializer.deserialize_seq(TransportsVisitor).map(Some)
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PublicKeyCredentialDescriptor {
-    // NB: if this is too small, get a nasty error
-    // See serde::error/custom for more info
-    pub id: Bytes<MAX_CREDENTIAL_ID_LENGTH>,
+    pub id: CredentialId,
     #[serde(rename = "type")]
     pub key_type: String<32>,
-    // https://w3c.github.io/webauthn/#enumdef-authenticatortransport
-    // transports: ...
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_transports"
+    )]
+    pub transports: Option<Vec<AuthenticatorTransport, MAX_TRANSPORTS>>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 /// Same as PublicKeyCredentialDescriptor but which deserializes using references
 pub struct PublicKeyCredentialDescriptorRef<'a> {
-    pub id: &'a serde_bytes::Bytes,
+    #[serde(borrow)]
+    pub id: CredentialIdRef<'a>,
     #[serde(rename = "type")]
     pub key_type: &'a str,
-    // https://w3c.github.io/webauthn/#enumdef-authenticatortransport
-    // transports: ...
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_transports"
+    )]
+    pub transports: Option<Vec<AuthenticatorTransport, MAX_TRANSPORTS>>,
+}
+
+/// Deserializes a sequence of [`PublicKeyCredentialDescriptorRef`][]s without storing them and
+/// returns the number of entries.
+pub(crate) fn count_descriptors<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DescriptorCountVisitor;
+
+    impl<'de> Visitor<'de> for DescriptorCountVisitor {
+        type Value = usize;
+
+        fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a sequence of credential descriptors")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut len = 0;
+            while seq
+                .next_element::<PublicKeyCredentialDescriptorRef<'de>>()?
+                .is_some()
+            {
+                len += 1;
+            }
+            Ok(len)
+        }
+    }
+
+    deserializer.deserialize_seq(DescriptorCountVisitor)
+}
+
+/// A lazy iterator over a sequence of [`PublicKeyCredentialDescriptorRef`][]s in the raw
+/// parameters of a request.
+#[derive(Clone, Debug)]
+pub(crate) struct DescriptorIter<'a> {
+    entries: crate::cbor::ArrayIter<'a>,
+}
+
+impl<'a> DescriptorIter<'a> {
+    /// Creates an iterator over the array stored for `key` in the CBOR-encoded request
+    /// `parameters`.
+    ///
+    /// If the key is not present, the iterator is empty.  If the parameters are malformed,
+    /// [`Error::InvalidCbor`][crate::ctap2::Error::InvalidCbor] is returned.
+    pub(crate) fn new(parameters: &'a [u8], key: u64) -> Result<Self, crate::ctap2::Error> {
+        use crate::{cbor::ArrayIter, ctap2::Error};
+
+        let entries = match crate::cbor::map_value(parameters, key).ok_or(Error::InvalidCbor)? {
+            Some(array) => ArrayIter::new(array).ok_or(Error::InvalidCbor)?,
+            None => ArrayIter::empty(),
+        };
+        Ok(Self { entries })
+    }
+
+    /// Creates an iterator over the given CBOR array with `len` entries.
+    ///
+    /// An empty `array` is only accepted if `len` is zero, otherwise the raw array has not been
+    /// captured and [`Error::Other`][crate::ctap2::Error::Other] is returned.  If `array` is not a
+    /// well-formed array, [`Error::InvalidCbor`][crate::ctap2::Error::InvalidCbor] is returned.
+    pub(crate) fn from_array(array: &'a [u8], len: usize) -> Result<Self, crate::ctap2::Error> {
+        use crate::{cbor::ArrayIter, ctap2::Error};
+
+        if array.is_empty() {
+            return if len == 0 {
+                Ok(Self {
+                    entries: ArrayIter::empty(),
+                })
+            } else {
+                Err(Error::Other)
+            };
+        }
+        let entries = ArrayIter::new(array).ok_or(Error::InvalidCbor)?;
+        Ok(Self { entries })
+    }
+}
+
+impl<'a> Iterator for DescriptorIter<'a> {
+    type Item = Result<PublicKeyCredentialDescriptorRef<'a>, crate::ctap2::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|entry| {
+            cbor_smol::cbor_deserialize(entry).map_err(|_| crate::ctap2::Error::InvalidCbor)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DescriptorIter<'_> {}
+
+/// The user verification requirement of a WebAuthn request.
+///
+/// See: https://w3c.github.io/webauthn/#enumdef-userverificationrequirement
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[serde(into = "&str", try_from = "&str")]
+pub enum UserVerificationRequirement {
+    Required,
+    #[default]
+    Preferred,
+    Discouraged,
+}
+
+impl UserVerificationRequirement {
+    const REQUIRED: &'static str = "required";
+    const PREFERRED: &'static str = "preferred";
+    const DISCOURAGED: &'static str = "discouraged";
+}
+
+impl From<UserVerificationRequirement> for &str {
+    fn from(requirement: UserVerificationRequirement) -> Self {
+        match requirement {
+            UserVerificationRequirement::Required => UserVerificationRequirement::REQUIRED,
+            UserVerificationRequirement::Preferred => UserVerificationRequirement::PREFERRED,
+            UserVerificationRequirement::Discouraged => UserVerificationRequirement::DISCOURAGED,
+        }
+    }
+}
+
+impl TryFrom<&str> for UserVerificationRequirement {
+    type Error = TryFromStrError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            Self::REQUIRED => Ok(Self::Required),
+            Self::PREFERRED => Ok(Self::Preferred),
+            Self::DISCOURAGED => Ok(Self::Discouraged),
+            _ => Err(TryFromStrError),
+        }
+    }
+}
+
+/// The resident key requirement of a WebAuthn registration request.
+///
+/// See: https://w3c.github.io/webauthn/#enumdef-residentkeyrequirement
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[serde(into = "&str", try_from = "&str")]
+pub enum ResidentKeyRequirement {
+    Discouraged,
+    Preferred,
+    Required,
+}
+
+impl ResidentKeyRequirement {
+    const DISCOURAGED: &'static str = "discouraged";
+    const PREFERRED: &'static str = "preferred";
+    const REQUIRED: &'static str = "required";
+
+    /// Returns the requirement that applies if the `residentKey` member is not set, based on the
+    /// deprecated `requireResidentKey` member.
+    pub fn from_require_resident_key(require_resident_key: bool) -> Self {
+        if require_resident_key {
+            Self::Required
+        } else {
+            Self::Discouraged
+        }
+    }
+}
+
+impl From<ResidentKeyRequirement> for &str {
+    fn from(requirement: ResidentKeyRequirement) -> Self {
+        match requirement {
+            ResidentKeyRequirement::Discouraged => ResidentKeyRequirement::DISCOURAGED,
+            ResidentKeyRequirement::Preferred => ResidentKeyRequirement::PREFERRED,
+            ResidentKeyRequirement::Required => ResidentKeyRequirement::REQUIRED,
+        }
+    }
+}
+
+impl TryFrom<&str> for ResidentKeyRequirement {
+    type Error = TryFromStrError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            Self::DISCOURAGED => Ok(Self::Discouraged),
+            Self::PREFERRED => Ok(Self::Preferred),
+            Self::REQUIRED => Ok(Self::Required),
+            _ => Err(TryFromStrError),
+        }
+    }
+}
+
+/// The attestation conveyance preference of a WebAuthn registration request.
+///
+/// See: https://w3c.github.io/webauthn/#enumdef-attestationconveyancepreference
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+#[serde(into = "&str", try_from = "&str")]
+pub enum AttestationConveyancePreference {
+    #[default]
+    None,
+    Indirect,
+    Direct,
+    Enterprise,
+}
+
+impl AttestationConveyancePreference {
+    const NONE: &'static str = "none";
+    const INDIRECT: &'static str = "indirect";
+    const DIRECT: &'static str = "direct";
+    const ENTERPRISE: &'static str = "enterprise";
+}
+
+impl From<AttestationConveyancePreference> for &str {
+    fn from(preference: AttestationConveyancePreference) -> Self {
+        match preference {
+            AttestationConveyancePreference::None => AttestationConveyancePreference::NONE,
+            AttestationConveyancePreference::Indirect => AttestationConveyancePreference::INDIRECT,
+            AttestationConveyancePreference::Direct => AttestationConveyancePreference::DIRECT,
+            AttestationConveyancePreference::Enterprise => {
+                AttestationConveyancePreference::ENTERPRISE
+            }
+        }
+    }
+}
+
+impl TryFrom<&str> for AttestationConveyancePreference {
+    type Error = TryFromStrError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            Self::NONE => Ok(Self::None),
+            Self::INDIRECT => Ok(Self::Indirect),
+            Self::DIRECT => Ok(Self::Direct),
+            Self::ENTERPRISE => Ok(Self::Enterprise),
+            _ => Err(TryFromStrError),
+        }
+    }
+}
+
+/// The client extension output of the `credProps` extension.
+///
+/// See: https://w3c.github.io/webauthn/#sctn-authenticator-credential-properties-extension
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CredentialPropertiesOutput {
+    /// Whether the created credential is a discoverable credential.
+    pub rk: bool,
+}
+
+impl CredentialPropertiesOutput {
+    pub fn new(rk: bool) -> Self {
+        Self { rk }
+    }
+
+    /// Derives the output from the options of a makeCredential request.
+    ///
+    /// Authenticators create a discoverable credential if and only if the `rk` option is set.
+    pub fn from_options(options: Option<&crate::ctap2::AuthenticatorOptions>) -> Self {
+        let rk = options.and_then(|options| options.rk).unwrap_or_default();
+        Self::new(rk)
+    }
+}
+
+impl From<bool> for CredentialPropertiesOutput {
+    fn from(rk: bool) -> Self {
+        Self::new(rk)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_user_handle() {
+        let user_handle = UserHandle::from_slice(&[0xaa; 16]).unwrap();
+        assert_eq!(user_handle.as_bytes(), &[0xaa; 16]);
+        assert_eq!(
+            UserHandle::from_slice(&[]),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+        assert_eq!(
+            UserHandle::from_slice(&[0xaa; 65]),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+        assert!(UserHandle::from_slice(&[0xaa; 64]).is_ok());
+
+        // the content of the user handle is not shown
+        assert_eq!(std::format!("{:?}", user_handle), "UserHandle(<16 bytes>)");
+
+        let mut buffer = [0; 32];
+        let serialized = cbor_smol::cbor_serialize(&user_handle, &mut buffer).unwrap();
+        assert_eq!(serialized[0], 0x50);
+        let deserialized: UserHandle = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, user_handle);
+
+        // empty user handles are rejected at parse time
+        assert!(cbor_smol::cbor_deserialize::<UserHandle>(&[0x40]).is_err());
+        assert!(
+            cbor_smol::cbor_deserialize::<PublicKeyCredentialUserEntity>(b"\xa1bid\x40").is_err()
+        );
+        let user: PublicKeyCredentialUserEntity =
+            cbor_smol::cbor_deserialize(b"\xa1bid\x41\x01").unwrap();
+        assert_eq!(
+            user,
+            PublicKeyCredentialUserEntity::from(UserHandle::from_slice(&[0x01]).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_filtered_public_key_credential_parameters() {
+        let parameters: heapless::Vec<PublicKeyCredentialParameters, 5> = [
+            PublicKeyCredentialParameters::public_key_with_alg(RS256),
+            PublicKeyCredentialParameters::public_key_with_alg(ES256),
+            PublicKeyCredentialParameters {
+                alg: ED_DSA,
+                key_type: "foo".into(),
+            },
+            PublicKeyCredentialParameters::public_key_with_alg(ES256),
+            PublicKeyCredentialParameters::public_key_with_alg(ES384),
+        ]
+        .into_iter()
+        .collect();
+        let mut buffer = [0; 256];
+        let serialized = cbor_smol::cbor_serialize(&parameters, &mut buffer).unwrap();
+        let filtered: FilteredPublicKeyCredentialParameters =
+            cbor_smol::cbor_deserialize(serialized).unwrap();
+
+        // unknown types and duplicates are dropped, the order is kept
+        let algs: heapless::Vec<i32, 5> = filtered.algorithms().collect();
+        assert_eq!(algs, [RS256, ES256, ES384]);
+        assert!(filtered.had_unknown());
+        assert!(!filtered.truncated());
+        let algs: heapless::Vec<i32, 5> = filtered.filter(&[ES384, ES256]).map(|p| p.alg).collect();
+        assert_eq!(algs, [ES256, ES384]);
+
+        assert_eq!(filtered.select(&KNOWN_ALGS), Some(ES256));
+        assert_eq!(filtered.select(&[ES384, RS256]), Some(RS256));
+        assert_eq!(filtered.select(&[ES384]), Some(ES384));
+        assert_eq!(filtered.select(&[ED_DSA]), None);
+
+        // duplicates are not unknown
+        let duplicates = [
+            parameters[1].clone(),
+            parameters[3].clone(),
+            parameters[0].clone(),
+        ];
+        let serialized = cbor_smol::cbor_serialize(&duplicates, &mut buffer).unwrap();
+        let filtered: FilteredPublicKeyCredentialParameters =
+            cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert!(!filtered.had_unknown());
+        assert!(!filtered.truncated());
+        let algs: heapless::Vec<i32, 5> = filtered.algorithms().collect();
+        assert_eq!(algs, [ES256, RS256]);
+        // too many entries are truncated
+        let parameters: heapless::Vec<PublicKeyCredentialParameters, 12> = (0..12)
+            .map(|alg| PublicKeyCredentialParameters::public_key_with_alg(-alg))
+            .collect();
+        let mut buffer = [0; 512];
+        let serialized = cbor_smol::cbor_serialize(&parameters, &mut buffer).unwrap();
+        let filtered: FilteredPublicKeyCredentialParameters =
+            cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert!(!filtered.had_unknown());
+        assert!(filtered.truncated());
+        assert_eq!(
+            filtered.parameters().len(),
+            MAX_PUBLIC_KEY_CREDENTIAL_PARAMETERS
+        );
+        assert_eq!(filtered.parameters()[0].alg, 0);
+    }
+
+    #[test]
+    fn test_descriptor_transports() {
+        let data = b"\xa3bidA\x01dtypejpublic-keyjtransports\x84cusbcfoofhybridcusb";
+        let descriptor: PublicKeyCredentialDescriptorRef<'_> =
+            cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(descriptor.id.as_bytes(), &[0x01]);
+        // unknown transports and duplicates are skipped
+        assert_eq!(
+            descriptor.transports.as_deref(),
+            Some([AuthenticatorTransport::Usb, AuthenticatorTransport::Hybrid].as_slice())
+        );
+        let owned: PublicKeyCredentialDescriptor = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(owned.transports, descriptor.transports);
+
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&owned, &mut buffer).unwrap();
+        assert_eq!(
+            serialized,
+            b"\xa3bidA\x01dtypejpublic-keyjtransports\x82cusbfhybrid"
+        );
+
+        let data = b"\xa2bidA\x01dtypejpublic-key";
+        let descriptor: PublicKeyCredentialDescriptorRef<'_> =
+            cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(descriptor.transports, None);
+        let serialized = cbor_smol::cbor_serialize(&descriptor, &mut buffer).unwrap();
+        assert_eq!(serialized, data);
+    }
+
+    #[test]
+    fn test_webauthn_options() {
+        let mut buffer = [0; 16];
+        for (requirement, data) in [
+            (
+                UserVerificationRequirement::Required,
+                b"hrequired".as_slice(),
+            ),
+            (UserVerificationRequirement::Preferred, b"ipreferred"),
+            (UserVerificationRequirement::Discouraged, b"kdiscouraged"),
+        ] {
+            let serialized = cbor_smol::cbor_serialize(&requirement, &mut buffer).unwrap();
+            assert_eq!(serialized, data);
+            let deserialized: UserVerificationRequirement =
+                cbor_smol::cbor_deserialize(data).unwrap();
+            assert_eq!(deserialized, requirement);
+        }
+        for (requirement, data) in [
+            (
+                ResidentKeyRequirement::Discouraged,
+                b"kdiscouraged".as_slice(),
+            ),
+            (ResidentKeyRequirement::Preferred, b"ipreferred"),
+            (ResidentKeyRequirement::Required, b"hrequired"),
+        ] {
+            let serialized = cbor_smol::cbor_serialize(&requirement, &mut buffer).unwrap();
+            assert_eq!(serialized, data);
+            let deserialized: ResidentKeyRequirement = cbor_smol::cbor_deserialize(data).unwrap();
+            assert_eq!(deserialized, requirement);
+        }
+        for (preference, data) in [
+            (AttestationConveyancePreference::None, b"dnone".as_slice()),
+            (AttestationConveyancePreference::Indirect, b"hindirect"),
+            (AttestationConveyancePreference::Direct, b"fdirect"),
+            (AttestationConveyancePreference::Enterprise, b"jenterprise"),
+        ] {
+            let serialized = cbor_smol::cbor_serialize(&preference, &mut buffer).unwrap();
+            assert_eq!(serialized, data);
+            let deserialized: AttestationConveyancePreference =
+                cbor_smol::cbor_deserialize(data).unwrap();
+            assert_eq!(deserialized, preference);
+        }
+
+        assert!(UserVerificationRequirement::try_from("Required").is_err());
+        assert!(ResidentKeyRequirement::try_from("").is_err());
+        assert!(AttestationConveyancePreference::try_from("indirect ").is_err());
+
+        assert_eq!(
+            UserVerificationRequirement::default(),
+            UserVerificationRequirement::Preferred
+        );
+        assert_eq!(
+            AttestationConveyancePreference::default(),
+            AttestationConveyancePreference::None
+        );
+        assert_eq!(
+            ResidentKeyRequirement::from_require_resident_key(true),
+            ResidentKeyRequirement::Required
+        );
+        assert_eq!(
+            ResidentKeyRequirement::from_require_resident_key(false),
+            ResidentKeyRequirement::Discouraged
+        );
+    }
+
+    #[test]
+    fn test_cred_props() {
+        let mut buffer = [0; 16];
+        let output = CredentialPropertiesOutput::new(true);
+        let serialized = cbor_smol::cbor_serialize(&output, &mut buffer).unwrap();
+        assert_eq!(serialized, b"\xa1brk\xf5");
+        let deserialized: CredentialPropertiesOutput =
+            cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, output);
+
+        assert_eq!(
+            CredentialPropertiesOutput::from_options(None),
+            CredentialPropertiesOutput::new(false)
+        );
+        let mut options = crate::ctap2::AuthenticatorOptions {
+            rk: None,
+            up: None,
+            uv: Some(true),
+        };
+        assert!(!CredentialPropertiesOutput::from_options(Some(&options)).rk);
+        options.rk = Some(true);
+        assert!(CredentialPropertiesOutput::from_options(Some(&options)).rk);
+    }
+
+    #[test]
+    fn test_rp_entity_ref() {
+        let data = b"\xa3bidkexample.comdnamegExampledicon`";
+        let rp: PublicKeyCredentialRpEntityRef<'_> = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(rp.id, "example.com");
+        assert_eq!(rp.name, Some("Example"));
+        assert_eq!(rp.icon, Some(Icon));
+        // the strings are borrowed from the request
+        assert_eq!(rp.id.as_ptr(), data[4..].as_ptr());
+
+        let owned: PublicKeyCredentialRpEntity = rp.try_into().unwrap();
+        assert_eq!(owned.id, "example.com");
+        assert_eq!(owned.name.as_deref(), Some("Example"));
+        assert_eq!(PublicKeyCredentialRpEntityRef::from(&owned), rp);
+
+        // the icon is not serialized
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&rp, &mut buffer).unwrap();
+        assert_eq!(serialized, b"\xa2bidkexample.comdnamegExample");
+
+        let id = [b'a'; 257];
+        let rp = PublicKeyCredentialRpEntityRef {
+            id: core::str::from_utf8(&id).unwrap(),
+            name: None,
+            icon: None,
+        };
+        let owned: Result<PublicKeyCredentialRpEntity, _> = rp.try_into();
+        assert_eq!(owned, Err(crate::ctap2::Error::InvalidLength));
+        let rp = PublicKeyCredentialRpEntityRef {
+            id: core::str::from_utf8(&id[..256]).unwrap(),
+            ..rp
+        };
+        let owned: Result<PublicKeyCredentialRpEntity, _> = rp.try_into();
+        assert!(owned.is_ok());
+    }
+
+    #[test]
+    fn test_entity_length_limits() {
+        let data = b"\xa2bidkexample.comdnamex\x48aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let rp: PublicKeyCredentialRpEntity = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(rp.name.unwrap().len(), 64);
+        let rp: PublicKeyCredentialRpEntity<72> = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(rp.name.unwrap().len(), 72);
+        let rp: PublicKeyCredentialRpEntity<16> = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(rp.name.unwrap().len(), 16);
+
+        let data = b"\xa3bidA\x01dnamex\x48aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaadiconcabc";
+        let user: PublicKeyCredentialUserEntity = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(user.name.unwrap().len(), 64);
+        assert_eq!(user.icon.as_deref(), Some("abc"));
+        let user: PublicKeyCredentialUserEntity<128, 2> =
+            cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(user.name.unwrap().len(), 72);
+        assert_eq!(user.icon, None);
+    }
+
+    #[test]
+    fn test_string_length_policy() {
+        let name = [b'a'; 80];
+        let name = core::str::from_utf8(&name).unwrap();
+        let user = PublicKeyCredentialUserEntityRef {
+            id: serde_bytes::Bytes::new(&[0x01]),
+            icon: Some(name),
+            name: Some(name),
+            display_name: Some("Joe"),
+        };
+
+        let owned: PublicKeyCredentialUserEntity =
+            PublicKeyCredentialUserEntity::from_ref(user, StringLengthPolicy::Truncate).unwrap();
+        assert_eq!(owned.name.as_deref(), Some(&name[..64]));
+        assert_eq!(owned.display_name.as_deref(), Some("Joe"));
+        assert_eq!(owned.icon, None);
+        let owned: PublicKeyCredentialUserEntity =
+            PublicKeyCredentialUserEntity::from_ref(user, StringLengthPolicy::Skip).unwrap();
+        assert_eq!(owned.name, None);
+        assert_eq!(owned.display_name.as_deref(), Some("Joe"));
+        assert_eq!(owned.icon.as_deref(), Some(name));
+        let owned: Result<PublicKeyCredentialUserEntity, _> =
+            PublicKeyCredentialUserEntity::from_ref(user, StringLengthPolicy::Reject);
+        assert_eq!(owned, Err(crate::ctap2::Error::InvalidLength));
+        let owned: PublicKeyCredentialUserEntity<80> =
+            PublicKeyCredentialUserEntity::from_ref(user, StringLengthPolicy::Reject).unwrap();
+        assert_eq!(owned.name.as_deref(), Some(name));
+
+        let rp = PublicKeyCredentialRpEntityRef {
+            id: "example.com",
+            name: Some(name),
+            icon: None,
+        };
+        let owned: PublicKeyCredentialRpEntity =
+            PublicKeyCredentialRpEntity::from_ref(rp, StringLengthPolicy::Truncate).unwrap();
+        assert_eq!(owned.name.as_deref(), Some(&name[..64]));
+        let owned: PublicKeyCredentialRpEntity =
+            PublicKeyCredentialRpEntity::from_ref(rp, StringLengthPolicy::Skip).unwrap();
+        assert_eq!(owned.name, None);
+        let owned: Result<PublicKeyCredentialRpEntity, _> =
+            PublicKeyCredentialRpEntity::from_ref(rp, StringLengthPolicy::Reject);
+        assert_eq!(owned, Err(crate::ctap2::Error::InvalidLength));
+    }
+
+    #[test]
+    fn test_user_entity_ref() {
+        let data = b"\xa3bidA\x01dnameuaaaaaaaaaaaaaaaaaaaaakdisplayNamecJoe";
+        let user: PublicKeyCredentialUserEntityRef<'_> = cbor_smol::cbor_deserialize(data).unwrap();
+        assert_eq!(user.id.as_ref(), &[0x01]);
+        assert_eq!(user.name, Some("aaaaaaaaaaaaaaaaaaaaa"));
+        assert_eq!(user.display_name, Some("Joe"));
+        assert_eq!(user.icon, None);
+        // the strings are borrowed from the request
+        assert_eq!(
+            user.display_name.unwrap().as_ptr(),
+            data[data.len() - 3..].as_ptr()
+        );
+
+        let owned: PublicKeyCredentialUserEntity = user.try_into().unwrap();
+        assert_eq!(owned.id.as_bytes(), &[0x01]);
+        assert_eq!(owned.name.as_deref(), Some("aaaaaaaaaaaaaaaaaaaaa"));
+        assert_eq!(PublicKeyCredentialUserEntityRef::from(&owned), user);
+
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&user, &mut buffer).unwrap();
+        assert_eq!(serialized, data);
+
+        // long names are truncated when converting to the owned type
+        let name = [b'a'; 80];
+        let user = PublicKeyCredentialUserEntityRef {
+            id: serde_bytes::Bytes::new(&[0x01]),
+            icon: None,
+            name: Some(core::str::from_utf8(&name).unwrap()),
+            display_name: None,
+        };
+        let owned: PublicKeyCredentialUserEntity = user.try_into().unwrap();
+        assert_eq!(owned.name.unwrap().len(), 64);
+        let owned: PublicKeyCredentialUserEntity<128> = user.try_into().unwrap();
+        assert_eq!(owned.name.unwrap().len(), 80);
+
+        // empty user handles are rejected
+        assert!(
+            cbor_smol::cbor_deserialize::<PublicKeyCredentialUserEntityRef<'_>>(b"\xa1bid\x40")
+                .is_err()
+        );
+        assert_eq!(
+            std::format!("{:?}", user),
+            "PublicKeyCredentialUserEntityRef { id: <1 bytes>, icon: None, name: Some(\"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\"), display_name: None }"
+        );
+    }
+
+    #[cfg(feature = "base64url")]
+    #[test]
+    fn test_base64url() {
+        let id = CredentialId::<16>::from_slice(&[0xfb, 0xff, 0xfe, 0x01]).unwrap();
+        let encoded: String<8> = id.to_base64url().unwrap();
+        assert_eq!(encoded, "-__-AQ");
+        assert_eq!(CredentialId::<16>::from_base64url(&encoded), Ok(id));
+        assert_eq!(
+            CredentialId::<2>::from_base64url(&encoded),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+        assert_eq!(
+            id.to_base64url::<4>(),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+
+        let user_handle = UserHandle::from_slice(b"foo").unwrap();
+        let encoded: String<4> = user_handle.to_base64url().unwrap();
+        assert_eq!(encoded, "Zm9v");
+        assert_eq!(UserHandle::from_base64url(&encoded), Ok(user_handle));
+        assert_eq!(
+            UserHandle::from_base64url(""),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn test_hashes() {
+        let rp_id_hash = RpIdHash::new([0xaa; 32]);
+        let mut buffer = [0; 64];
+        let serialized = cbor_smol::cbor_serialize(&rp_id_hash, &mut buffer).unwrap();
+        assert_eq!(serialized[..2], [0x58, 0x20]);
+        assert_eq!(serialized[2..], [0xaa; 32]);
+        let deserialized: RpIdHash = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, rp_id_hash);
+        let deserialized: &RpIdHash = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, &rp_id_hash);
+
+        let client_data_hash: ClientDataHash = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(client_data_hash.as_bytes(), rp_id_hash.as_bytes());
+
+        // only 32 byte strings are accepted
+        assert!(cbor_smol::cbor_deserialize::<RpIdHash>(&serialized[..33]).is_err());
+        assert_eq!(
+            RpIdHash::try_from(&[0xaa; 31][..]),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+        assert_eq!(RpIdHash::try_from(&[0xaa; 32][..]), Ok(rp_id_hash));
+    }
+
+    #[test]
+    fn test_credential_id() {
+        let id = CredentialId::<16>::from_slice(&[0xc1; 16]).unwrap();
+        assert_eq!(id.as_bytes(), &[0xc1; 16]);
+        assert_eq!(
+            CredentialId::<16>::from_slice(&[0xc1; 17]),
+            Err(crate::ctap2::Error::InvalidLength)
+        );
+
+        let mut buffer = [0; 32];
+        let serialized = cbor_smol::cbor_serialize(&id, &mut buffer).unwrap();
+        assert_eq!(serialized[0], 0x50);
+        let deserialized: CredentialId<16> = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(deserialized, id);
+        assert!(cbor_smol::cbor_deserialize::<CredentialId<8>>(serialized).is_err());
+
+        // borrowed credential IDs are not copied
+        let id_ref: CredentialIdRef<'_> = cbor_smol::cbor_deserialize(serialized).unwrap();
+        assert_eq!(id_ref, id.as_id_ref());
+        assert_eq!(id_ref.as_bytes().as_ptr(), serialized[1..].as_ptr());
+        assert_eq!(CredentialId::try_from(id_ref), Ok(id));
+    }
+
     #[test]
     fn test_truncate() {
         // Example from § 6.4.1 String Truncation in the Webauthn spec
@@ -0,0 +1,462 @@
+//! The client data collected by WebAuthn clients, see
+//! https://w3c.github.io/webauthn/#dictionary-client-data
+//!
+//! This module contains a minimal JSON parser and serializer for the client data JSON that does
+//! not require an allocator.  Strings are borrowed from the parsed data, so the members used by
+//! [`CollectedClientData`][] must not contain escape sequences.  Unknown members are skipped.
+
+use crate::{ctap2::Error, Bytes};
+
+/// The maximum nesting depth of skipped JSON values.
+const MAX_DEPTH: u8 = 8;
+
+/// The `type` member of the client data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ClientDataType {
+    /// `webauthn.create`, used for registrations.
+    Create,
+    /// `webauthn.get`, used for authentications.
+    Get,
+}
+
+impl ClientDataType {
+    const CREATE: &'static str = "webauthn.create";
+    const GET: &'static str = "webauthn.get";
+}
+
+impl From<ClientDataType> for &str {
+    fn from(client_data_type: ClientDataType) -> Self {
+        match client_data_type {
+            ClientDataType::Create => ClientDataType::CREATE,
+            ClientDataType::Get => ClientDataType::GET,
+        }
+    }
+}
+
+impl TryFrom<&str> for ClientDataType {
+    type Error = crate::TryFromStrError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            Self::CREATE => Ok(Self::Create),
+            Self::GET => Ok(Self::Get),
+            _ => Err(crate::TryFromStrError),
+        }
+    }
+}
+
+/// The client data that is hashed to the client data hash of a request.
+///
+/// `challenge` is the base64url-encoded challenge as it appears in the JSON data.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct CollectedClientData<'a> {
+    pub client_data_type: ClientDataType,
+    pub challenge: &'a str,
+    pub origin: &'a str,
+    pub cross_origin: Option<bool>,
+}
+
+impl<'a> CollectedClientData<'a> {
+    pub fn new(client_data_type: ClientDataType, challenge: &'a str, origin: &'a str) -> Self {
+        Self {
+            client_data_type,
+            challenge,
+            origin,
+            cross_origin: None,
+        }
+    }
+
+    /// Parses the client data JSON.
+    ///
+    /// - [`Error::InvalidParameter`][] is returned if the data is not a JSON object, if a member
+    ///   has an unexpected type or value, if a member occurs more than once or if one of the
+    ///   parsed strings contains an escape sequence.
+    /// - [`Error::MissingParameter`][] is returned if `type`, `challenge` or `origin` is missing.
+    pub fn parse(json: &'a [u8]) -> Result<Self, Error> {
+        let mut parser = Parser { data: json };
+        let mut client_data_type = None;
+        let mut challenge = None;
+        let mut origin = None;
+        let mut cross_origin = None;
+
+        parser.expect(b'{')?;
+        if !parser.consume(b'}') {
+            loop {
+                let (key, _) = parser.string()?;
+                parser.expect(b':')?;
+                match key {
+                    b"type" => {
+                        let value = parser.str()?;
+                        let value =
+                            ClientDataType::try_from(value).map_err(|_| Error::InvalidParameter)?;
+                        set_once(&mut client_data_type, value)?;
+                    }
+                    b"challenge" => set_once(&mut challenge, parser.str()?)?,
+                    b"origin" => set_once(&mut origin, parser.str()?)?,
+                    b"crossOrigin" => set_once(&mut cross_origin, parser.bool()?)?,
+                    _ => parser.skip_value(0)?,
+                }
+                if !parser.consume(b',') {
+                    parser.expect(b'}')?;
+                    break;
+                }
+            }
+        }
+        parser.skip_whitespace();
+        if !parser.data.is_empty() {
+            return Err(Error::InvalidParameter);
+        }
+
+        Ok(Self {
+            client_data_type: client_data_type.ok_or(Error::MissingParameter)?,
+            challenge: challenge.ok_or(Error::MissingParameter)?,
+            origin: origin.ok_or(Error::MissingParameter)?,
+            cross_origin,
+        })
+    }
+
+    /// Serializes the client data JSON by appending it to the given buffer.
+    ///
+    /// The members are written in the order used by the WebAuthn specification, i.e. `type`,
+    /// `challenge`, `origin` and `crossOrigin`.  Returns the number of bytes written.  If the
+    /// buffer is too small, [`Error::InvalidLength`][] is returned and the buffer is restored to
+    /// its previous length.
+    pub fn serialize_into<const N: usize>(&self, buffer: &mut Bytes<N>) -> Result<usize, Error> {
+        let start = buffer.len();
+        let result = self.write(&mut |data| {
+            buffer
+                .extend_from_slice(data)
+                .map_err(|_| Error::InvalidLength)
+        });
+        if let Err(err) = result {
+            buffer.truncate(start);
+            return Err(err);
+        }
+        Ok(buffer.len() - start)
+    }
+
+    /// Computes the client data hash of the serialized client data JSON.
+    ///
+    /// If the client data has been parsed, the hash has to be computed from the original data
+    /// using [`ClientDataHash::compute`][super::ClientDataHash::compute] instead.
+    #[cfg(feature = "sha2")]
+    pub fn hash(&self) -> super::ClientDataHash {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        // cannot fail as the writer does not return errors
+        self.write(&mut |data| {
+            hasher.update(data);
+            Ok(())
+        })
+        .ok();
+        super::ClientDataHash::new(hasher.finalize().into())
+    }
+
+    fn write(&self, out: &mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+        out(b"{\"type\":")?;
+        write_string(out, self.client_data_type.into())?;
+        out(b",\"challenge\":")?;
+        write_string(out, self.challenge)?;
+        out(b",\"origin\":")?;
+        write_string(out, self.origin)?;
+        if let Some(cross_origin) = self.cross_origin {
+            out(b",\"crossOrigin\":")?;
+            out(if cross_origin { b"true" } else { b"false" })?;
+        }
+        out(b"}")
+    }
+}
+
+fn set_once<T>(member: &mut Option<T>, value: T) -> Result<(), Error> {
+    if member.is_some() {
+        return Err(Error::InvalidParameter);
+    }
+    *member = Some(value);
+    Ok(())
+}
+
+fn write_string(out: &mut dyn FnMut(&[u8]) -> Result<(), Error>, s: &str) -> Result<(), Error> {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    out(b"\"")?;
+    let mut unescaped = 0;
+    for (i, c) in s.bytes().enumerate() {
+        if c == b'"' || c == b'\\' || c < 0x20 {
+            out(&s.as_bytes()[unescaped..i])?;
+            match c {
+                b'"' => out(b"\\\"")?,
+                b'\\' => out(b"\\\\")?,
+                _ => out(&[
+                    b'\\',
+                    b'u',
+                    b'0',
+                    b'0',
+                    HEX[usize::from(c >> 4)],
+                    HEX[usize::from(c & 0xf)],
+                ])?,
+            }
+            unescaped = i + 1;
+        }
+    }
+    out(&s.as_bytes()[unescaped..])?;
+    out(b"\"")
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let n = self
+            .data
+            .iter()
+            .position(|c| !matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+            .unwrap_or(self.data.len());
+        self.data = &self.data[n..];
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.first().copied()
+    }
+
+    fn consume(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.data = &self.data[1..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), Error> {
+        if self.consume(c) {
+            Ok(())
+        } else {
+            Err(Error::InvalidParameter)
+        }
+    }
+
+    fn literal(&mut self, literal: &[u8]) -> Result<(), Error> {
+        self.skip_whitespace();
+        if let Some(rest) = self.data.strip_prefix(literal) {
+            self.data = rest;
+            Ok(())
+        } else {
+            Err(Error::InvalidParameter)
+        }
+    }
+
+    /// Parses a string and returns its raw content and whether it contains escape sequences.
+    fn string(&mut self) -> Result<(&'a [u8], bool), Error> {
+        self.expect(b'"')?;
+        let data = self.data;
+        let mut escaped = false;
+        let mut i = 0;
+        loop {
+            match data.get(i) {
+                None => return Err(Error::InvalidParameter),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    escaped = true;
+                    let n = match data.get(i + 1) {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => 2,
+                        Some(b'u') => {
+                            let hex = data.get(i + 2..i + 6).ok_or(Error::InvalidParameter)?;
+                            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                                return Err(Error::InvalidParameter);
+                            }
+                            6
+                        }
+                        _ => return Err(Error::InvalidParameter),
+                    };
+                    i += n;
+                }
+                Some(c) if *c < 0x20 => return Err(Error::InvalidParameter),
+                Some(_) => i += 1,
+            }
+        }
+        self.data = &data[i + 1..];
+        Ok((&data[..i], escaped))
+    }
+
+    /// Parses a string without escape sequences.
+    fn str(&mut self) -> Result<&'a str, Error> {
+        let (s, escaped) = self.string()?;
+        if escaped {
+            return Err(Error::InvalidParameter);
+        }
+        core::str::from_utf8(s).map_err(|_| Error::InvalidParameter)
+    }
+
+    fn bool(&mut self) -> Result<bool, Error> {
+        match self.peek() {
+            Some(b't') => self.literal(b"true").map(|_| true),
+            Some(b'f') => self.literal(b"false").map(|_| false),
+            _ => Err(Error::InvalidParameter),
+        }
+    }
+
+    fn number(&mut self) -> Result<(), Error> {
+        let n = self
+            .data
+            .iter()
+            .position(|c| !matches!(c, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+            .unwrap_or(self.data.len());
+        if n == 0 {
+            return Err(Error::InvalidParameter);
+        }
+        self.data = &self.data[n..];
+        Ok(())
+    }
+
+    fn skip_value(&mut self, depth: u8) -> Result<(), Error> {
+        if depth >= MAX_DEPTH {
+            return Err(Error::InvalidParameter);
+        }
+        match self.peek() {
+            Some(b'"') => self.string().map(|_| ()),
+            Some(b't') => self.literal(b"true"),
+            Some(b'f') => self.literal(b"false"),
+            Some(b'n') => self.literal(b"null"),
+            Some(b'{') => {
+                self.expect(b'{')?;
+                if self.consume(b'}') {
+                    return Ok(());
+                }
+                loop {
+                    self.string()?;
+                    self.expect(b':')?;
+                    self.skip_value(depth + 1)?;
+                    if !self.consume(b',') {
+                        return self.expect(b'}');
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.expect(b'[')?;
+                if self.consume(b']') {
+                    return Ok(());
+                }
+                loop {
+                    self.skip_value(depth + 1)?;
+                    if !self.consume(b',') {
+                        return self.expect(b']');
+                    }
+                }
+            }
+            Some(_) => self.number(),
+            None => Err(Error::InvalidParameter),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let json = br#"{"type":"webauthn.get","challenge":"AAECAw","origin":"https://example.com","crossOrigin":false,"topOrigin":"https://example.org","tokenBinding":{"status":"present","id":"a\"b"},"other_keys_can_be_added_here":["do not compare clientDataJSON against a template. See https://goo.gl/yabPex", 1.5e3, null, true]}"#;
+        let client_data = CollectedClientData::parse(json).unwrap();
+        assert_eq!(client_data.client_data_type, ClientDataType::Get);
+        assert_eq!(client_data.challenge, "AAECAw");
+        assert_eq!(client_data.origin, "https://example.com");
+        assert_eq!(client_data.cross_origin, Some(false));
+
+        let json = b" { \"origin\" : \"https://example.com\" ,\n\t\"challenge\": \"AA\", \"type\": \"webauthn.create\" } ";
+        let client_data = CollectedClientData::parse(json).unwrap();
+        assert_eq!(
+            client_data,
+            CollectedClientData::new(ClientDataType::Create, "AA", "https://example.com")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let invalid: &[(&[u8], Error)] = &[
+            (b"", Error::InvalidParameter),
+            (b"[]", Error::InvalidParameter),
+            (b"{}", Error::MissingParameter),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA"}"#,
+                Error::MissingParameter,
+            ),
+            (
+                br#"{"type":"payment.get","challenge":"AA","origin":"o"}"#,
+                Error::InvalidParameter,
+            ),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA","origin":"o\/"}"#,
+                Error::InvalidParameter,
+            ),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA","origin":"o","origin":"o"}"#,
+                Error::InvalidParameter,
+            ),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA","origin":"o","crossOrigin":0}"#,
+                Error::InvalidParameter,
+            ),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA","origin":"o"} {}"#,
+                Error::InvalidParameter,
+            ),
+            (
+                br#"{"type":"webauthn.get","challenge":"AA","origin":"o","x":[[[[[[[[[]]]]]]]]]}"#,
+                Error::InvalidParameter,
+            ),
+            (br#"{"type":"webauthn.get","#, Error::InvalidParameter),
+        ];
+        for (json, error) in invalid {
+            assert_eq!(CollectedClientData::parse(json), Err(*error));
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let mut client_data =
+            CollectedClientData::new(ClientDataType::Create, "AAECAw", "https://example.com");
+        let mut buffer = Bytes::<128>::new();
+        let n = client_data.serialize_into(&mut buffer).unwrap();
+        assert_eq!(n, buffer.len());
+        assert_eq!(
+            buffer.as_slice(),
+            br#"{"type":"webauthn.create","challenge":"AAECAw","origin":"https://example.com"}"#
+        );
+        assert_eq!(CollectedClientData::parse(&buffer), Ok(client_data));
+
+        client_data.cross_origin = Some(true);
+        client_data.origin = "a\"b\\c\n";
+        buffer.clear();
+        client_data.serialize_into(&mut buffer).unwrap();
+        assert_eq!(
+            buffer.as_slice(),
+            br#"{"type":"webauthn.create","challenge":"AAECAw","origin":"a\"b\\c\u000a","crossOrigin":true}"#
+        );
+
+        let mut buffer = Bytes::<16>::from_slice(b"abc").unwrap();
+        assert_eq!(
+            client_data.serialize_into(&mut buffer),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(buffer.as_slice(), b"abc");
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_hash() {
+        let client_data =
+            CollectedClientData::new(ClientDataType::Get, "AAECAw", "https://example.com");
+        let mut buffer = Bytes::<128>::new();
+        client_data.serialize_into(&mut buffer).unwrap();
+        assert_eq!(
+            client_data.hash(),
+            crate::webauthn::ClientDataHash::compute(&buffer)
+        );
+    }
+}